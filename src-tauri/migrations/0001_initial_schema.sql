@@ -0,0 +1,202 @@
+-- 0001_initial_schema
+--
+-- Base schema for ZeNote: users, pages, blocks, embeddings and the
+-- match_embeddings RPC used for semantic search, plus the migration
+-- bookkeeping used by the `check_schema` command.
+--
+-- This file is safe to run against a database that already has the tables:
+-- every statement is idempotent. Run it once by hand (Supabase SQL editor or
+-- `psql`) to bootstrap a new project; later migrations are applied by the app.
+
+create extension if not exists vector with schema extensions;
+
+-- ---------------------------------------------------------------------------
+-- Migration bookkeeping
+-- ---------------------------------------------------------------------------
+
+create table if not exists public.schema_migrations (
+    version integer primary key,
+    name text not null,
+    applied_at timestamptz not null default now()
+);
+
+-- Executes a migration script and records it. Only the service role may call
+-- this; the app uses SUPABASE_SERVICE_ROLE_KEY when applying migrations.
+create or replace function public.apply_migration(p_version integer, p_name text, p_sql text)
+returns void
+language plpgsql
+security definer
+set search_path = public, extensions
+as $$
+begin
+    if exists (select 1 from public.schema_migrations where version = p_version) then
+        return;
+    end if;
+
+    execute p_sql;
+
+    insert into public.schema_migrations (version, name)
+    values (p_version, p_name)
+    on conflict (version) do nothing;
+end;
+$$;
+
+revoke all on function public.apply_migration(integer, text, text) from public, anon, authenticated;
+grant execute on function public.apply_migration(integer, text, text) to service_role;
+
+-- ---------------------------------------------------------------------------
+-- Tables
+-- ---------------------------------------------------------------------------
+
+-- Ids for users and blocks are random 64-bit integers generated client side
+-- by supabase_rs; page ids are UUIDs generated by the webview.
+create table if not exists public.users (
+    id bigint primary key,
+    created_at timestamptz not null default now(),
+    email text not null unique,
+    password text not null,
+    first_name text not null default '',
+    last_name text not null default '',
+    avatar_url text not null default ''
+);
+
+create table if not exists public.pages (
+    id uuid primary key,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz not null default now(),
+    user_id bigint not null references public.users (id) on delete cascade,
+    title text not null default '',
+    parent_page_id uuid references public.pages (id) on delete set null
+);
+
+create table if not exists public.blocks (
+    id bigint primary key,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz not null default now(),
+    type text not null default 'text',
+    "order" integer not null default 0,
+    content text not null default '',
+    page_id uuid not null references public.pages (id) on delete cascade,
+    parent_block_id bigint references public.blocks (id) on delete cascade
+);
+
+create table if not exists public.embeddings (
+    block_id text not null,
+    page_id uuid not null references public.pages (id) on delete cascade,
+    user_id bigint not null references public.users (id) on delete cascade,
+    content text not null,
+    embedding extensions.vector(1536) not null,
+    metadata jsonb not null default '{}'::jsonb,
+    created_at timestamptz not null default now(),
+    primary key (block_id, page_id)
+);
+
+-- ---------------------------------------------------------------------------
+-- Indexes
+-- ---------------------------------------------------------------------------
+
+create index if not exists pages_user_id_updated_at_idx on public.pages (user_id, updated_at desc);
+create index if not exists pages_parent_page_id_idx on public.pages (parent_page_id);
+create index if not exists blocks_page_id_order_idx on public.blocks (page_id, "order");
+create index if not exists blocks_parent_block_id_idx on public.blocks (parent_block_id);
+create index if not exists embeddings_user_id_idx on public.embeddings (user_id);
+create index if not exists embeddings_embedding_idx
+    on public.embeddings using hnsw (embedding extensions.vector_cosine_ops);
+
+-- ---------------------------------------------------------------------------
+-- Semantic search
+-- ---------------------------------------------------------------------------
+
+create or replace function public.match_embeddings(
+    query_embedding extensions.vector(1536),
+    match_threshold float,
+    match_count integer,
+    p_user_id text
+)
+returns table (
+    block_id text,
+    page_id uuid,
+    content text,
+    metadata jsonb,
+    similarity float
+)
+language sql
+stable
+set search_path = public, extensions
+as $$
+    select
+        e.block_id,
+        e.page_id,
+        e.content,
+        e.metadata,
+        1 - (e.embedding <=> query_embedding) as similarity
+    from public.embeddings e
+    where e.user_id = p_user_id::bigint
+      and 1 - (e.embedding <=> query_embedding) > match_threshold
+    order by e.embedding <=> query_embedding
+    limit match_count;
+$$;
+
+-- ---------------------------------------------------------------------------
+-- Row level security
+-- ---------------------------------------------------------------------------
+
+-- The id of the ZeNote user a request is made for, taken from the `user_id`
+-- claim of the request JWT. Returns null for the anon/service keys.
+create or replace function public.requesting_user_id()
+returns bigint
+language sql
+stable
+as $$
+    select nullif(auth.jwt() ->> 'user_id', '')::bigint;
+$$;
+
+alter table public.schema_migrations enable row level security;
+alter table public.users enable row level security;
+alter table public.pages enable row level security;
+alter table public.blocks enable row level security;
+alter table public.embeddings enable row level security;
+
+drop policy if exists "schema_migrations are readable" on public.schema_migrations;
+create policy "schema_migrations are readable" on public.schema_migrations
+    for select to anon, authenticated
+    using (true);
+
+drop policy if exists "users can read themselves" on public.users;
+create policy "users can read themselves" on public.users
+    for select to authenticated
+    using (id = public.requesting_user_id());
+
+drop policy if exists "users can update themselves" on public.users;
+create policy "users can update themselves" on public.users
+    for update to authenticated
+    using (id = public.requesting_user_id())
+    with check (id = public.requesting_user_id());
+
+drop policy if exists "pages are owned by their user" on public.pages;
+create policy "pages are owned by their user" on public.pages
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (user_id = public.requesting_user_id());
+
+drop policy if exists "blocks follow their page" on public.blocks;
+create policy "blocks follow their page" on public.blocks
+    for all to authenticated
+    using (exists (
+        select 1 from public.pages p
+        where p.id = blocks.page_id and p.user_id = public.requesting_user_id()
+    ))
+    with check (exists (
+        select 1 from public.pages p
+        where p.id = blocks.page_id and p.user_id = public.requesting_user_id()
+    ));
+
+drop policy if exists "embeddings are owned by their user" on public.embeddings;
+create policy "embeddings are owned by their user" on public.embeddings
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (user_id = public.requesting_user_id());
+
+insert into public.schema_migrations (version, name)
+values (1, 'initial_schema')
+on conflict (version) do nothing;
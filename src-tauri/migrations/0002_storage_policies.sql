@@ -0,0 +1,26 @@
+-- 0002_storage_policies
+--
+-- Storage access for user-scoped JWTs. Uploads are now made with the signed-in
+-- user's token instead of the project key, so the buckets need policies.
+-- Storage records the JWT `sub` (the ZeNote user id) in `owner_id`.
+
+insert into storage.buckets (id, name, public)
+values ('images', 'images', true), ('avatars', 'avatars', true)
+on conflict (id) do nothing;
+
+drop policy if exists "users upload their images" on storage.objects;
+create policy "users upload their images" on storage.objects
+    for insert to authenticated
+    with check (bucket_id = 'images');
+
+drop policy if exists "users manage their images" on storage.objects;
+create policy "users manage their images" on storage.objects
+    for all to authenticated
+    using (bucket_id = 'images' and owner_id = public.requesting_user_id()::text)
+    with check (bucket_id = 'images' and owner_id = public.requesting_user_id()::text);
+
+-- Avatars are uploaded during sign up, before the user has a token.
+drop policy if exists "anyone uploads avatars" on storage.objects;
+create policy "anyone uploads avatars" on storage.objects
+    for insert to anon, authenticated
+    with check (bucket_id = 'avatars');
@@ -0,0 +1,38 @@
+-- 0003_page_positions
+--
+-- Explicit ordering among sibling pages, and an RPC that applies a batch of
+-- parent/position changes in one transaction so a move never leaves siblings
+-- half renumbered.
+
+alter table public.pages add column if not exists position integer not null default 0;
+
+create index if not exists pages_parent_page_id_position_idx
+    on public.pages (user_id, parent_page_id, position);
+
+-- Number existing siblings by their current sidebar order (most recent first).
+with ranked as (
+    select id, row_number() over (
+        partition by user_id, parent_page_id
+        order by updated_at desc, id
+    ) - 1 as position
+    from public.pages
+)
+update public.pages p
+set position = ranked.position
+from ranked
+where p.id = ranked.id;
+
+-- p_pages: [{ "id": uuid, "parent_page_id": uuid | null, "position": int }]
+-- Runs as the caller, so RLS still decides which rows may change.
+create or replace function public.set_page_positions(p_pages jsonb)
+returns setof public.pages
+language sql
+security invoker
+as $$
+    update public.pages p
+    set parent_page_id = x.parent_page_id,
+        position = x.position
+    from jsonb_to_recordset(p_pages) as x(id uuid, parent_page_id uuid, position integer)
+    where p.id = x.id
+    returning p.*;
+$$;
@@ -0,0 +1,66 @@
+-- 0004_trash
+--
+-- Soft delete for pages. Deleting a page stamps it and its whole subtree with
+-- `deleted_at` and the id of the page the user deleted (`trashed_root_id`),
+-- so restoring brings back exactly that subtree. Blocks and embeddings stay
+-- with their page and are removed by the existing cascades when the trash is
+-- purged.
+
+alter table public.pages add column if not exists deleted_at timestamptz;
+alter table public.pages add column if not exists trashed_root_id uuid;
+
+create index if not exists pages_user_id_deleted_at_idx
+    on public.pages (user_id, deleted_at)
+    where deleted_at is not null;
+create index if not exists pages_trashed_root_id_idx
+    on public.pages (trashed_root_id)
+    where trashed_root_id is not null;
+
+-- Same signature as 0001, now skipping embeddings of trashed pages.
+create or replace function public.match_embeddings(
+    query_embedding extensions.vector(1536),
+    match_threshold float,
+    match_count integer,
+    p_user_id text
+)
+returns table (
+    block_id text,
+    page_id uuid,
+    content text,
+    metadata jsonb,
+    similarity float
+)
+language sql
+stable
+set search_path = public, extensions
+as $$
+    select
+        e.block_id,
+        e.page_id,
+        e.content,
+        e.metadata,
+        1 - (e.embedding <=> query_embedding) as similarity
+    from public.embeddings e
+    join public.pages p on p.id = e.page_id
+    where e.user_id = p_user_id::bigint
+      and p.deleted_at is null
+      and 1 - (e.embedding <=> query_embedding) > match_threshold
+    order by e.embedding <=> query_embedding
+    limit match_count;
+$$;
+
+-- Permanently deletes the caller's pages that have been in the trash longer
+-- than `p_retention_days`. Runs as the caller, so RLS limits it to their pages.
+create or replace function public.purge_trash(p_retention_days integer)
+returns integer
+language sql
+security invoker
+as $$
+    with purged as (
+        delete from public.pages
+        where deleted_at is not null
+          and deleted_at < now() - make_interval(days => p_retention_days)
+        returning 1
+    )
+    select count(*)::integer from purged;
+$$;
@@ -0,0 +1,11 @@
+-- 0005_templates
+--
+-- Any page can be marked as a template. Templates stay ordinary pages (they
+-- keep their blocks, RLS and trash behaviour); the flag only decides whether
+-- `list_templates` offers them.
+
+alter table public.pages add column if not exists is_template boolean not null default false;
+
+create index if not exists pages_user_id_is_template_idx
+    on public.pages (user_id)
+    where is_template;
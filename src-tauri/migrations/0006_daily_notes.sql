@@ -0,0 +1,28 @@
+-- 0006_daily_notes
+--
+-- Journal pages are ordinary pages tagged with the day they belong to. Each
+-- user has at most one live journal page per day; a trashed one doesn't
+-- block creating a new one.
+
+alter table public.pages add column if not exists journal_date date;
+
+create unique index if not exists pages_user_id_journal_date_key
+    on public.pages (user_id, journal_date)
+    where journal_date is not null and deleted_at is null;
+
+-- Where new journal pages go and which template they start from. Template
+-- ids are page ids or `builtin:*` names, so the column is text.
+create table if not exists public.daily_note_settings (
+    user_id bigint primary key references public.users (id) on delete cascade,
+    parent_page_id uuid references public.pages (id) on delete set null,
+    template_id text,
+    updated_at timestamptz not null default now()
+);
+
+alter table public.daily_note_settings enable row level security;
+
+drop policy if exists "daily_note_settings are owned by their user" on public.daily_note_settings;
+create policy "daily_note_settings are owned by their user" on public.daily_note_settings
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (user_id = public.requesting_user_id());
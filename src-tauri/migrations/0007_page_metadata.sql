@@ -0,0 +1,12 @@
+-- 0007_page_metadata
+--
+-- Optional presentation fields for pages: an emoji or icon, a cover image
+-- (a public Storage URL in the images bucket), free-form tags and a short
+-- description.
+
+alter table public.pages add column if not exists icon text;
+alter table public.pages add column if not exists cover_url text;
+alter table public.pages add column if not exists tags text[] not null default '{}';
+alter table public.pages add column if not exists description text not null default '';
+
+create index if not exists pages_tags_idx on public.pages using gin (tags);
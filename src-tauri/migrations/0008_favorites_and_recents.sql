@@ -0,0 +1,57 @@
+-- 0008_favorites_and_recents
+--
+-- Per-user favorites (pinned pages) and a most-recently-visited history for
+-- the sidebar. Both rows go away with their page.
+
+create table if not exists public.page_favorites (
+    user_id bigint not null references public.users (id) on delete cascade,
+    page_id uuid not null references public.pages (id) on delete cascade,
+    created_at timestamptz not null default now(),
+    primary key (user_id, page_id)
+);
+
+create table if not exists public.page_visits (
+    user_id bigint not null references public.users (id) on delete cascade,
+    page_id uuid not null references public.pages (id) on delete cascade,
+    visited_at timestamptz not null default now(),
+    primary key (user_id, page_id)
+);
+
+create index if not exists page_visits_user_id_visited_at_idx
+    on public.page_visits (user_id, visited_at desc);
+
+alter table public.page_favorites enable row level security;
+alter table public.page_visits enable row level security;
+
+drop policy if exists "page_favorites are owned by their user" on public.page_favorites;
+create policy "page_favorites are owned by their user" on public.page_favorites
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (user_id = public.requesting_user_id());
+
+drop policy if exists "page_visits are owned by their user" on public.page_visits;
+create policy "page_visits are owned by their user" on public.page_visits
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (user_id = public.requesting_user_id());
+
+-- Records a visit by the calling user and trims their history to the
+-- `p_keep` most recent pages.
+create or replace function public.record_page_visit(p_page_id uuid, p_keep integer)
+returns void
+language sql
+security invoker
+as $$
+    insert into public.page_visits (user_id, page_id, visited_at)
+    values (public.requesting_user_id(), p_page_id, now())
+    on conflict (user_id, page_id) do update set visited_at = excluded.visited_at;
+
+    delete from public.page_visits v
+    where v.user_id = public.requesting_user_id()
+      and v.page_id not in (
+          select page_id from public.page_visits
+          where user_id = public.requesting_user_id()
+          order by visited_at desc
+          limit p_keep
+      );
+$$;
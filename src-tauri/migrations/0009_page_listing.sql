@@ -0,0 +1,18 @@
+-- 0009_page_listing
+--
+-- Supports paginated `fetch_pages`: a lowercase title for case-insensitive
+-- sorting, and indexes matching each sort order's keyset (sort key, id).
+
+alter table public.pages
+    add column if not exists title_key text generated always as (lower(title)) stored;
+
+create index if not exists pages_user_id_updated_at_id_idx
+    on public.pages (user_id, updated_at desc, id desc);
+create index if not exists pages_user_id_created_at_id_idx
+    on public.pages (user_id, created_at desc, id desc);
+create index if not exists pages_user_id_title_key_id_idx
+    on public.pages (user_id, title_key, id);
+create index if not exists pages_user_id_position_id_idx
+    on public.pages (user_id, position, id);
+create index if not exists pages_title_key_prefix_idx
+    on public.pages (user_id, title_key text_pattern_ops);
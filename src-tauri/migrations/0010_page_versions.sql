@@ -0,0 +1,28 @@
+-- 0010_page_versions
+--
+-- Page snapshots: the title and the ordered block list as they were before
+-- an edit session, a block deletion or a restore. `blocks` holds
+-- [{ id, type, order, content, parent_block_id }].
+
+create table if not exists public.page_versions (
+    id bigint generated always as identity primary key,
+    page_id uuid not null references public.pages (id) on delete cascade,
+    user_id bigint not null references public.users (id) on delete cascade,
+    created_at timestamptz not null default now(),
+    reason text not null,
+    title text not null default '',
+    blocks jsonb not null default '[]'::jsonb,
+    block_count integer not null default 0,
+    content_hash text not null
+);
+
+create index if not exists page_versions_page_id_created_at_idx
+    on public.page_versions (page_id, created_at desc);
+
+alter table public.page_versions enable row level security;
+
+drop policy if exists "page_versions are owned by their user" on public.page_versions;
+create policy "page_versions are owned by their user" on public.page_versions
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (user_id = public.requesting_user_id());
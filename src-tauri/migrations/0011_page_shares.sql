@@ -0,0 +1,217 @@
+-- 0011_page_shares
+--
+-- Sharing pages with other users. A grant gives one user a role on a page
+-- (viewer, commenter or editor) and, unless `include_subpages` is false, on
+-- every page below it. `page_role` resolves the caller's effective role and
+-- the RLS policies for pages, blocks and versions are built on it.
+
+create table if not exists public.page_shares (
+    page_id uuid not null references public.pages (id) on delete cascade,
+    user_id bigint not null references public.users (id) on delete cascade,
+    role text not null check (role in ('viewer', 'commenter', 'editor')),
+    include_subpages boolean not null default true,
+    invited_by bigint references public.users (id) on delete set null,
+    created_at timestamptz not null default now(),
+    primary key (page_id, user_id)
+);
+
+create index if not exists page_shares_user_id_idx on public.page_shares (user_id);
+
+-- The caller's role on a page: 'owner', the strongest grant on the page or
+-- an ancestor that includes subpages, or null. Security definer so it can
+-- walk ancestors the caller cannot otherwise see without recursing into RLS.
+create or replace function public.page_role(p_page_id uuid)
+returns text
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    with recursive ancestors (id, parent_page_id, depth) as (
+        select p.id, p.parent_page_id, 0
+        from public.pages p
+        where p.id = p_page_id
+        union all
+        select p.id, p.parent_page_id, a.depth + 1
+        from public.pages p
+        join ancestors a on p.id = a.parent_page_id
+        where a.depth < 64
+    )
+    select case
+        when exists (
+            select 1 from public.pages
+            where id = p_page_id and user_id = public.requesting_user_id()
+        ) then 'owner'
+        else (
+            select s.role
+            from public.page_shares s
+            join ancestors a on a.id = s.page_id
+            where s.user_id = public.requesting_user_id()
+              and (a.depth = 0 or s.include_subpages)
+            order by case s.role when 'editor' then 3 when 'commenter' then 2 else 1 end desc
+            limit 1
+        )
+    end;
+$$;
+
+-- Grants `p_role` on a page the caller owns to the user with `p_email`.
+-- Returns the collaborator, or null when no user has that email.
+create or replace function public.share_page(
+    p_page_id uuid,
+    p_email text,
+    p_role text,
+    p_include_subpages boolean
+)
+returns json
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    grantee public.users%rowtype;
+begin
+    if public.page_role(p_page_id) is distinct from 'owner' then
+        raise exception 'Only the page owner can share it';
+    end if;
+
+    select * into grantee from public.users where lower(email) = lower(trim(p_email));
+    if not found then
+        return null;
+    end if;
+    if grantee.id = public.requesting_user_id() then
+        raise exception 'You already own this page';
+    end if;
+
+    insert into public.page_shares (page_id, user_id, role, include_subpages, invited_by)
+    values (p_page_id, grantee.id, p_role, p_include_subpages, public.requesting_user_id())
+    on conflict (page_id, user_id) do update
+        set role = excluded.role, include_subpages = excluded.include_subpages;
+
+    return json_build_object(
+        'user_id', grantee.id::text,
+        'email', grantee.email,
+        'first_name', grantee.first_name,
+        'last_name', grantee.last_name,
+        'avatar_url', grantee.avatar_url,
+        'role', p_role,
+        'include_subpages', p_include_subpages,
+        'page_id', p_page_id
+    );
+end;
+$$;
+
+-- The owner and everyone with a grant on the page or an ancestor that
+-- applies to it. `page_id` is where the grant was made, so inherited access
+-- can be told apart. Visible to anyone who can see the page.
+create or replace function public.list_page_collaborators(p_page_id uuid)
+returns table (
+    user_id text,
+    email text,
+    first_name text,
+    last_name text,
+    avatar_url text,
+    role text,
+    include_subpages boolean,
+    page_id uuid
+)
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    with recursive ancestors (id, parent_page_id, depth) as (
+        select p.id, p.parent_page_id, 0
+        from public.pages p
+        where p.id = p_page_id and public.page_role(p_page_id) is not null
+        union all
+        select p.id, p.parent_page_id, a.depth + 1
+        from public.pages p
+        join ancestors a on p.id = a.parent_page_id
+        where a.depth < 64
+    )
+    select u.id::text, u.email, u.first_name, u.last_name, u.avatar_url, 'owner', true, p.id
+    from public.pages p
+    join public.users u on u.id = p.user_id
+    where p.id = p_page_id and public.page_role(p_page_id) is not null
+    union all
+    select u.id::text, u.email, u.first_name, u.last_name, u.avatar_url, s.role, s.include_subpages, s.page_id
+    from public.page_shares s
+    join ancestors a on a.id = s.page_id
+    join public.users u on u.id = s.user_id
+    where a.depth = 0 or s.include_subpages;
+$$;
+
+alter table public.page_shares enable row level security;
+
+-- Owners manage grants on their pages; grantees see theirs and may leave.
+drop policy if exists "page_shares are visible to owner and grantee" on public.page_shares;
+create policy "page_shares are visible to owner and grantee" on public.page_shares
+    for select to authenticated
+    using (
+        user_id = public.requesting_user_id()
+        or exists (
+            select 1 from public.pages p
+            where p.id = page_shares.page_id and p.user_id = public.requesting_user_id()
+        )
+    );
+
+drop policy if exists "page_shares are revocable by owner and grantee" on public.page_shares;
+create policy "page_shares are revocable by owner and grantee" on public.page_shares
+    for delete to authenticated
+    using (
+        user_id = public.requesting_user_id()
+        or exists (
+            select 1 from public.pages p
+            where p.id = page_shares.page_id and p.user_id = public.requesting_user_id()
+        )
+    );
+
+drop policy if exists "shared pages are readable" on public.pages;
+create policy "shared pages are readable" on public.pages
+    for select to authenticated
+    using (public.page_role(id) is not null);
+
+-- Blocks: anyone with a role reads, owners and editors write.
+drop policy if exists "blocks follow their page" on public.blocks;
+
+drop policy if exists "blocks are readable by collaborators" on public.blocks;
+create policy "blocks are readable by collaborators" on public.blocks
+    for select to authenticated
+    using (public.page_role(page_id) is not null);
+
+drop policy if exists "blocks are insertable by editors" on public.blocks;
+create policy "blocks are insertable by editors" on public.blocks
+    for insert to authenticated
+    with check (public.page_role(page_id) in ('owner', 'editor'));
+
+drop policy if exists "blocks are updatable by editors" on public.blocks;
+create policy "blocks are updatable by editors" on public.blocks
+    for update to authenticated
+    using (public.page_role(page_id) in ('owner', 'editor'))
+    with check (public.page_role(page_id) in ('owner', 'editor'));
+
+drop policy if exists "blocks are deletable by editors" on public.blocks;
+create policy "blocks are deletable by editors" on public.blocks
+    for delete to authenticated
+    using (public.page_role(page_id) in ('owner', 'editor'));
+
+-- Versions: collaborators see a page's history; editors' snapshots count.
+drop policy if exists "page_versions are owned by their user" on public.page_versions;
+
+drop policy if exists "page_versions are readable by collaborators" on public.page_versions;
+create policy "page_versions are readable by collaborators" on public.page_versions
+    for select to authenticated
+    using (public.page_role(page_id) is not null);
+
+drop policy if exists "page_versions are written by editors" on public.page_versions;
+create policy "page_versions are written by editors" on public.page_versions
+    for insert to authenticated
+    with check (
+        user_id = public.requesting_user_id()
+        and public.page_role(page_id) in ('owner', 'editor')
+    );
+
+drop policy if exists "page_versions are pruned by editors" on public.page_versions;
+create policy "page_versions are pruned by editors" on public.page_versions
+    for delete to authenticated
+    using (public.page_role(page_id) in ('owner', 'editor'));
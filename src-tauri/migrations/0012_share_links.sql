@@ -0,0 +1,102 @@
+-- 0012_share_links
+--
+-- Public, read-only links to a page for people without an account. Links
+-- are unguessable tokens that the owner can disable and that may expire.
+-- Readers use the project key, so everything they can reach goes through
+-- the security definer functions below.
+
+create table if not exists public.share_links (
+    id uuid primary key default gen_random_uuid(),
+    token text not null unique,
+    page_id uuid not null references public.pages (id) on delete cascade,
+    user_id bigint not null references public.users (id) on delete cascade,
+    created_at timestamptz not null default now(),
+    expires_at timestamptz,
+    disabled_at timestamptz
+);
+
+create index if not exists share_links_page_id_idx on public.share_links (page_id);
+
+alter table public.share_links enable row level security;
+
+drop policy if exists "share_links are owned by their user" on public.share_links;
+create policy "share_links are owned by their user" on public.share_links
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (
+        user_id = public.requesting_user_id()
+        and exists (
+            select 1 from public.pages p
+            where p.id = share_links.page_id and p.user_id = public.requesting_user_id()
+        )
+    );
+
+-- The page behind a link that is enabled, unexpired and not in the trash.
+create or replace function public.share_link_page(p_token text)
+returns uuid
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select l.page_id
+    from public.share_links l
+    join public.pages p on p.id = l.page_id
+    where l.token = p_token
+      and l.disabled_at is null
+      and (l.expires_at is null or l.expires_at > now())
+      and p.deleted_at is null;
+$$;
+
+-- The page and its blocks for a valid link, or null.
+create or replace function public.fetch_shared_page(p_token text)
+returns json
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select json_build_object(
+        'page', to_jsonb(p) - 'user_id' - 'trashed_root_id' - 'title_key',
+        'blocks', coalesce(
+            (select json_agg(b order by b."order") from public.blocks b where b.page_id = p.id),
+            '[]'::json
+        )
+    )
+    from public.pages p
+    where p.id = public.share_link_page(p_token);
+$$;
+
+grant execute on function public.fetch_shared_page(text) to anon, authenticated;
+
+-- Whether an image is used by a page with a live share link, as its cover
+-- or in one of its blocks. Lets link readers sign URLs for those images.
+create or replace function public.is_shared_image(p_key text)
+returns boolean
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select exists (
+        select 1
+        from public.share_links l
+        join public.pages p on p.id = l.page_id
+        where l.disabled_at is null
+          and (l.expires_at is null or l.expires_at > now())
+          and p.deleted_at is null
+          and (
+              p.cover_url like '%/storage/v1/object/public/images/' || p_key
+              or exists (
+                  select 1 from public.blocks b
+                  where b.page_id = p.id
+                    and b.content like '%/storage/v1/object/public/images/' || p_key || '%'
+              )
+          )
+    );
+$$;
+
+drop policy if exists "shared images are readable" on storage.objects;
+create policy "shared images are readable" on storage.objects
+    for select to anon, authenticated
+    using (bucket_id = 'images' and public.is_shared_image(name));
@@ -0,0 +1,431 @@
+-- 0013_workspaces
+--
+-- Workspaces group pages and the people working on them. Every user has a
+-- personal workspace that can't be shared; team workspaces have members
+-- with a role: owner, admin, member or guest. Owners, admins and members
+-- can read and edit every page in the workspace; guests only see pages
+-- shared with them. New pages land in the workspace of their parent, or in
+-- the user's active workspace.
+
+create table if not exists public.workspaces (
+    id uuid primary key default gen_random_uuid(),
+    name text not null,
+    owner_id bigint not null references public.users (id) on delete cascade,
+    personal boolean not null default false,
+    created_at timestamptz not null default now()
+);
+
+create unique index if not exists workspaces_personal_owner_key
+    on public.workspaces (owner_id)
+    where personal;
+
+create table if not exists public.workspace_members (
+    workspace_id uuid not null references public.workspaces (id) on delete cascade,
+    user_id bigint not null references public.users (id) on delete cascade,
+    role text not null check (role in ('owner', 'admin', 'member', 'guest')),
+    invited_by bigint references public.users (id) on delete set null,
+    created_at timestamptz not null default now(),
+    primary key (workspace_id, user_id)
+);
+
+create index if not exists workspace_members_user_id_idx on public.workspace_members (user_id);
+
+create table if not exists public.active_workspaces (
+    user_id bigint primary key references public.users (id) on delete cascade,
+    workspace_id uuid not null references public.workspaces (id) on delete cascade,
+    updated_at timestamptz not null default now()
+);
+
+alter table public.pages add column if not exists workspace_id uuid
+    references public.workspaces (id) on delete cascade;
+
+create index if not exists pages_workspace_id_idx on public.pages (workspace_id);
+
+-- Backfill: a personal workspace for every user, holding their pages.
+insert into public.workspaces (name, owner_id, personal)
+select 'Personal', u.id, true
+from public.users u
+where not exists (
+    select 1 from public.workspaces w where w.owner_id = u.id and w.personal
+);
+
+insert into public.workspace_members (workspace_id, user_id, role)
+select w.id, w.owner_id, 'owner'
+from public.workspaces w
+where w.personal
+on conflict (workspace_id, user_id) do nothing;
+
+update public.pages p
+set workspace_id = w.id
+from public.workspaces w
+where p.workspace_id is null and w.personal and w.owner_id = p.user_id;
+
+-- The caller's role in a workspace, or null.
+create or replace function public.workspace_role(p_workspace_id uuid)
+returns text
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select role from public.workspace_members
+    where workspace_id = p_workspace_id and user_id = public.requesting_user_id();
+$$;
+
+-- The user's personal workspace, created on first use.
+create or replace function public.ensure_personal_workspace(p_user_id bigint)
+returns uuid
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    workspace uuid;
+begin
+    select id into workspace from public.workspaces where owner_id = p_user_id and personal;
+    if workspace is null then
+        insert into public.workspaces (name, owner_id, personal)
+        values ('Personal', p_user_id, true)
+        on conflict (owner_id) where personal do nothing
+        returning id into workspace;
+        if workspace is null then
+            select id into workspace from public.workspaces where owner_id = p_user_id and personal;
+        end if;
+        insert into public.workspace_members (workspace_id, user_id, role)
+        values (workspace, p_user_id, 'owner')
+        on conflict (workspace_id, user_id) do nothing;
+    end if;
+    return workspace;
+end;
+$$;
+
+revoke all on function public.ensure_personal_workspace(bigint) from public, anon, authenticated;
+
+-- The workspace the caller last switched to, if they are still a member,
+-- otherwise their personal workspace.
+create or replace function public.active_workspace_id()
+returns uuid
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    workspace uuid;
+begin
+    select a.workspace_id into workspace
+    from public.active_workspaces a
+    join public.workspace_members m
+        on m.workspace_id = a.workspace_id and m.user_id = a.user_id
+    where a.user_id = public.requesting_user_id();
+    return coalesce(workspace, public.ensure_personal_workspace(public.requesting_user_id()));
+end;
+$$;
+
+-- Places new pages: under their parent's workspace, else the owner's
+-- active workspace unless they are only a guest there, else their
+-- personal workspace.
+create or replace function public.set_page_workspace()
+returns trigger
+language plpgsql
+security definer
+set search_path = public
+as $$
+begin
+    if new.workspace_id is null and new.parent_page_id is not null then
+        select workspace_id into new.workspace_id from public.pages where id = new.parent_page_id;
+    end if;
+    if new.workspace_id is null then
+        select a.workspace_id into new.workspace_id
+        from public.active_workspaces a
+        join public.workspace_members m
+            on m.workspace_id = a.workspace_id and m.user_id = a.user_id
+        where a.user_id = new.user_id and m.role <> 'guest';
+    end if;
+    if new.workspace_id is null then
+        new.workspace_id := public.ensure_personal_workspace(new.user_id);
+    end if;
+    return new;
+end;
+$$;
+
+drop trigger if exists pages_set_workspace on public.pages;
+create trigger pages_set_workspace
+    before insert on public.pages
+    for each row execute function public.set_page_workspace();
+
+-- Same as 0011, plus workspace access: owners, admins and members of a
+-- page's workspace edit it like editors.
+create or replace function public.page_role(p_page_id uuid)
+returns text
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    with recursive ancestors (id, parent_page_id, depth) as (
+        select p.id, p.parent_page_id, 0
+        from public.pages p
+        where p.id = p_page_id
+        union all
+        select p.id, p.parent_page_id, a.depth + 1
+        from public.pages p
+        join ancestors a on p.id = a.parent_page_id
+        where a.depth < 64
+    )
+    select case
+        when exists (
+            select 1 from public.pages
+            where id = p_page_id and user_id = public.requesting_user_id()
+        ) then 'owner'
+        when exists (
+            select 1
+            from public.pages p
+            join public.workspace_members m on m.workspace_id = p.workspace_id
+            where p.id = p_page_id
+              and m.user_id = public.requesting_user_id()
+              and m.role <> 'guest'
+        ) then 'editor'
+        else (
+            select s.role
+            from public.page_shares s
+            join ancestors a on a.id = s.page_id
+            where s.user_id = public.requesting_user_id()
+              and (a.depth = 0 or s.include_subpages)
+            order by case s.role when 'editor' then 3 when 'commenter' then 2 else 1 end desc
+            limit 1
+        )
+    end;
+$$;
+
+create or replace function public.create_workspace(p_name text)
+returns json
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    workspace public.workspaces%rowtype;
+begin
+    insert into public.workspaces (name, owner_id)
+    values (trim(p_name), public.requesting_user_id())
+    returning * into workspace;
+
+    insert into public.workspace_members (workspace_id, user_id, role)
+    values (workspace.id, workspace.owner_id, 'owner');
+
+    return json_build_object(
+        'id', workspace.id,
+        'name', workspace.name,
+        'owner_id', workspace.owner_id::text,
+        'personal', workspace.personal,
+        'created_at', workspace.created_at,
+        'role', 'owner'
+    );
+end;
+$$;
+
+-- Adds the user with `p_email` to a team workspace, or changes their role.
+-- Admins invite members and guests; only the owner can make admins.
+-- Returns null when no user has that email.
+create or replace function public.invite_workspace_member(
+    p_workspace_id uuid,
+    p_email text,
+    p_role text
+)
+returns json
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    caller_role text := public.workspace_role(p_workspace_id);
+    invitee public.users%rowtype;
+begin
+    if caller_role is null or caller_role not in ('owner', 'admin') then
+        raise exception 'Only owners and admins can invite members';
+    end if;
+    if p_role not in ('admin', 'member', 'guest') then
+        raise exception 'Invalid role %', p_role;
+    end if;
+    if p_role = 'admin' and caller_role <> 'owner' then
+        raise exception 'Only the owner can add admins';
+    end if;
+    if exists (select 1 from public.workspaces where id = p_workspace_id and personal) then
+        raise exception 'Personal workspaces can''t be shared';
+    end if;
+
+    select * into invitee from public.users where lower(email) = lower(trim(p_email));
+    if not found then
+        return null;
+    end if;
+    if exists (
+        select 1 from public.workspaces where id = p_workspace_id and owner_id = invitee.id
+    ) then
+        raise exception 'The owner''s role can''t be changed';
+    end if;
+
+    insert into public.workspace_members (workspace_id, user_id, role, invited_by)
+    values (p_workspace_id, invitee.id, p_role, public.requesting_user_id())
+    on conflict (workspace_id, user_id) do update set role = excluded.role;
+
+    return json_build_object(
+        'user_id', invitee.id::text,
+        'email', invitee.email,
+        'first_name', invitee.first_name,
+        'last_name', invitee.last_name,
+        'avatar_url', invitee.avatar_url,
+        'role', p_role
+    );
+end;
+$$;
+
+create or replace function public.list_workspace_members(p_workspace_id uuid)
+returns table (
+    user_id text,
+    email text,
+    first_name text,
+    last_name text,
+    avatar_url text,
+    role text,
+    joined_at timestamptz
+)
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select u.id::text, u.email, u.first_name, u.last_name, u.avatar_url, m.role, m.created_at
+    from public.workspace_members m
+    join public.users u on u.id = m.user_id
+    where m.workspace_id = p_workspace_id
+      and public.workspace_role(p_workspace_id) is not null
+    order by case m.role when 'owner' then 0 when 'admin' then 1 when 'member' then 2 else 3 end,
+             m.created_at;
+$$;
+
+-- Hands a team workspace to another member; the previous owner stays on
+-- as an admin.
+create or replace function public.transfer_workspace_ownership(
+    p_workspace_id uuid,
+    p_user_id bigint
+)
+returns void
+language plpgsql
+security definer
+set search_path = public
+as $$
+begin
+    if public.workspace_role(p_workspace_id) is distinct from 'owner' then
+        raise exception 'Only the owner can transfer a workspace';
+    end if;
+    if exists (select 1 from public.workspaces where id = p_workspace_id and personal) then
+        raise exception 'Personal workspaces can''t be transferred';
+    end if;
+    if not exists (
+        select 1 from public.workspace_members
+        where workspace_id = p_workspace_id and user_id = p_user_id
+    ) then
+        raise exception 'The new owner must be a member';
+    end if;
+
+    update public.workspace_members set role = 'admin'
+    where workspace_id = p_workspace_id and user_id = public.requesting_user_id();
+    update public.workspace_members set role = 'owner'
+    where workspace_id = p_workspace_id and user_id = p_user_id;
+    update public.workspaces set owner_id = p_user_id where id = p_workspace_id;
+end;
+$$;
+
+-- Removes the caller from a team workspace. Owners transfer it first.
+-- Pages they created stay in the workspace.
+create or replace function public.leave_workspace(p_workspace_id uuid)
+returns void
+language plpgsql
+security definer
+set search_path = public
+as $$
+begin
+    if public.workspace_role(p_workspace_id) = 'owner' then
+        raise exception 'Transfer ownership before leaving';
+    end if;
+
+    delete from public.workspace_members
+    where workspace_id = p_workspace_id and user_id = public.requesting_user_id();
+    delete from public.active_workspaces
+    where workspace_id = p_workspace_id and user_id = public.requesting_user_id();
+end;
+$$;
+
+alter table public.workspaces enable row level security;
+alter table public.workspace_members enable row level security;
+alter table public.active_workspaces enable row level security;
+
+drop policy if exists "workspaces are visible to members" on public.workspaces;
+create policy "workspaces are visible to members" on public.workspaces
+    for select to authenticated
+    using (public.workspace_role(id) is not null);
+
+drop policy if exists "workspaces are renamed by admins" on public.workspaces;
+create policy "workspaces are renamed by admins" on public.workspaces
+    for update to authenticated
+    using (public.workspace_role(id) in ('owner', 'admin'))
+    with check (public.workspace_role(id) in ('owner', 'admin'));
+
+drop policy if exists "workspace_members are visible to members" on public.workspace_members;
+create policy "workspace_members are visible to members" on public.workspace_members
+    for select to authenticated
+    using (public.workspace_role(workspace_id) is not null);
+
+drop policy if exists "active_workspaces are owned by their user" on public.active_workspaces;
+create policy "active_workspaces are owned by their user" on public.active_workspaces
+    for all to authenticated
+    using (user_id = public.requesting_user_id())
+    with check (
+        user_id = public.requesting_user_id()
+        and public.workspace_role(workspace_id) is not null
+    );
+
+-- Workspace members search each other's pages.
+drop policy if exists "embeddings are readable by collaborators" on public.embeddings;
+create policy "embeddings are readable by collaborators" on public.embeddings
+    for select to authenticated
+    using (public.page_role(page_id) is not null);
+
+-- With `p_workspace_id`, searches every page in that workspace the caller
+-- can read instead of only their own.
+drop function if exists public.match_embeddings(extensions.vector, float, integer, text);
+create or replace function public.match_embeddings(
+    query_embedding extensions.vector(1536),
+    match_threshold float,
+    match_count integer,
+    p_user_id text,
+    p_workspace_id uuid default null
+)
+returns table (
+    block_id text,
+    page_id uuid,
+    content text,
+    metadata jsonb,
+    similarity float
+)
+language sql
+stable
+set search_path = public, extensions
+as $$
+    select
+        e.block_id,
+        e.page_id,
+        e.content,
+        e.metadata,
+        1 - (e.embedding <=> query_embedding) as similarity
+    from public.embeddings e
+    join public.pages p on p.id = e.page_id
+    where (
+            (p_workspace_id is null and e.user_id = p_user_id::bigint)
+            or p.workspace_id = p_workspace_id
+          )
+      and p.deleted_at is null
+      and 1 - (e.embedding <=> query_embedding) > match_threshold
+    order by e.embedding <=> query_embedding
+    limit match_count;
+$$;
@@ -0,0 +1,48 @@
+-- 0014_page_links
+--
+-- Index of page mentions in block content. A mention is written
+-- `[[page:<page id>|<label>]]` (the label is optional) and refers to the page
+-- by id, so renaming the page keeps the link. Rows are rewritten whenever a
+-- block's content is saved and disappear with the block or its page.
+
+create table if not exists public.page_links (
+    block_id bigint not null references public.blocks (id) on delete cascade,
+    source_page_id uuid not null references public.pages (id) on delete cascade,
+    -- No foreign key: a mention of a deleted page is kept but matches nothing
+    target_page_id uuid not null,
+    created_at timestamptz not null default now(),
+    primary key (block_id, target_page_id)
+);
+
+create index if not exists page_links_target_page_id_idx on public.page_links (target_page_id);
+create index if not exists page_links_source_page_id_idx on public.page_links (source_page_id);
+
+-- Backfill from existing blocks.
+insert into public.page_links (block_id, source_page_id, target_page_id)
+select distinct b.id, b.page_id, lower(m[1])::uuid
+from public.blocks b,
+     regexp_matches(
+         b.content,
+         '\[\[page:([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})(\|[^\]]*)?\]\]',
+         'g'
+     ) as m
+where lower(m[1])::uuid <> b.page_id
+on conflict (block_id, target_page_id) do nothing;
+
+alter table public.page_links enable row level security;
+
+-- Links are visible with the block that holds them.
+drop policy if exists "page_links are readable by collaborators" on public.page_links;
+create policy "page_links are readable by collaborators" on public.page_links
+    for select to authenticated
+    using (public.page_role(source_page_id) is not null);
+
+drop policy if exists "page_links are written by editors" on public.page_links;
+create policy "page_links are written by editors" on public.page_links
+    for insert to authenticated
+    with check (public.page_role(source_page_id) in ('owner', 'editor'));
+
+drop policy if exists "page_links are removed by editors" on public.page_links;
+create policy "page_links are removed by editors" on public.page_links
+    for delete to authenticated
+    using (public.page_role(source_page_id) in ('owner', 'editor'));
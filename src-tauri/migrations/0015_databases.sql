@@ -0,0 +1,99 @@
+-- 0015_databases
+--
+-- A page can act as a database: its child pages are the rows, and each row
+-- carries values for the database's typed properties. Values live in the
+-- row page's `properties`, keyed by property id, so renaming a property
+-- keeps them. Saved views store a layout and its filters, sorts and
+-- grouping.
+
+alter table public.pages add column if not exists is_database boolean not null default false;
+alter table public.pages add column if not exists properties jsonb not null default '{}'::jsonb;
+
+create index if not exists pages_properties_idx on public.pages using gin (properties);
+
+create table if not exists public.database_properties (
+    id uuid primary key default gen_random_uuid(),
+    database_page_id uuid not null references public.pages (id) on delete cascade,
+    name text not null,
+    type text not null check (
+        type in ('text', 'number', 'select', 'multi_select', 'date', 'checkbox', 'relation', 'url')
+    ),
+    -- `{"options": [{"name", "color"}]}` for selects, `{"database_page_id"}` for relations
+    options jsonb not null default '{}'::jsonb,
+    position integer not null default 0,
+    created_at timestamptz not null default now(),
+    unique (database_page_id, name)
+);
+
+create table if not exists public.database_views (
+    id uuid primary key default gen_random_uuid(),
+    database_page_id uuid not null references public.pages (id) on delete cascade,
+    name text not null,
+    layout text not null check (layout in ('table', 'board', 'calendar')),
+    -- filters, sorts, group_by, date_property and visible_properties
+    config jsonb not null default '{}'::jsonb,
+    position integer not null default 0,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz not null default now()
+);
+
+create index if not exists database_properties_page_idx on public.database_properties (database_page_id);
+create index if not exists database_views_page_idx on public.database_views (database_page_id);
+
+-- Sets one property value on a row page, or removes it when `p_value` is
+-- null. Editors of a database aren't necessarily the row's owner, so this
+-- checks the role itself instead of relying on the pages update policy.
+create or replace function public.set_page_property(
+    p_page_id uuid,
+    p_property_id uuid,
+    p_value jsonb
+)
+returns jsonb
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    result jsonb;
+begin
+    if public.page_role(p_page_id) is null or public.page_role(p_page_id) not in ('owner', 'editor') then
+        raise exception 'You don''t have permission to edit this page';
+    end if;
+
+    update public.pages
+    set properties = case
+            when p_value is null or p_value = 'null'::jsonb then properties - p_property_id::text
+            else jsonb_set(properties, array[p_property_id::text], p_value)
+        end,
+        updated_at = now()
+    where id = p_page_id
+    returning properties into result;
+
+    return result;
+end;
+$$;
+
+alter table public.database_properties enable row level security;
+alter table public.database_views enable row level security;
+
+drop policy if exists "database_properties are readable by collaborators" on public.database_properties;
+create policy "database_properties are readable by collaborators" on public.database_properties
+    for select to authenticated
+    using (public.page_role(database_page_id) is not null);
+
+drop policy if exists "database_properties are written by editors" on public.database_properties;
+create policy "database_properties are written by editors" on public.database_properties
+    for all to authenticated
+    using (public.page_role(database_page_id) in ('owner', 'editor'))
+    with check (public.page_role(database_page_id) in ('owner', 'editor'));
+
+drop policy if exists "database_views are readable by collaborators" on public.database_views;
+create policy "database_views are readable by collaborators" on public.database_views
+    for select to authenticated
+    using (public.page_role(database_page_id) is not null);
+
+drop policy if exists "database_views are written by editors" on public.database_views;
+create policy "database_views are written by editors" on public.database_views
+    for all to authenticated
+    using (public.page_role(database_page_id) in ('owner', 'editor'))
+    with check (public.page_role(database_page_id) in ('owner', 'editor'));
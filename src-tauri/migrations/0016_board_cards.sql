@@ -0,0 +1,53 @@
+-- 0016_board_cards
+--
+-- Moving a card on a board changes the row's status and its place among
+-- its siblings. Both happen here in one transaction so a failed move can't
+-- leave a card in the new column at its old position, or the reverse.
+
+create or replace function public.move_board_card(
+    p_page_id uuid,
+    p_property_id uuid,
+    p_value jsonb,
+    p_positions jsonb
+)
+returns jsonb
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    database_id uuid;
+    card jsonb;
+begin
+    select parent_page_id into database_id from public.pages where id = p_page_id;
+    if database_id is null
+        or public.page_role(database_id) is null
+        or public.page_role(database_id) not in ('owner', 'editor') then
+        raise exception 'You don''t have permission to edit this board';
+    end if;
+    if exists (
+        select 1
+        from jsonb_to_recordset(p_positions) as x(id uuid, position integer)
+        left join public.pages p on p.id = x.id
+        where p.parent_page_id is distinct from database_id
+    ) then
+        raise exception 'Positions must be for cards on the same board';
+    end if;
+
+    update public.pages p
+    set position = x.position
+    from jsonb_to_recordset(p_positions) as x(id uuid, position integer)
+    where p.id = x.id;
+
+    update public.pages
+    set properties = case
+            when p_value is null or p_value = 'null'::jsonb then properties - p_property_id::text
+            else jsonb_set(properties, array[p_property_id::text], p_value)
+        end,
+        updated_at = now()
+    where id = p_page_id
+    returning to_jsonb(pages.*) into card;
+
+    return card;
+end;
+$$;
@@ -0,0 +1,45 @@
+-- 0017_restore_page_version
+--
+-- Restoring a version replaces a page's title and every block. Doing it in
+-- one transaction means a failed insert can't leave the page empty. It runs
+-- as definer behind its own role check, so editors can restore the title of
+-- a page they don't own.
+
+create or replace function public.restore_page_version(p_version_id bigint)
+returns void
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    version public.page_versions;
+begin
+    select * into version from public.page_versions where id = p_version_id;
+    if version.id is null then
+        raise exception 'Version not found';
+    end if;
+    if coalesce(public.page_role(version.page_id), '') not in ('owner', 'editor') then
+        raise exception 'You don''t have permission to edit this page';
+    end if;
+
+    update public.pages
+    set title = version.title,
+        updated_at = now()
+    where id = version.page_id;
+
+    delete from public.blocks where page_id = version.page_id;
+
+    insert into public.blocks (id, page_id, type, "order", content, parent_block_id, created_at, updated_at)
+    select
+        x.id,
+        version.page_id,
+        coalesce(x.type, 'text'),
+        coalesce(x."order", 0),
+        coalesce(x.content, ''),
+        x.parent_block_id,
+        now(),
+        now()
+    from jsonb_to_recordset(version.blocks)
+        as x(id bigint, type text, "order" integer, content text, parent_block_id bigint);
+end;
+$$;
@@ -0,0 +1,129 @@
+-- 0018_sign_in
+--
+-- Sign up and sign in happen before there is a user JWT, so they run with
+-- the project key. Rather than opening `users` to that key, they go through
+-- these functions, which touch only the row for the given email. Passwords
+-- are hashed and checked here (bcrypt via pgcrypto), so a password hash
+-- never leaves the database.
+
+create extension if not exists pgcrypto with schema extensions;
+
+-- Replaced by sign_in below, which checks the password instead of
+-- returning the hash.
+drop function if exists public.user_for_sign_in(text);
+
+create or replace function public.email_exists(p_email text)
+returns boolean
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select exists (select 1 from public.users where email = p_email);
+$$;
+
+-- Returns the new user's id, or null when the email is already taken.
+-- `p_password` is the plain password; only its bcrypt hash is stored.
+create or replace function public.sign_up_user(
+    p_id bigint,
+    p_email text,
+    p_password text,
+    p_first_name text,
+    p_last_name text,
+    p_avatar_url text
+)
+returns bigint
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    v_id bigint;
+begin
+    insert into public.users (id, email, password, first_name, last_name, avatar_url)
+    values (
+        p_id,
+        p_email,
+        extensions.crypt(p_password, extensions.gen_salt('bf')),
+        coalesce(p_first_name, ''),
+        coalesce(p_last_name, ''),
+        coalesce(p_avatar_url, '')
+    )
+    on conflict (email) do nothing
+    returning id into v_id;
+    return v_id;
+end;
+$$;
+
+-- Accounts created before this migration hold an argon2 hash, which
+-- pgcrypto can't check. For those this returns the hash's parameters and
+-- salt (everything but the hash itself) so the app can hash the password
+-- the same way; null for every other account.
+create or replace function public.legacy_password_salt(p_email text)
+returns text
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select regexp_replace(password, '\$[^$]*$', '')
+    from public.users
+    where email = p_email and password like '$argon2%';
+$$;
+
+-- Checks a password and returns `{"status": "ok", "user": {...}}`, or a
+-- status of `not_found` or `invalid_password`. For an argon2 account,
+-- `p_legacy_hash` is the app's hash of the password; once it matches, the
+-- stored hash is replaced with a bcrypt one and the argon2 path is no
+-- longer used for that account.
+create or replace function public.sign_in(
+    p_email text,
+    p_password text,
+    p_legacy_hash text default null
+)
+returns json
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    account public.users;
+begin
+    select * into account from public.users where email = p_email;
+    if account.id is null then
+        return json_build_object('status', 'not_found');
+    end if;
+
+    if account.password like '$argon2%' then
+        if p_legacy_hash is distinct from account.password then
+            return json_build_object('status', 'invalid_password');
+        end if;
+        update public.users
+        set password = extensions.crypt(p_password, extensions.gen_salt('bf'))
+        where id = account.id;
+    elsif account.password is distinct from extensions.crypt(p_password, account.password) then
+        return json_build_object('status', 'invalid_password');
+    end if;
+
+    return json_build_object(
+        'status', 'ok',
+        'user', json_build_object(
+            'id', account.id::text,
+            'email', account.email,
+            'first_name', account.first_name,
+            'last_name', account.last_name,
+            'avatar_url', account.avatar_url,
+            'created_at', account.created_at
+        )
+    );
+end;
+$$;
+
+revoke all on function public.email_exists(text) from public;
+revoke all on function public.sign_up_user(bigint, text, text, text, text, text) from public;
+revoke all on function public.legacy_password_salt(text) from public;
+revoke all on function public.sign_in(text, text, text) from public;
+grant execute on function public.email_exists(text) to anon, authenticated;
+grant execute on function public.sign_up_user(bigint, text, text, text, text, text) to anon, authenticated;
+grant execute on function public.legacy_password_salt(text) to anon, authenticated;
+grant execute on function public.sign_in(text, text, text) to anon, authenticated;
@@ -0,0 +1,136 @@
+-- 0019_access_tokens
+--
+-- User JWTs are signed here rather than in the app, so the project's JWT
+-- secret never has to ship with it. The secret is read from Vault: store the
+-- JWT secret from Project Settings → API once with
+--
+--     select vault.create_secret('<jwt secret>', 'jwt_secret');
+--
+-- `sign_in` returns a token after checking the password, and
+-- `refresh_session` trades a still-valid token for a new one. Nothing else
+-- can ask for a token, so only the user's own password or session yields
+-- one.
+
+create schema if not exists private;
+revoke all on schema private from public, anon, authenticated;
+
+create or replace function private.base64url(p_data bytea)
+returns text
+language sql
+immutable
+as $$
+    select translate(rtrim(replace(encode(p_data, 'base64'), E'\n', ''), '='), '+/', '-_');
+$$;
+
+-- Signs an HS256 token for the user: role `authenticated` for the REST API,
+-- the `user_id` claim that requesting_user_id() reads, and the profile the
+-- app shows for the signed-in user. Valid for an hour.
+create or replace function private.access_token(p_user public.users)
+returns text
+language plpgsql
+stable
+security definer
+set search_path = public
+as $$
+declare
+    secret text;
+    header text;
+    payload text;
+    signing_input text;
+begin
+    select decrypted_secret into secret from vault.decrypted_secrets where name = 'jwt_secret';
+    if secret is null then
+        raise exception 'The jwt_secret Vault secret is not set';
+    end if;
+
+    header := private.base64url(convert_to('{"alg":"HS256","typ":"JWT"}', 'utf8'));
+    payload := private.base64url(convert_to(json_build_object(
+        'sub', p_user.id::text,
+        'user_id', p_user.id::text,
+        'email', p_user.email,
+        'first_name', p_user.first_name,
+        'last_name', p_user.last_name,
+        'avatar_url', p_user.avatar_url,
+        'created_at', p_user.created_at,
+        'role', 'authenticated',
+        'aud', 'authenticated',
+        'iat', extract(epoch from now())::bigint,
+        'exp', extract(epoch from now())::bigint + 3600
+    )::text, 'utf8'));
+    signing_input := header || '.' || payload;
+    return signing_input || '.' || private.base64url(extensions.hmac(signing_input, secret, 'sha256'));
+end;
+$$;
+
+create or replace function private.session_for(p_user public.users)
+returns json
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select json_build_object(
+        'status', 'ok',
+        'access_token', private.access_token(p_user),
+        'user', json_build_object(
+            'id', p_user.id::text,
+            'email', p_user.email,
+            'first_name', p_user.first_name,
+            'last_name', p_user.last_name,
+            'avatar_url', p_user.avatar_url,
+            'created_at', p_user.created_at
+        )
+    );
+$$;
+
+-- Same as 0018, with the session's access token alongside the user.
+create or replace function public.sign_in(
+    p_email text,
+    p_password text,
+    p_legacy_hash text default null
+)
+returns json
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    account public.users;
+begin
+    select * into account from public.users where email = p_email;
+    if account.id is null then
+        return json_build_object('status', 'not_found');
+    end if;
+
+    if account.password like '$argon2%' then
+        if p_legacy_hash is distinct from account.password then
+            return json_build_object('status', 'invalid_password');
+        end if;
+        update public.users
+        set password = extensions.crypt(p_password, extensions.gen_salt('bf'))
+        where id = account.id;
+    elsif account.password is distinct from extensions.crypt(p_password, account.password) then
+        return json_build_object('status', 'invalid_password');
+    end if;
+
+    return private.session_for(account);
+end;
+$$;
+
+-- A new token for the caller, with their current profile; null when the
+-- user no longer exists.
+create or replace function public.refresh_session()
+returns json
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select private.session_for(u) from public.users u where u.id = public.requesting_user_id();
+$$;
+
+revoke all on function private.base64url(bytea) from public;
+revoke all on function private.access_token(public.users) from public;
+revoke all on function private.session_for(public.users) from public;
+revoke all on function public.refresh_session() from public;
+grant execute on function public.refresh_session() to authenticated;
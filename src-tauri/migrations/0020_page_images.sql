@@ -0,0 +1,136 @@
+-- 0020_page_images
+--
+-- Which pages use which images, as their cover or in a block. Storage
+-- policies look an object's key up here instead of scanning page content
+-- with LIKE, which was slow and treated `%` and `_` in a key as wildcards.
+-- Triggers keep it in step with pages and blocks; it has no policies of its
+-- own and is only read through the security definer functions below.
+
+create table if not exists public.page_images (
+    key text not null,
+    page_id uuid not null references public.pages (id) on delete cascade,
+    primary key (key, page_id)
+);
+
+create index if not exists page_images_page_id_idx on public.page_images (page_id);
+
+alter table public.page_images enable row level security;
+
+-- The keys of images-bucket URLs in `p_text`, in public, signed or
+-- authenticated form, without any query string.
+create or replace function public.image_keys(p_text text)
+returns setof text
+language sql
+immutable
+as $$
+    select distinct m.parts[1]
+    from regexp_matches(
+        coalesce(p_text, ''),
+        '/storage/v1/object/(?:public|sign|authenticated)/images/([^?#"''<>\s)]+)',
+        'g'
+    ) as m (parts);
+$$;
+
+create or replace function public.sync_page_images(p_page_ids uuid[])
+returns void
+language sql
+security definer
+set search_path = public
+as $$
+    delete from public.page_images where page_id = any (p_page_ids);
+
+    insert into public.page_images (key, page_id)
+    select distinct k.key, p.id
+    from public.pages p
+    cross join lateral (
+        select public.image_keys(p.cover_url) as key
+        union
+        select public.image_keys(b.content)
+        from public.blocks b
+        where b.page_id = p.id
+    ) k
+    where p.id = any (p_page_ids)
+    on conflict do nothing;
+$$;
+
+revoke all on function public.sync_page_images(uuid[]) from public, anon, authenticated;
+
+-- Blocks are resynced once per statement, so inserting a page's blocks in
+-- one request rescans the page once rather than once per block.
+create or replace function public.blocks_sync_page_images()
+returns trigger
+language plpgsql
+security definer
+set search_path = public
+as $$
+begin
+    if tg_op = 'INSERT' then
+        perform public.sync_page_images(array(select distinct page_id from new_rows));
+    elsif tg_op = 'UPDATE' then
+        perform public.sync_page_images(array(
+            select page_id from new_rows union select page_id from old_rows
+        ));
+    else
+        perform public.sync_page_images(array(select distinct page_id from old_rows));
+    end if;
+    return null;
+end;
+$$;
+
+drop trigger if exists blocks_sync_page_images_insert on public.blocks;
+create trigger blocks_sync_page_images_insert
+    after insert on public.blocks
+    referencing new table as new_rows
+    for each statement execute function public.blocks_sync_page_images();
+
+drop trigger if exists blocks_sync_page_images_update on public.blocks;
+create trigger blocks_sync_page_images_update
+    after update on public.blocks
+    referencing old table as old_rows new table as new_rows
+    for each statement execute function public.blocks_sync_page_images();
+
+drop trigger if exists blocks_sync_page_images_delete on public.blocks;
+create trigger blocks_sync_page_images_delete
+    after delete on public.blocks
+    referencing old table as old_rows
+    for each statement execute function public.blocks_sync_page_images();
+
+create or replace function public.pages_sync_page_images()
+returns trigger
+language plpgsql
+security definer
+set search_path = public
+as $$
+begin
+    perform public.sync_page_images(array[new.id]);
+    return null;
+end;
+$$;
+
+drop trigger if exists pages_sync_page_images on public.pages;
+create trigger pages_sync_page_images
+    after insert or update of cover_url on public.pages
+    for each row execute function public.pages_sync_page_images();
+
+-- Backfill from the pages and blocks written so far.
+select public.sync_page_images(array(select id from public.pages));
+
+-- Same as 0012, but an exact lookup of the key.
+create or replace function public.is_shared_image(p_key text)
+returns boolean
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select exists (
+        select 1
+        from public.page_images i
+        join public.share_links l on l.page_id = i.page_id
+        join public.pages p on p.id = i.page_id
+        where i.key = p_key
+          and l.disabled_at is null
+          and (l.expires_at is null or l.expires_at > now())
+          and p.deleted_at is null
+    );
+$$;
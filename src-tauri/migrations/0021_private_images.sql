@@ -0,0 +1,33 @@
+-- 0021_private_images
+--
+-- The images bucket was public, so anyone with an image's URL could read
+-- it. It is now private: the app shows images through short-lived signed
+-- URLs, which Storage only issues to callers that can read the object.
+-- Uploaders keep reading their own images (0002), link readers the images of
+-- shared pages (0012, 0020), and everyone else the images on pages they can
+-- read, looked up in page_images. Avatars stay public.
+
+update storage.buckets set public = false where id = 'images';
+
+create or replace function public.is_readable_image(p_key text)
+returns boolean
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    select exists (
+        select 1
+        from public.page_images i
+        where i.key = p_key
+          and public.page_role(i.page_id) is not null
+    );
+$$;
+
+revoke all on function public.is_readable_image(text) from public, anon;
+grant execute on function public.is_readable_image(text) to authenticated;
+
+drop policy if exists "images on readable pages are readable" on storage.objects;
+create policy "images on readable pages are readable" on storage.objects
+    for select to authenticated
+    using (bucket_id = 'images' and public.is_readable_image(name));
@@ -0,0 +1,7 @@
+-- 0022_purge_trash_search_path
+--
+-- purge_trash (0004) runs with the caller's search_path. Pin it like the
+-- other functions so an object earlier on the path can't shadow
+-- public.pages.
+
+alter function public.purge_trash(integer) set search_path = public;
@@ -0,0 +1,50 @@
+-- 0023_restore_trashed_pages
+--
+-- Restores a deletion (a trashed root page and the subpages trashed with it)
+-- in one transaction, so a failure can't bring back the subpages without
+-- their root. The root goes back under its parent if that is still live,
+-- else to the top level, and after its new siblings. Runs as the caller, so
+-- RLS still decides which rows may change.
+
+create or replace function public.restore_trashed_pages(p_root_id uuid)
+returns setof public.pages
+language plpgsql
+security invoker
+set search_path = public
+as $$
+declare
+    root public.pages;
+    parent uuid;
+begin
+    select * into root from public.pages where id = p_root_id and deleted_at is not null;
+    if root.id is null then
+        return;
+    end if;
+
+    select id into parent
+    from public.pages
+    where id = root.parent_page_id and deleted_at is null;
+
+    return query
+    update public.pages
+    set deleted_at = null, trashed_root_id = null
+    where trashed_root_id = p_root_id and id <> p_root_id
+    returning *;
+
+    return query
+    update public.pages
+    set deleted_at = null,
+        trashed_root_id = null,
+        parent_page_id = parent,
+        position = (
+            select coalesce(max(s.position) + 1, 0)
+            from public.pages s
+            where s.workspace_id is not distinct from root.workspace_id
+              and s.parent_page_id is not distinct from parent
+              and s.deleted_at is null
+              and s.id <> p_root_id
+        )
+    where id = p_root_id
+    returning *;
+end;
+$$;
@@ -0,0 +1,20 @@
+-- 0024_workspace_page_listing
+--
+-- `fetch_pages` lists a workspace rather than a user's pages, so the
+-- keyset indexes from 0009 move from user_id to workspace_id. Trashed pages
+-- are never listed there, so they're left out of the indexes.
+drop index if exists public.pages_user_id_updated_at_id_idx;
+drop index if exists public.pages_user_id_created_at_id_idx;
+drop index if exists public.pages_user_id_title_key_id_idx;
+drop index if exists public.pages_user_id_position_id_idx;
+drop index if exists public.pages_title_key_prefix_idx;
+create index if not exists pages_workspace_id_updated_at_id_idx
+    on public.pages (workspace_id, updated_at desc, id desc) where deleted_at is null;
+create index if not exists pages_workspace_id_created_at_id_idx
+    on public.pages (workspace_id, created_at desc, id desc) where deleted_at is null;
+create index if not exists pages_workspace_id_title_key_id_idx
+    on public.pages (workspace_id, title_key, id) where deleted_at is null;
+create index if not exists pages_workspace_id_position_id_idx
+    on public.pages (workspace_id, position, id) where deleted_at is null;
+create index if not exists pages_workspace_id_title_key_prefix_idx
+    on public.pages (workspace_id, title_key text_pattern_ops) where deleted_at is null;
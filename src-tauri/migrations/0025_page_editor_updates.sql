@@ -0,0 +1,28 @@
+-- 0025_page_editor_updates
+--
+-- Editors may change pages they don't own. A page's owner never changes, so
+-- an editor can't take it over by rewriting user_id.
+
+drop policy if exists "shared pages are updatable by editors" on public.pages;
+create policy "shared pages are updatable by editors" on public.pages
+    for update to authenticated
+    using (public.page_role(id) in ('owner', 'editor'))
+    with check (public.page_role(id) in ('owner', 'editor'));
+
+create or replace function public.keep_page_owner()
+returns trigger
+language plpgsql
+set search_path = public
+as $$
+begin
+    if new.user_id is distinct from old.user_id then
+        raise exception 'A page''s owner can''t be changed';
+    end if;
+    return new;
+end;
+$$;
+
+drop trigger if exists keep_page_owner on public.pages;
+create trigger keep_page_owner
+    before update on public.pages
+    for each row execute function public.keep_page_owner();
@@ -0,0 +1,74 @@
+-- 0026_page_workspace_rules
+--
+-- Keeps pages inside workspaces their creator belongs to: subpages follow
+-- their parent, only non-guest members create pages, and a page can't be
+-- moved to another workspace or under a page of one.
+
+-- Same as 0013, except that a subpage always joins its parent's workspace,
+-- whatever workspace_id it was inserted with.
+create or replace function public.set_page_workspace()
+returns trigger
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    parent_workspace uuid;
+begin
+    if new.parent_page_id is not null then
+        select workspace_id into parent_workspace from public.pages where id = new.parent_page_id;
+        if found then
+            new.workspace_id := parent_workspace;
+        end if;
+    end if;
+    if new.workspace_id is null then
+        select a.workspace_id into new.workspace_id
+        from public.active_workspaces a
+        join public.workspace_members m
+            on m.workspace_id = a.workspace_id and m.user_id = a.user_id
+        where a.user_id = new.user_id and m.role <> 'guest';
+    end if;
+    if new.workspace_id is null then
+        new.workspace_id := public.ensure_personal_workspace(new.user_id);
+    end if;
+    return new;
+end;
+$$;
+
+-- Only owners, admins and members add pages to a workspace. Restrictive, so
+-- it applies on top of the owner policy from 0001 rather than beside it.
+drop policy if exists "pages are created by workspace members" on public.pages;
+create policy "pages are created by workspace members" on public.pages
+    as restrictive
+    for insert to authenticated
+    with check (public.workspace_role(workspace_id) in ('owner', 'admin', 'member'));
+
+-- A page stays in the workspace it was created in, and can only be nested
+-- under pages of that workspace, so an update can't move it somewhere the
+-- editor isn't a member.
+create or replace function public.keep_page_workspace()
+returns trigger
+language plpgsql
+security definer
+set search_path = public
+as $$
+declare
+    parent_workspace uuid;
+begin
+    if old.workspace_id is not null and new.workspace_id is distinct from old.workspace_id then
+        raise exception 'A page can''t be moved to another workspace';
+    end if;
+    if new.parent_page_id is not null and new.parent_page_id is distinct from old.parent_page_id then
+        select workspace_id into parent_workspace from public.pages where id = new.parent_page_id;
+        if parent_workspace is distinct from new.workspace_id then
+            raise exception 'A page can only be nested under a page in its workspace';
+        end if;
+    end if;
+    return new;
+end;
+$$;
+
+drop trigger if exists pages_keep_workspace on public.pages;
+create trigger pages_keep_workspace
+    before update on public.pages
+    for each row execute function public.keep_page_workspace();
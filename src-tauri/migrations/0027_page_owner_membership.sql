@@ -0,0 +1,73 @@
+-- 0027_page_owner_membership
+--
+-- A page's creator only keeps owner rights while they belong to the page's
+-- workspace, so leaving a workspace (or being removed) gives up its pages
+-- like everyone else's. page_role is otherwise the same as in 0013.
+
+create or replace function public.page_role(p_page_id uuid)
+returns text
+language sql
+stable
+security definer
+set search_path = public
+as $$
+    with recursive ancestors (id, parent_page_id, depth) as (
+        select p.id, p.parent_page_id, 0
+        from public.pages p
+        where p.id = p_page_id
+        union all
+        select p.id, p.parent_page_id, a.depth + 1
+        from public.pages p
+        join ancestors a on p.id = a.parent_page_id
+        where a.depth < 64
+    )
+    select case
+        when exists (
+            select 1
+            from public.pages p
+            join public.workspace_members m on m.workspace_id = p.workspace_id
+            where p.id = p_page_id
+              and p.user_id = public.requesting_user_id()
+              and m.user_id = p.user_id
+        ) then 'owner'
+        when exists (
+            select 1
+            from public.pages p
+            join public.workspace_members m on m.workspace_id = p.workspace_id
+            where p.id = p_page_id
+              and m.user_id = public.requesting_user_id()
+              and m.role <> 'guest'
+        ) then 'editor'
+        else (
+            select s.role
+            from public.page_shares s
+            join ancestors a on a.id = s.page_id
+            where s.user_id = public.requesting_user_id()
+              and (a.depth = 0 or s.include_subpages)
+            order by case s.role when 'editor' then 3 when 'commenter' then 2 else 1 end desc
+            limit 1
+        )
+    end;
+$$;
+
+-- The owner policy from 0001 matches on user_id alone. These restrictive
+-- policies apply on top of it (and every other policy): a page is only
+-- visible or changeable to members of its workspace, or to people it is
+-- shared with. Inserts are covered by 0026.
+drop policy if exists "pages are read within their workspace" on public.pages;
+create policy "pages are read within their workspace" on public.pages
+    as restrictive
+    for select to authenticated
+    using (public.workspace_role(workspace_id) is not null or public.page_role(id) is not null);
+
+drop policy if exists "pages are updated within their workspace" on public.pages;
+create policy "pages are updated within their workspace" on public.pages
+    as restrictive
+    for update to authenticated
+    using (public.workspace_role(workspace_id) is not null or public.page_role(id) is not null);
+
+drop policy if exists "pages are deleted within their workspace" on public.pages;
+create policy "pages are deleted within their workspace" on public.pages
+    as restrictive
+    for delete to authenticated
+    using (public.workspace_role(workspace_id) is not null or public.page_role(id) is not null);
@@ -0,0 +1,24 @@
+-- 0028_page_position_parents
+--
+-- set_page_positions (0003) wrote parent_page_id for every entry, so a
+-- renumbered sibling had its parent rewritten too, and a stale read could
+-- move it back under a parent it had since left. Entries now only change
+-- the parent when they carry a parent_page_id key.
+
+-- p_pages: [{ "id": uuid, "position": int, "parent_page_id"?: uuid | null }]
+create or replace function public.set_page_positions(p_pages jsonb)
+returns setof public.pages
+language sql
+security invoker
+set search_path = public
+as $$
+    update public.pages p
+    set parent_page_id = case
+            when e ? 'parent_page_id' then (e ->> 'parent_page_id')::uuid
+            else p.parent_page_id
+        end,
+        position = (e ->> 'position')::integer
+    from jsonb_array_elements(p_pages) as e
+    where p.id = (e ->> 'id')::uuid
+    returning p.*;
+$$;
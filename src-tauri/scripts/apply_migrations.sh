@@ -0,0 +1,24 @@
+#!/usr/bin/env sh
+# Applies every migration in src-tauri/migrations that the database is
+# missing, in order, through `public.apply_migration` so each one is recorded
+# in schema_migrations. Run it from CI or a maintainer's machine with the
+# project's connection string (Project Settings → Database):
+#
+#     DATABASE_URL=postgresql://... src-tauri/scripts/apply_migrations.sh
+#
+# 0001_initial_schema.sql must have been run once by hand first.
+set -eu
+
+: "${DATABASE_URL:?Set DATABASE_URL to the database connection string}"
+dir="$(cd "$(dirname "$0")/../migrations" && pwd)"
+
+for file in "$dir"/[0-9][0-9][0-9][0-9]_*.sql; do
+    base="$(basename "$file" .sql)"
+    version="$(echo "${base%%_*}" | sed 's/^0*//')"
+    name="${base#*_}"
+    echo "select public.apply_migration($version, '$name', :'sql');" |
+        psql "$DATABASE_URL" --quiet --set ON_ERROR_STOP=1 --set sql="$(cat "$file")"
+done
+
+psql "$DATABASE_URL" --quiet --tuples-only \
+    --command "select 'schema version ' || coalesce(max(version), 0) from public.schema_migrations;"
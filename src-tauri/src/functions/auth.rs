@@ -1,14 +1,14 @@
-use crate::functions::supabase::initialize_supabase_client;
+use crate::functions::session::{clear_session, set_session, Session};
+use crate::functions::trash::schedule_purge;
+use crate::functions::supabase::{initialize_anon_client, initialize_token_client};
 use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    password_hash::{PasswordHash, PasswordHasher},
+    Argon2, Params,
 };
-use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
 use serde::{de::Deserializer, ser::Serializer, Deserialize, Serialize};
 use serde_json::json;
+use supabase_rs::generate_random_id;
 
-use crate::functions::queries::fetch_user_entry_by_email;
 #[derive(Debug, Copy, Clone)]
 pub enum StatusCode {
     Ok = 200,
@@ -51,66 +51,45 @@ pub struct Response<T = serde_json::Value> {
     error: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String,
-    email: String,
-    user_id: String,
-    first_name: String,
-    last_name: String,
-    avatar_url: String,
-    created_at: String,
-    exp: usize,
-}
-
-fn get_jwt_secret() -> Result<String, String> {
-    dotenv::var("JWT_SECRET").map_err(|e| format!("Failed to read JWT_SECRET: {}", e))
-}
-
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn check_if_email_exists(email: String) -> Result<Response<bool>, String> {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_anon_client().await?;
 
-    match supabase_client
-        .select("users")
-        .columns(["email"].to_vec())
-        .eq("email", &email)
-        .execute()
+    let exists = supabase_client
+        .rpc_read("email_exists", json!({ "p_email": email }))
         .await
-    {
-        Ok(data) => {
-            let exists = !data.is_empty();
-            Ok(Response {
-                status: StatusCode::Ok,
-                data: Some(exists),
-                error: if exists {
-                    None
-                } else {
-                    Some("Email does not exist".to_string())
-                },
-            })
-        }
-        Err(e) => Err(format!("Database query failed: {}", e)),
-    }
-}
+        .map_err(|e| format!("Database query failed: {}", e))?
+        .as_bool()
+        .unwrap_or(false);
 
-fn hash_password(password: &str) -> String {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    argon2
-        .hash_password(password.as_bytes(), &salt)
-        .unwrap()
-        .to_string()
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(exists),
+        error: if exists {
+            None
+        } else {
+            Some("Email does not exist".to_string())
+        },
+    })
 }
 
-fn verify_password(password: &str, password_hash: &str) -> bool {
-    let parsed_hash = PasswordHash::new(password_hash).unwrap();
+/// Hashes `password` with the parameters and salt of an argon2 hash from
+/// before passwords were checked in the database, given as that hash minus
+/// its last (hash) field. The result matches the stored hash when the
+/// password is right.
+fn legacy_password_hash(password: &str, salt: &str) -> Result<String, String> {
+    let parsed = PasswordHash::new(salt).map_err(|e| e.to_string())?;
+    let params = Params::try_from(&parsed).map_err(|e| e.to_string())?;
+    let salt = parsed.salt.ok_or("Stored password hash has no salt")?;
     Argon2::default()
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .is_ok()
+        .hash_password_customized(password.as_bytes(), Some(parsed.algorithm), parsed.version, params, salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn sign_up(
     email: String,
     password: String,
@@ -118,24 +97,24 @@ pub async fn sign_up(
     last_name: String,
     avatar_url: String,
 ) -> Result<Response<String>, String> {
-    let supabase_client = initialize_supabase_client().await;
-
+    let supabase_client = initialize_anon_client().await?;
 
+    // Returns the new id, or null when the email is already registered
     let response = supabase_client
-        .insert(
-            "users",
+        .rpc(
+            "sign_up_user",
             json!({
-                "email": email,
-                "password": hash_password(&password),
-                "first_name": first_name,
-                "last_name": last_name,
-                "avatar_url": avatar_url
+                "p_id": generate_random_id(),
+                "p_email": email,
+                "p_password": password,
+                "p_first_name": first_name,
+                "p_last_name": last_name,
+                "p_avatar_url": avatar_url
             }),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
-    if !response.is_empty() {
+    if !response.is_null() {
         Ok(Response {
             status: StatusCode::Ok,
             data: Some("User created successfully".to_string()),
@@ -151,75 +130,133 @@ pub async fn sign_up(
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn sign_in(
     email: String,
     password: String,
 ) -> Result<Response<serde_json::Value>, String> {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_anon_client().await?;
+
+    // The password is checked by the database; only accounts still holding
+    // an argon2 hash need the app to hash it first.
+    let legacy_hash = match supabase_client
+        .rpc_read("legacy_password_salt", json!({ "p_email": email }))
+        .await?
+        .as_str()
+    {
+        Some(salt) => Some(legacy_password_hash(&password, salt)?),
+        None => None,
+    };
 
     let response = supabase_client
-        .select("users")
-        .columns(["email", "password"].to_vec())
-        .eq("email", &email)
-        .execute()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    match response.first() {
-        Some(user) => {
-            let stored_hash = user
-                .get("password")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid user data")?;
-
-            if verify_password(&password, stored_hash) {
-                let expiration = Utc::now()
-                    .checked_add_signed(Duration::seconds(3600))
-                    .expect("Invalid timestamp")
-                    .timestamp() as usize;
-
-                let user_entry = fetch_user_entry_by_email(&email)
-                    .await
-                    .map_err(|e| e.to_string())?
-                    .ok_or("User not found")?;
-                
-                let claims = Claims {
-                    sub: email.clone(),
-                    email: user_entry.email,
-                    user_id: user_entry.id.to_string(),
-                    first_name: user_entry.first_name,
-                    last_name: user_entry.last_name,
-                    avatar_url: user_entry.avatar_url,
-                    created_at: user_entry.created_at,
-                    exp: expiration,
-                };
-
-                let token = encode(
-                    &Header::default(),
-                    &claims,
-                    &EncodingKey::from_secret(get_jwt_secret()?.as_bytes()),
-                )
-                .map_err(|e| e.to_string())?;
-
-                let response = Response {
-                    status: StatusCode::Ok,
-                    data: Some(serde_json::json!({ "token": token })),
-                    error: None,
-                };
-
-                Ok(response)
-            } else {
-                Ok(Response {
-                    status: StatusCode::Unauthorized,
-                    data: None,
-                    error: Some("Invalid password".to_string()),
-                })
-            }
+        .rpc(
+            "sign_in",
+            json!({
+                "p_email": email,
+                "p_password": password,
+                "p_legacy_hash": legacy_hash,
+            }),
+        )
+        .await?;
+
+    match response["status"].as_str() {
+        Some("ok") => {
+            let session = Session::from_rpc(&response).ok_or("Invalid user data")?;
+            set_session(session);
+            schedule_purge();
+
+            // The frontend reads the user's profile from this token's claims
+            Ok(Response {
+                status: StatusCode::Ok,
+                data: Some(json!({ "token": response["access_token"] })),
+                error: None,
+            })
         }
-        None => Ok(Response {
+        Some("invalid_password") => Ok(Response {
+            status: StatusCode::Unauthorized,
+            data: None,
+            error: Some("Invalid password".to_string()),
+        }),
+        _ => Ok(Response {
             status: StatusCode::NotFound,
             data: None,
             error: Some("User not found".to_string()),
         }),
     }
 }
+
+/// Re-establishes the backend session from a token issued by `sign_in`, e.g.
+/// after the webview reloads with a token still in session storage. The
+/// database checks the token and answers with a fresh one.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn restore_session(token: String) -> Result<Response<serde_json::Value>, String> {
+    let supabase_client = initialize_token_client(token).await?;
+    let session = match supabase_client.rpc_read("refresh_session", json!({})).await {
+        Ok(result) => Session::from_rpc(&result),
+        Err(e) => {
+            tracing::debug!(error = %e, "session token rejected");
+            None
+        }
+    };
+    let Some(session) = session else {
+        return Ok(Response {
+            status: StatusCode::Unauthorized,
+            data: None,
+            error: Some("Invalid session token".to_string()),
+        });
+    };
+
+    let token = session.access_token.clone();
+    set_session(session);
+    schedule_purge();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "token": token })),
+        error: None,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn sign_out() -> Result<Response<serde_json::Value>, String> {
+    clear_session();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: None,
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::password_hash::{rand_core::OsRng, SaltString};
+
+    fn stored_hash(password: &str) -> String {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap()
+            .to_string()
+    }
+
+    // What `legacy_password_salt` returns: the hash without its last field
+    fn salt_of(hash: &str) -> &str {
+        &hash[..hash.rfind('$').unwrap()]
+    }
+
+    #[test]
+    fn legacy_hash_matches_the_stored_hash_for_the_right_password() {
+        let hash = stored_hash("correct horse");
+        assert_eq!(legacy_password_hash("correct horse", salt_of(&hash)).unwrap(), hash);
+    }
+
+    #[test]
+    fn legacy_hash_differs_for_a_wrong_password() {
+        let hash = stored_hash("correct horse");
+        assert_ne!(legacy_password_hash("battery staple", salt_of(&hash)).unwrap(), hash);
+    }
+}
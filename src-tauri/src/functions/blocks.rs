@@ -1,8 +1,12 @@
-use crate::functions::supabase::initialize_supabase_client;
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::links::reindex_block_links;
 use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::{forbidden, page_role};
+use crate::functions::storage::{sign_images, stored_image_url};
+use crate::functions::versions::{snapshot_before_change, SnapshotReason};
 use serde::{Deserialize, Serialize};
-
-use super::supabase;
+use urlencoding::encode;
+use supabase_rs::generate_random_id;
 
 #[derive(Debug, Deserialize, Serialize)] // Add Serialize here
 pub struct Block {
@@ -17,15 +21,45 @@ pub struct Block {
     pub parent_block_id: Option<String>, // Assuming this can be null
 }
 
+impl Block {
+    pub fn from_row(block: &serde_json::Value) -> Block {
+        Block {
+            // Handle id as either a number or a string
+            id: if block["id"].is_i64() || block["id"].is_u64() {
+                block["id"].as_i64().map_or_else(
+                    || block["id"].as_u64().unwrap_or_default().to_string(),
+                    |id| id.to_string()
+                )
+            } else {
+                block["id"].as_str().unwrap_or_default().to_string()
+            },
+            created_at: block["created_at"].as_str().unwrap_or_default().to_string(),
+            updated_at: block["updated_at"].as_str().unwrap_or_default().to_string(),
+            block_type: block["type"].as_str().unwrap_or_default().to_string(),
+            order: block["order"].as_i64().unwrap_or_default() as i32,
+            content: block["content"].as_str().unwrap_or_default().to_string(),
+            page_id: block["page_id"].as_str().unwrap_or_default().to_string(),
+            parent_block_id: block["parent_block_id"]
+                .as_str()
+                .map(|s| s.to_string()),
+        }
+    }
+}
+
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
 pub async fn fetch_blocks(page_id: String) -> Result<Response<serde_json::Value>, String> {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_user_client().await?;
+    if page_role(&supabase_client, &page_id).await?.is_none() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    }
     let data = supabase_client
-        .select("blocks")
-        .eq("page_id", &page_id)
-        .execute()
-        .await
-        .map_err(|e| e.to_string())?;
+        .select("blocks", &format!("page_id=eq.{}", encode(&page_id)))
+        .await?;
 
     if data.is_empty() {
         return Ok(Response {
@@ -35,36 +69,22 @@ pub async fn fetch_blocks(page_id: String) -> Result<Response<serde_json::Value>
         });
     }
 
-    let mut blocks: Vec<Block> = data
-    .iter()
-    .map(|block| Block {
-        // Handle id as either a number or a string
-        id: if block["id"].is_i64() || block["id"].is_u64() {
-            block["id"].as_i64().map_or_else(
-                || block["id"].as_u64().unwrap_or_default().to_string(),
-                |id| id.to_string()
-            )
-        } else {
-            block["id"].as_str().unwrap_or_default().to_string()
-        },
-        created_at: block["created_at"].as_str().unwrap_or_default().to_string(),
-        updated_at: block["updated_at"].as_str().unwrap_or_default().to_string(),
-        block_type: block["type"].as_str().unwrap_or_default().to_string(),
-        order: block["order"].as_i64().unwrap_or_default() as i32,
-        content: block["content"].as_str().unwrap_or_default().to_string(),
-        page_id: block["page_id"].as_str().unwrap_or_default().to_string(),
-        parent_block_id: block["parent_block_id"]
-            .as_str()
-            .map(|s| s.to_string()),
-    })
-    .collect();
-    
+    let mut blocks: Vec<Block> = data.iter().map(Block::from_row).collect();
 
     blocks.sort_by(|a, b| {
         // Parse timestamp, adding UTC timezone if missing
         a.order.cmp(&b.order)
     });
-    
+    sign_images(
+        &supabase_client,
+        blocks
+            .iter_mut()
+            .filter(|block| block.block_type == "image")
+            .map(|block| &mut block.content)
+            .collect(),
+    )
+    .await;
+
     Ok(Response {
         status: StatusCode::Ok,
         data: Some(serde_json::json!(blocks)),
@@ -73,11 +93,12 @@ pub async fn fetch_blocks(page_id: String) -> Result<Response<serde_json::Value>
 }
 
 async fn block_exists(block_id: String) -> bool {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = match initialize_user_client().await {
+        Ok(client) => client,
+        Err(_) => return false,
+    };
     let data = match supabase_client
-        .select("blocks")
-        .eq("id", &block_id)
-        .execute()
+        .select("blocks", &format!("select=id&id=eq.{}", encode(&block_id)))
         .await
     {
         Ok(data) => data,
@@ -90,6 +111,7 @@ async fn block_exists(block_id: String) -> bool {
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(block_id = %block_id, page_id = %page_id))]
 pub async fn update_block(
     block_id: String,
     page_id: String,
@@ -98,7 +120,17 @@ pub async fn update_block(
     order: i32,
     block_type: String,
 ) -> Result<Response<serde_json::Value>, String> {
-    let supbase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_user_client().await?;
+    if !page_role(&supabase_client, &page_id).await?.is_some_and(|role| role.can_edit()) {
+        return Ok(forbidden());
+    }
+    snapshot_before_change(&supabase_client, &page_id, SnapshotReason::Edit).await;
+    // The webview shows images through signed URLs; store the plain one
+    let content = if block_type == "image" {
+        stored_image_url(&content)
+    } else {
+        content
+    };
 
     let body = serde_json::json!({
         "id": block_id,
@@ -117,23 +149,32 @@ pub async fn update_block(
         "type": block_type,
         "updated_at": chrono::Utc::now().to_rfc3339(),
     });
-    println!("body: {:?}", body);
-
-    let result = supbase_client
-    .update("blocks", &block_id, body).await?;
+    let rows = supabase_client
+        .update("blocks", &format!("id=eq.{}", encode(&block_id)), body)
+        .await?;
+    let Some(row) = rows.first() else {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Block not found".to_string()),
+        });
+    };
+    if let Ok(id) = block_id.parse::<i64>() {
+        if let Err(e) = reindex_block_links(&supabase_client, id, &page_id, &content).await {
+            tracing::warn!(error = %e, "failed to index page links");
+        }
+    }
 
-    // Parse the string result into a JSON value
-    let result_json = serde_json::from_str::<serde_json::Value>(&result.to_string())
-        .map_err(|e| e.to_string())?;
-    println!("result_json: {:?}", result_json);
+    tracing::debug!("block updated");
     Ok(Response {
         status: StatusCode::Ok,
-        data: Some(result_json),
+        data: Some(serde_json::json!(Block::from_row(row))),
         error: None,
     })
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
 pub async fn create_block(
     page_id: String,
     content: String,
@@ -141,9 +182,22 @@ pub async fn create_block(
     order: i32,
     block_type: String,
 ) -> Result<Response<serde_json::Value>, String> {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_user_client().await?;
+    if !page_role(&supabase_client, &page_id).await?.is_some_and(|role| role.can_edit()) {
+        return Ok(forbidden());
+    }
+    snapshot_before_change(&supabase_client, &page_id, SnapshotReason::Edit).await;
+    // The webview shows images through signed URLs; store the plain one
+    let content = if block_type == "image" {
+        stored_image_url(&content)
+    } else {
+        content
+    };
 
+    // Block ids are random 64-bit integers, as supabase_rs used to generate them
+    let block_id = generate_random_id();
     let body = serde_json::json!({
+        "id": block_id,
         "page_id": page_id,
         "content": content,
         "parent_block_id": if let Some(ref id) = parent_block_id {
@@ -160,13 +214,12 @@ pub async fn create_block(
         "updated_at": chrono::Utc::now().to_rfc3339(),
         "created_at": chrono::Utc::now().to_rfc3339(),
     });
-    println!("body: {:?}", body);
-    let result = supabase_client
-        .insert("blocks", body)
-        .await?;
-    // Parse the string result into a JSON value
-    let result_json = serde_json::from_str::<serde_json::Value>(&result)
-        .map_err(|e| e.to_string())?;
+    supabase_client.insert("blocks", body).await?;
+    if let Err(e) = reindex_block_links(&supabase_client, block_id, &page_id, &content).await {
+        tracing::warn!(error = %e, "failed to index page links");
+    }
+    tracing::debug!(block_id, "block created");
+    let result_json = serde_json::json!(block_id);
 
     Ok(Response {
         status: StatusCode::Ok,
@@ -177,14 +230,25 @@ pub async fn create_block(
 
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(block_id = %block_id))]
 pub async fn delete_block(block_id: String) -> Result<Response<serde_json::Value>, String> {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_user_client().await?;
+
+    let block = supabase_client
+        .select("blocks", &format!("select=page_id&id=eq.{}", encode(&block_id)))
+        .await?;
+    if let Some(page_id) = block.first().and_then(|row| row["page_id"].as_str()) {
+        if !page_role(&supabase_client, page_id).await?.is_some_and(|role| role.can_edit()) {
+            return Ok(forbidden());
+        }
+        snapshot_before_change(&supabase_client, page_id, SnapshotReason::Delete).await;
+    }
+
     let result = supabase_client
-        .delete("blocks", &block_id)
-        .await
-        .map_err(|e| e.to_string())?;
+        .delete("blocks", &format!("id=eq.{}", encode(&block_id)))
+        .await?;
     
-    println!("result: {:?}", result);
+    tracing::debug!(deleted = result.len(), "block deleted");
     
     // Create a result JSON with the deleted block id
     let result_json = serde_json::json!({
@@ -0,0 +1,206 @@
+use crate::functions::databases::{
+    bad_request, database_not_found, find_property, group_rows, load_database, load_page,
+    load_properties, load_rows, normalize_value, Property, PropertyType,
+};
+use crate::functions::pages::Page;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::{forbidden, page_role};
+use crate::functions::supabase::initialize_user_client;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Boards group by this property when none is given, falling back to the
+/// first select.
+const DEFAULT_STATUS_PROPERTY: &str = "Status";
+
+#[derive(Debug, Serialize)]
+pub struct BoardColumn {
+    /// The option the column stands for; `null` for cards without one.
+    pub key: Value,
+    pub label: String,
+    pub color: Option<String>,
+    pub cards: Vec<Page>,
+}
+
+/// The select property a board groups by: `group_by` as an id or name, or
+/// the default status property.
+fn status_property<'a>(properties: &'a [Property], group_by: Option<&str>) -> Result<&'a Property, String> {
+    let selects = || {
+        properties
+            .iter()
+            .filter(|property| property.property_type == PropertyType::Select)
+    };
+    let found = match group_by.filter(|group_by| !group_by.is_empty()) {
+        Some(group_by) => find_property(properties, group_by).or_else(|| {
+            properties
+                .iter()
+                .find(|property| property.name.eq_ignore_ascii_case(group_by))
+        }),
+        None => selects()
+            .find(|property| property.name.eq_ignore_ascii_case(DEFAULT_STATUS_PROPERTY))
+            .or_else(|| selects().next()),
+    };
+    match found {
+        Some(property) if property.property_type == PropertyType::Select => Ok(property),
+        Some(property) => Err(format!("{:?} is not a select property", property.name)),
+        None => Err("Add a select property to use the board".to_string()),
+    }
+}
+
+/// Columns in option order, then cards without a status. Cards keep the
+/// rows' manual order within each column.
+fn board_columns(property: &Property, rows: Vec<Page>) -> Vec<BoardColumn> {
+    let colors: Vec<(String, Option<String>)> = property.options["options"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|option| {
+            let name = option["name"].as_str()?.to_string();
+            Some((name, option["color"].as_str().map(|color| color.to_string())))
+        })
+        .collect();
+    group_rows(Some(property), &property.id, rows)
+        .into_iter()
+        .map(|group| BoardColumn {
+            color: group.key.as_str().and_then(|key| {
+                colors
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .and_then(|(_, color)| color.clone())
+            }),
+            key: group.key,
+            label: group.label,
+            cards: group.rows,
+        })
+        .collect()
+}
+
+/// A database's rows as board columns grouped by a select property.
+/// `group_by` is a property id or name; without it the board uses the
+/// "Status" property, or the first select.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, group_by = ?group_by))]
+pub async fn fetch_board(page_id: String, group_by: Option<String>) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if load_database(&supabase_client, &page_id).await?.is_none() {
+        return Ok(database_not_found());
+    }
+    let properties = load_properties(&supabase_client, &page_id).await?;
+    let property = match status_property(&properties, group_by.as_deref()) {
+        Ok(property) => property,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let rows = load_rows(&supabase_client, &page_id).await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "database_page_id": page_id,
+            "group_by": property,
+            "columns": board_columns(property, rows),
+        })),
+        error: None,
+    })
+}
+
+/// Moves a card to the column for `value` (`null` for no status) at
+/// `position` within that column, or to its end. The status and the new
+/// order are saved together.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(card_id = %card_id, position = ?position))]
+pub async fn move_card(
+    card_id: String,
+    group_by: String,
+    value: Option<String>,
+    position: Option<usize>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let Some(card) = load_page(&supabase_client, &card_id).await? else {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Card not found".to_string()),
+        });
+    };
+    let Some(database_id) = card.parent_page_id.clone() else {
+        return Ok(bad_request("Page is not on a board"));
+    };
+    if load_database(&supabase_client, &database_id).await?.is_none() {
+        return Ok(bad_request("Page is not on a board"));
+    }
+    if !page_role(&supabase_client, &database_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+    let properties = load_properties(&supabase_client, &database_id).await?;
+    let property = match status_property(&properties, Some(&group_by)) {
+        Ok(property) => property,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let value = match normalize_value(property, value.map(Value::String).unwrap_or(Value::Null)) {
+        Ok(value) => value,
+        Err(e) => return Ok(bad_request(e)),
+    };
+
+    // Cards are ordered by their page position, so the card is placed
+    // before the card it lands on, or after the column's last card
+    let mut rows: Vec<Page> = load_rows(&supabase_client, &database_id)
+        .await?
+        .into_iter()
+        .filter(|row| row.id != card_id)
+        .collect();
+    let in_column = |row: &Page| row.properties.get(&property.id).unwrap_or(&Value::Null) == &value;
+    let column: Vec<usize> = rows
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| in_column(row))
+        .map(|(index, _)| index)
+        .collect();
+    let index = match position.and_then(|position| column.get(position)) {
+        Some(&index) => index,
+        None => column.last().map_or(rows.len(), |&index| index + 1),
+    };
+    let mut moved = card;
+    match &value {
+        Value::Null => {
+            if let Some(properties) = moved.properties.as_object_mut() {
+                properties.remove(&property.id);
+            }
+        }
+        value => moved.properties[&property.id] = value.clone(),
+    }
+    rows.insert(index, moved);
+
+    let positions: Vec<Value> = rows
+        .iter_mut()
+        .enumerate()
+        .filter(|(index, row)| row.position != *index as i32 || row.id == card_id)
+        .map(|(index, row)| {
+            row.position = index as i32;
+            json!({ "id": row.id, "position": index })
+        })
+        .collect();
+    let updated = supabase_client
+        .rpc(
+            "move_board_card",
+            json!({
+                "p_page_id": card_id,
+                "p_property_id": property.id,
+                "p_value": value,
+                "p_positions": positions,
+            }),
+        )
+        .await?;
+    tracing::debug!(moved = positions.len(), "card moved");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "card": Page::from_row(&updated),
+            "columns": board_columns(property, rows),
+        })),
+        error: None,
+    })
+}
@@ -0,0 +1,500 @@
+use crate::functions::databases::{bad_request, date_start};
+use crate::functions::pages::{fetch_workspace_pages, Page};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use chrono::{DateTime, Datelike, Local, Months, NaiveDate, Utc};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use urlencoding::encode;
+
+const MAX_CALENDAR_DAYS: i64 = 366;
+const MAX_ENTRY_TEXT: usize = 200;
+/// Most blocks with dates read for one calendar, most recently edited first.
+const MAX_DATED_BLOCKS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Created,
+    Updated,
+    Journal,
+    Mention,
+    Todo,
+    Property,
+}
+
+/// Something on a page that falls on a day.
+#[derive(Debug, Serialize)]
+pub struct CalendarEntry {
+    pub date: NaiveDate,
+    pub kind: EntryKind,
+    pub page_id: String,
+    pub title: String,
+    pub block_id: Option<String>,
+    /// The database property a `property` entry's date comes from.
+    pub property_id: Option<String>,
+    /// The block text for mentions and todos, the property name for dates
+    /// from a database.
+    pub text: Option<String>,
+    pub done: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub entries: Vec<CalendarEntry>,
+}
+
+fn date_pattern() -> &'static Regex {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    DATE.get_or_init(|| Regex::new(r"\b(\d{4}-\d{2}-\d{2})\b").unwrap())
+}
+
+fn todo_pattern() -> &'static Regex {
+    static TODO: OnceLock<Regex> = OnceLock::new();
+    TODO.get_or_init(|| Regex::new(r"^\s*\[( |x|X)\]\s*").unwrap())
+}
+
+/// The local day of a `YYYY-MM-DD` date or an RFC 3339 timestamp.
+fn local_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|at| at.with_timezone(&Local).date_naive())
+        })
+}
+
+fn parse_range(start_date: &str, end_date: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let parse = |date: &str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date {:?}, expected YYYY-MM-DD", date))
+    };
+    let (start, end) = (parse(start_date)?, parse(end_date)?);
+    if end < start {
+        return Err("end_date is before start_date".to_string());
+    }
+    if (end - start).num_days() >= MAX_CALENDAR_DAYS {
+        return Err(format!("Ranges can span at most {} days", MAX_CALENDAR_DAYS));
+    }
+    Ok((start, end))
+}
+
+/// A pattern for the ISO dates in the months `start` to `end` touch, so
+/// only blocks that can have entries in the range are read.
+fn range_pattern(start: NaiveDate, end: NaiveDate) -> String {
+    let mut months: Vec<String> = Vec::new();
+    let mut month = start.with_day(1);
+    while let Some(first) = month.filter(|first| *first <= end) {
+        months.push(first.format("%Y-%m").to_string());
+        month = first.checked_add_months(Months::new(1));
+    }
+    format!("({})-[0-9]{{2}}", months.join("|"))
+}
+
+fn shorten(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= MAX_ENTRY_TEXT {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(MAX_ENTRY_TEXT).collect();
+    short.push('…');
+    short
+}
+
+/// Dates from blocks: every ISO date written in a block is a mention, or a
+/// todo when the block is a todo (type `todo`, or text starting with `[ ]`
+/// or `[x]`).
+async fn block_entries(
+    supabase_client: &RestClient,
+    workspace_id: &str,
+    titles: &HashMap<&str, &str>,
+    (start, end): (NaiveDate, NaiveDate),
+) -> Result<Vec<CalendarEntry>, String> {
+    let rows = supabase_client
+        .select(
+            "blocks",
+            &format!(
+                "select=id,page_id,type,content,pages!inner(workspace_id,deleted_at)&pages.workspace_id=eq.{}&pages.deleted_at=is.null&content=match.{}&order=updated_at.desc&limit={}",
+                encode(workspace_id),
+                encode(&range_pattern(start, end)),
+                MAX_DATED_BLOCKS
+            ),
+        )
+        .await?;
+
+    let mut entries = Vec::new();
+    for row in &rows {
+        let page_id = row["page_id"].as_str().unwrap_or_default();
+        // Blocks of templates aren't listed in `titles`
+        let Some(title) = titles.get(page_id) else {
+            continue;
+        };
+        let content = row["content"].as_str().unwrap_or_default();
+        let checkbox = todo_pattern().captures(content);
+        let is_todo = row["type"].as_str() == Some("todo") || checkbox.is_some();
+        let done = checkbox.as_ref().map(|caps| !caps[1].trim().is_empty());
+        let text = shorten(&todo_pattern().replace(content, ""));
+
+        let mut seen: Vec<NaiveDate> = Vec::new();
+        for caps in date_pattern().captures_iter(content) {
+            let Ok(date) = NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d") else {
+                continue;
+            };
+            if date < start || date > end || seen.contains(&date) {
+                continue;
+            }
+            seen.push(date);
+            entries.push(CalendarEntry {
+                date,
+                kind: if is_todo { EntryKind::Todo } else { EntryKind::Mention },
+                page_id: page_id.to_string(),
+                title: title.to_string(),
+                block_id: Some(match &row["id"] {
+                    Value::String(id) => id.clone(),
+                    other => other.to_string(),
+                }),
+                property_id: None,
+                text: Some(text.clone()),
+                done: if is_todo { Some(done.unwrap_or(false)) } else { None },
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Dates from database rows' date properties. A date range adds an entry
+/// for each of its days within the calendar's range.
+async fn property_entries(
+    supabase_client: &RestClient,
+    workspace_id: &str,
+    pages: &[Page],
+    (start, end): (NaiveDate, NaiveDate),
+) -> Result<Vec<CalendarEntry>, String> {
+    let properties = supabase_client
+        .select(
+            "database_properties",
+            &format!(
+                "select=id,name,database_page_id,pages!inner(workspace_id)&type=eq.date&pages.workspace_id=eq.{}",
+                encode(workspace_id)
+            ),
+        )
+        .await?;
+
+    let mut entries = Vec::new();
+    for property in &properties {
+        let (Some(id), Some(database_id)) = (
+            property["id"].as_str(),
+            property["database_page_id"].as_str(),
+        ) else {
+            continue;
+        };
+        let name = property["name"].as_str().unwrap_or_default();
+        for row in pages
+            .iter()
+            .filter(|page| page.parent_page_id.as_deref() == Some(database_id))
+        {
+            let value = &row.properties[id];
+            let Some(from) = date_start(value).and_then(local_date) else {
+                continue;
+            };
+            let to = value["end"].as_str().and_then(local_date).unwrap_or(from).max(from);
+            let mut day = from.max(start);
+            while day <= to.min(end) {
+                entries.push(CalendarEntry {
+                    date: day,
+                    kind: EntryKind::Property,
+                    page_id: row.id.clone(),
+                    title: row.title.clone(),
+                    block_id: None,
+                    property_id: Some(id.to_string()),
+                    text: Some(name.to_string()),
+                    done: None,
+                });
+                day = day.succ_opt().unwrap_or(NaiveDate::MAX);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Everything dated between `start` and `end` in the active workspace, by
+/// day: pages created, updated and journal pages, dates written in blocks,
+/// dated todos and database date properties.
+async fn calendar_days(
+    supabase_client: &RestClient,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<CalendarDay>, String> {
+    let workspace_id = active_workspace_id(supabase_client).await?;
+    let pages: Vec<Page> = fetch_workspace_pages(supabase_client, &workspace_id)
+        .await?
+        .into_iter()
+        .filter(|page| !page.is_template)
+        .collect();
+    let in_range = |date: &NaiveDate| *date >= start && *date <= end;
+
+    let mut entries: Vec<CalendarEntry> = Vec::new();
+    for page in &pages {
+        let entry = |date, kind| CalendarEntry {
+            date,
+            kind,
+            page_id: page.id.clone(),
+            title: page.title.clone(),
+            block_id: None,
+            property_id: None,
+            text: None,
+            done: None,
+        };
+        let created = local_date(&page.created_at);
+        if let Some(date) = created.filter(in_range) {
+            entries.push(entry(date, EntryKind::Created));
+        }
+        // An edit on the day a page was created adds nothing
+        if let Some(date) = local_date(&page.updated_at).filter(|date| in_range(date) && Some(*date) != created) {
+            entries.push(entry(date, EntryKind::Updated));
+        }
+        if let Some(date) = page.journal_date.as_deref().and_then(local_date).filter(in_range) {
+            entries.push(entry(date, EntryKind::Journal));
+        }
+    }
+
+    let titles: HashMap<&str, &str> = pages
+        .iter()
+        .map(|page| (page.id.as_str(), page.title.as_str()))
+        .collect();
+    entries.extend(block_entries(supabase_client, &workspace_id, &titles, (start, end)).await?);
+    entries.extend(property_entries(supabase_client, &workspace_id, &pages, (start, end)).await?);
+
+    let mut days: BTreeMap<NaiveDate, Vec<CalendarEntry>> = BTreeMap::new();
+    for entry in entries {
+        days.entry(entry.date).or_default().push(entry);
+    }
+    Ok(days
+        .into_iter()
+        .map(|(date, entries)| CalendarDay { date, entries })
+        .collect())
+}
+
+/// Escapes text for an iCalendar property value.
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line at 75 octets, as iCalendar requires.
+fn ics_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+fn ics_summary(entry: &CalendarEntry) -> String {
+    let title = if entry.title.is_empty() { "Untitled" } else { &entry.title };
+    let text = entry.text.as_deref().unwrap_or_default();
+    match entry.kind {
+        EntryKind::Created => format!("Created: {}", title),
+        EntryKind::Updated => format!("Updated: {}", title),
+        EntryKind::Journal => title.to_string(),
+        EntryKind::Mention => title.to_string(),
+        EntryKind::Todo if entry.done == Some(true) => format!("[x] {}", text),
+        EntryKind::Todo => format!("[ ] {}", text),
+        EntryKind::Property => format!("{}: {}", title, text),
+    }
+}
+
+/// The calendar as an iCalendar file of all-day events. UIDs are stable, so
+/// re-importing a later export updates events instead of duplicating them.
+fn to_ics(days: &[CalendarDay]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut ics = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//ZeNote//Calendar//EN",
+        "CALSCALE:GREGORIAN",
+        "X-WR-CALNAME:ZeNote",
+    ] {
+        ics_line(&mut ics, line);
+    }
+    for entry in days.iter().flat_map(|day| &day.entries) {
+        let kind = serde_json::to_value(entry.kind).unwrap_or_default();
+        let uid = format!(
+            "{}-{}-{}-{}@zenote",
+            kind.as_str().unwrap_or_default(),
+            entry.page_id,
+            entry
+                .block_id
+                .as_deref()
+                .or(entry.property_id.as_deref())
+                .unwrap_or("page"),
+            entry.date.format("%Y%m%d")
+        );
+        ics_line(&mut ics, "BEGIN:VEVENT");
+        ics_line(&mut ics, &format!("UID:{}", uid));
+        ics_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        ics_line(&mut ics, &format!("DTSTART;VALUE=DATE:{}", entry.date.format("%Y%m%d")));
+        let next = entry.date.succ_opt().unwrap_or(entry.date);
+        ics_line(&mut ics, &format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")));
+        ics_line(&mut ics, &format!("SUMMARY:{}", ics_text(&ics_summary(entry))));
+        if let Some(text) = entry.text.as_deref().filter(|_| entry.kind == EntryKind::Mention) {
+            ics_line(&mut ics, &format!("DESCRIPTION:{}", ics_text(text)));
+        }
+        ics_line(&mut ics, &format!("X-ZENOTE-PAGE-ID:{}", entry.page_id));
+        ics_line(&mut ics, "END:VEVENT");
+    }
+    ics_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+/// Days between `start_date` and `end_date` (inclusive, YYYY-MM-DD) that
+/// have something dated on them, each with its entries.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(start_date = %start_date, end_date = %end_date))]
+pub async fn fetch_calendar(start_date: String, end_date: String) -> Result<Response<Value>, String> {
+    let (start, end) = match parse_range(&start_date, &end_date) {
+        Ok(range) => range,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let supabase_client = initialize_user_client().await?;
+    let days = calendar_days(&supabase_client, start, end).await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(days)),
+        error: None,
+    })
+}
+
+/// The same entries as `fetch_calendar` as an iCalendar (.ics) file, for
+/// importing into or subscribing from calendar apps. Written to `file_path`
+/// when given, otherwise returned as text.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(start_date = %start_date, end_date = %end_date))]
+pub async fn export_calendar_ics(
+    start_date: String,
+    end_date: String,
+    file_path: Option<String>,
+) -> Result<Response<Value>, String> {
+    let (start, end) = match parse_range(&start_date, &end_date) {
+        Ok(range) => range,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let supabase_client = initialize_user_client().await?;
+    let days = calendar_days(&supabase_client, start, end).await?;
+    let events: usize = days.iter().map(|day| day.entries.len()).sum();
+    let ics = to_ics(&days);
+
+    let data = match file_path.filter(|path| !path.is_empty()) {
+        Some(path) => {
+            std::fs::write(&path, &ics).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            tracing::info!(events, "calendar exported");
+            json!({ "file_path": path, "events": events })
+        }
+        None => json!({ "ics": ics, "events": events }),
+    };
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(data),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn entry(kind: EntryKind, text: Option<&str>) -> CalendarEntry {
+        CalendarEntry {
+            date: date("2024-02-29"),
+            kind,
+            page_id: "p1".to_string(),
+            title: "Plan, v2".to_string(),
+            block_id: Some("42".to_string()),
+            property_id: None,
+            text: text.map(str::to_string),
+            done: None,
+        }
+    }
+
+    #[test]
+    fn text_is_escaped_for_ics() {
+        assert_eq!(ics_text("a;b,c\\d\r\ne\nf"), r"a\;b\,c\\d\ne\nf");
+    }
+
+    #[test]
+    fn todo_summaries_show_their_state() {
+        let mut todo = entry(EntryKind::Todo, Some("Ship it"));
+        assert_eq!(ics_summary(&todo), "[ ] Ship it");
+        todo.done = Some(true);
+        assert_eq!(ics_summary(&todo), "[x] Ship it");
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let mut ics = String::new();
+        ics_line(&mut ics, &"a".repeat(160));
+        let lines: Vec<&str> = ics.split("\r\n").collect();
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1].len(), 75);
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines[2].len(), 12);
+        assert_eq!(lines[3], "");
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        let mut ics = String::new();
+        ics_line(&mut ics, &"é".repeat(50));
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert_eq!(ics.replace("\r\n ", "").trim_end(), "é".repeat(50));
+    }
+
+    #[test]
+    fn events_are_all_day_with_stable_uids() {
+        let days = vec![CalendarDay {
+            date: date("2024-02-29"),
+            entries: vec![entry(EntryKind::Mention, Some("Due 2024-02-29"))],
+        }];
+        let ics = to_ics(&days);
+        assert!(ics.contains("UID:mention-p1-42-20240229@zenote\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240229\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20240301\r\n"));
+        assert!(ics.contains("SUMMARY:Plan\\, v2\r\n"));
+        assert!(ics.contains("DESCRIPTION:Due 2024-02-29\r\n"));
+    }
+
+    #[test]
+    fn ranges_are_checked_and_cover_their_months() {
+        assert!(parse_range("2024-03-01", "2024-02-01").is_err());
+        assert!(parse_range("2024-01-01", "2025-01-01").is_err());
+        assert!(parse_range("2024-01-01", "2024-12-31").is_ok());
+        assert_eq!(
+            range_pattern(date("2024-11-20"), date("2025-01-05")),
+            "(2024-11|2024-12|2025-01)-[0-9]{2}"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::functions::pages::{live_page_exists, next_position, Page, PAGE_COLUMNS};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::{current_session, Session};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::templates::{create_page_from_template, template_exists};
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Debug, Default, Serialize)]
+pub struct DailyNoteSettings {
+    pub parent_page_id: Option<String>,
+    pub template_id: Option<String>,
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, Response<Value>> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| Response {
+        status: StatusCode::BadRequest,
+        data: None,
+        error: Some(format!("Invalid date {:?}, expected YYYY-MM-DD", date)),
+    })
+}
+
+async fn load_settings(
+    supabase_client: &RestClient,
+    user_id: &str,
+) -> Result<DailyNoteSettings, String> {
+    let rows = supabase_client
+        .select(
+            "daily_note_settings",
+            &format!("select=parent_page_id,template_id&user_id=eq.{}", user_id),
+        )
+        .await?;
+    Ok(rows
+        .first()
+        .map(|row| DailyNoteSettings {
+            parent_page_id: row["parent_page_id"].as_str().map(|id| id.to_string()),
+            template_id: row["template_id"].as_str().map(|id| id.to_string()),
+        })
+        .unwrap_or_default())
+}
+
+async fn find_daily_note(
+    supabase_client: &RestClient,
+    user_id: &str,
+    date: NaiveDate,
+) -> Result<Option<Page>, String> {
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select={}&user_id=eq.{}&journal_date=eq.{}&deleted_at=is.null",
+                PAGE_COLUMNS, user_id, date
+            ),
+        )
+        .await?;
+    Ok(rows.first().map(Page::from_row))
+}
+
+/// Creates the journal page for `date` under the configured parent, from
+/// the configured template if there is one. A parent or template that has
+/// since been deleted is ignored rather than failing the whole note.
+async fn create_daily_note(
+    supabase_client: &RestClient,
+    session: &Session,
+    date: NaiveDate,
+) -> Result<Page, String> {
+    let settings = load_settings(supabase_client, &session.user_id).await?;
+    let title = date.format("%Y-%m-%d").to_string();
+
+    let mut parent_page_id = settings.parent_page_id;
+    if let Some(parent_id) = &parent_page_id {
+        if !live_page_exists(supabase_client, parent_id).await? {
+            tracing::warn!(%parent_id, "daily note parent is gone, creating at the top level");
+            parent_page_id = None;
+        }
+    }
+
+    if let Some(template_id) = &settings.template_id {
+        let page = create_page_from_template(
+            supabase_client,
+            session,
+            template_id,
+            Some(title.clone()),
+            parent_page_id.clone(),
+            date,
+            json!({ "journal_date": date.to_string() }),
+        )
+        .await?;
+        match page {
+            Some(page) => return Ok(page),
+            None => tracing::warn!(%template_id, "daily note template is gone, creating a blank page"),
+        }
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let workspace_id = active_workspace_id(supabase_client).await?;
+    let position = next_position(supabase_client, &workspace_id, parent_page_id.as_deref()).await?;
+    let rows = supabase_client
+        .insert(
+            "pages",
+            json!({
+                "id": Uuid::new_v4().to_string(),
+                "user_id": session.user_id,
+                "title": title,
+                "parent_page_id": parent_page_id,
+                "position": position,
+                "journal_date": date.to_string(),
+                "created_at": now,
+                "updated_at": now,
+            }),
+        )
+        .await?;
+    rows.first()
+        .map(Page::from_row)
+        .ok_or_else(|| "Page was not created".to_string())
+}
+
+/// Returns the journal page for `date` (YYYY-MM-DD), creating it first if
+/// the user has none for that day.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(date = %date))]
+pub async fn open_daily_note(date: String) -> Result<Response<Value>, String> {
+    let date = match parse_date(&date) {
+        Ok(date) => date,
+        Err(response) => return Ok(response),
+    };
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+
+    if let Some(page) = find_daily_note(&supabase_client, &session.user_id, date).await? {
+        return Ok(Response {
+            status: StatusCode::Ok,
+            data: Some(json!({ "page": page, "created": false })),
+            error: None,
+        });
+    }
+
+    let page = match create_daily_note(&supabase_client, &session, date).await {
+        Ok(page) => page,
+        // Opened twice at once: the unique index lets only one create win
+        Err(e) => match find_daily_note(&supabase_client, &session.user_id, date).await? {
+            Some(page) => {
+                return Ok(Response {
+                    status: StatusCode::Ok,
+                    data: Some(json!({ "page": page, "created": false })),
+                    error: None,
+                })
+            }
+            None => return Err(e),
+        },
+    };
+
+    tracing::info!(page_id = %page.id, "daily note created");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page": page, "created": true })),
+        error: None,
+    })
+}
+
+/// The days between `start_date` and `end_date` (inclusive, YYYY-MM-DD)
+/// that have a journal page, for marking them in a calendar.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(start_date = %start_date, end_date = %end_date))]
+pub async fn list_daily_notes(start_date: String, end_date: String) -> Result<Response<Value>, String> {
+    let (start, end) = match (parse_date(&start_date), parse_date(&end_date)) {
+        (Ok(start), Ok(end)) => (start, end),
+        (Err(response), _) | (_, Err(response)) => return Ok(response),
+    };
+    if end < start {
+        return Ok(Response {
+            status: StatusCode::BadRequest,
+            data: None,
+            error: Some("end_date is before start_date".to_string()),
+        });
+    }
+
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select=id,title,journal_date&user_id=eq.{}&deleted_at=is.null&journal_date=gte.{}&journal_date=lte.{}&order=journal_date.asc",
+                session.user_id, start, end
+            ),
+        )
+        .await?;
+
+    let notes: Vec<Value> = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "date": row["journal_date"],
+                "page_id": row["id"],
+                "title": row["title"],
+            })
+        })
+        .collect();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(notes)),
+        error: None,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_daily_note_settings() -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let settings = load_settings(&supabase_client, &session.user_id).await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(settings)),
+        error: None,
+    })
+}
+
+/// Sets where new journal pages are created and which template they use.
+/// `None` (or an empty string) means the top level and a blank page.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(parent_page_id = ?parent_page_id, template_id = ?template_id))]
+pub async fn set_daily_note_settings(
+    parent_page_id: Option<String>,
+    template_id: Option<String>,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let settings = DailyNoteSettings {
+        parent_page_id: parent_page_id.filter(|id| !id.is_empty()),
+        template_id: template_id.filter(|id| !id.is_empty()),
+    };
+
+    if let Some(parent_id) = &settings.parent_page_id {
+        if !live_page_exists(&supabase_client, parent_id).await? {
+            return Ok(Response {
+                status: StatusCode::BadRequest,
+                data: None,
+                error: Some(format!("Parent page {} not found", parent_id)),
+            });
+        }
+    }
+    if let Some(template_id) = &settings.template_id {
+        if !template_exists(&supabase_client, &session.user_id, template_id).await? {
+            return Ok(Response {
+                status: StatusCode::BadRequest,
+                data: None,
+                error: Some(format!("Template {} not found", template_id)),
+            });
+        }
+    }
+
+    supabase_client
+        .upsert(
+            "daily_note_settings",
+            "user_id",
+            json!({
+                "user_id": session.user_id,
+                "parent_page_id": settings.parent_page_id,
+                "template_id": settings.template_id,
+                "updated_at": chrono::Utc::now().to_rfc3339(),
+            }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(settings)),
+        error: None,
+    })
+}
@@ -0,0 +1,228 @@
+use crate::functions::databases::{
+    bad_request, database_not_found, load_database, load_properties, validate_query, DatabaseQuery,
+    Property, PropertyType,
+};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::{forbidden, page_role};
+use crate::functions::supabase::initialize_user_client;
+use crate::supabase::rest::RestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use urlencoding::encode;
+
+const MAX_VIEW_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewLayout {
+    Table,
+    Board,
+    Calendar,
+}
+
+/// What a saved view shows: its query, plus the date property a calendar
+/// is laid out by and the properties shown as columns or on cards.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ViewConfig {
+    #[serde(flatten)]
+    pub query: DatabaseQuery,
+    #[serde(default)]
+    pub date_property: Option<String>,
+    #[serde(default)]
+    pub visible_properties: Option<Vec<String>>,
+}
+
+fn view_not_found() -> Response<Value> {
+    Response {
+        status: StatusCode::NotFound,
+        data: None,
+        error: Some("View not found".to_string()),
+    }
+}
+
+/// Checks that a view's config fits its layout and the database's schema:
+/// boards are grouped by a select and calendars need a date property.
+fn validate_view(properties: &[Property], layout: ViewLayout, config: &ViewConfig) -> Result<(), String> {
+    validate_query(properties, &config.query)?;
+    let property = |id: &str| properties.iter().find(|property| property.id == id);
+    for id in config.visible_properties.iter().flatten() {
+        if property(id).is_none() {
+            return Err(format!("Unknown property {}", id));
+        }
+    }
+
+    match layout {
+        ViewLayout::Table => Ok(()),
+        ViewLayout::Board => match config.query.group_by.as_deref().and_then(property) {
+            Some(property) if property.property_type == PropertyType::Select => Ok(()),
+            _ => Err("Boards are grouped by a select property".to_string()),
+        },
+        ViewLayout::Calendar => match config.date_property.as_deref().and_then(property) {
+            Some(property) if property.property_type == PropertyType::Date => Ok(()),
+            _ => Err("Calendars need a date property".to_string()),
+        },
+    }
+}
+
+async fn load_view(supabase_client: &RestClient, view_id: &str) -> Result<Option<Value>, String> {
+    let rows = supabase_client
+        .select("database_views", &format!("id=eq.{}", encode(view_id)))
+        .await?;
+    Ok(rows.into_iter().next())
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_VIEW_NAME_LENGTH {
+        return Err(format!(
+            "View names must be 1 to {} characters",
+            MAX_VIEW_NAME_LENGTH
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Saves a table, board or calendar view of a database. New views are
+/// added after the existing ones.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(database_page_id = %database_page_id, layout = ?layout))]
+pub async fn create_database_view(
+    database_page_id: String,
+    name: String,
+    layout: ViewLayout,
+    config: Option<ViewConfig>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if load_database(&supabase_client, &database_page_id).await?.is_none() {
+        return Ok(database_not_found());
+    }
+    if !page_role(&supabase_client, &database_page_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+    let name = match validate_name(&name) {
+        Ok(name) => name,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let config = config.unwrap_or_default();
+    let properties = load_properties(&supabase_client, &database_page_id).await?;
+    if let Err(e) = validate_view(&properties, layout, &config) {
+        return Ok(bad_request(e));
+    }
+
+    let last = supabase_client
+        .select(
+            "database_views",
+            &format!(
+                "select=position&database_page_id=eq.{}&order=position.desc&limit=1",
+                encode(&database_page_id)
+            ),
+        )
+        .await?;
+    let position = last
+        .first()
+        .and_then(|row| row["position"].as_i64())
+        .map_or(0, |position| position + 1);
+
+    let rows = supabase_client
+        .insert(
+            "database_views",
+            json!({
+                "database_page_id": database_page_id,
+                "name": name,
+                "layout": layout,
+                "config": config,
+                "position": position,
+            }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: rows.into_iter().next(),
+        error: None,
+    })
+}
+
+/// Renames a view or changes its layout or config; `None` leaves a field
+/// as it is.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(view_id = %view_id))]
+pub async fn update_database_view(
+    view_id: String,
+    name: Option<String>,
+    layout: Option<ViewLayout>,
+    config: Option<ViewConfig>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let Some(view) = load_view(&supabase_client, &view_id).await? else {
+        return Ok(view_not_found());
+    };
+    let database_page_id = view["database_page_id"].as_str().unwrap_or_default().to_string();
+    if !page_role(&supabase_client, &database_page_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+
+    let mut body = json!({ "updated_at": chrono::Utc::now().to_rfc3339() });
+    if let Some(name) = name {
+        match validate_name(&name) {
+            Ok(name) => body["name"] = json!(name),
+            Err(e) => return Ok(bad_request(e)),
+        }
+    }
+    let layout = match layout {
+        Some(layout) => layout,
+        None => serde_json::from_value(view["layout"].clone()).unwrap_or(ViewLayout::Table),
+    };
+    let config = match config {
+        Some(config) => config,
+        None => serde_json::from_value(view["config"].clone()).unwrap_or_default(),
+    };
+    let properties = load_properties(&supabase_client, &database_page_id).await?;
+    if let Err(e) = validate_view(&properties, layout, &config) {
+        return Ok(bad_request(e));
+    }
+    body["layout"] = json!(layout);
+    body["config"] = json!(config);
+
+    let rows = supabase_client
+        .update("database_views", &format!("id=eq.{}", encode(&view_id)), body)
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: rows.into_iter().next(),
+        error: None,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(view_id = %view_id))]
+pub async fn delete_database_view(view_id: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let Some(view) = load_view(&supabase_client, &view_id).await? else {
+        return Ok(view_not_found());
+    };
+    let database_page_id = view["database_page_id"].as_str().unwrap_or_default();
+    if !page_role(&supabase_client, database_page_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+
+    supabase_client
+        .delete("database_views", &format!("id=eq.{}", encode(&view_id)))
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "id": view_id, "deleted": true })),
+        error: None,
+    })
+}
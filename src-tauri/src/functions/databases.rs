@@ -0,0 +1,1037 @@
+use crate::functions::pages::{ids_filter, Page, PAGE_COLUMNS};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::{forbidden, page_role, PageRole};
+use crate::functions::supabase::initialize_user_client;
+use crate::supabase::rest::RestClient;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use urlencoding::encode;
+use uuid::Uuid;
+
+const MAX_PROPERTIES: usize = 100;
+const MAX_PROPERTY_NAME_LENGTH: usize = 100;
+/// Filters and sorts can refer to the row's title as if it were a property.
+pub const TITLE_PROPERTY: &str = "title";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertyType {
+    Text,
+    Number,
+    Select,
+    MultiSelect,
+    Date,
+    Checkbox,
+    Relation,
+    Url,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Property {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub property_type: PropertyType,
+    #[serde(default)]
+    pub options: Value,
+    #[serde(default)]
+    pub position: i32,
+}
+
+impl Property {
+    /// The option names of a select or multi-select, in their defined order.
+    pub fn option_names(&self) -> Vec<String> {
+        self.options["options"]
+            .as_array()
+            .map(|options| {
+                options
+                    .iter()
+                    .filter_map(|option| option["name"].as_str().map(|name| name.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn canonical_option(&self, name: &str) -> Option<String> {
+        self.option_names()
+            .into_iter()
+            .find(|option| option.eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// A property as sent to `set_database_schema`. Properties without an `id`
+/// are created; existing ones keep their id, and with it their values.
+#[derive(Debug, Deserialize)]
+pub struct PropertyInput {
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub property_type: PropertyType,
+    #[serde(default)]
+    pub options: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Equals,
+    NotEquals,
+    Contains,
+    NotContains,
+    GreaterThan,
+    GreaterOrEqual,
+    LessThan,
+    LessOrEqual,
+    IsEmpty,
+    IsNotEmpty,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PropertyFilter {
+    pub property_id: String,
+    pub operator: FilterOperator,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PropertySort {
+    pub property_id: String,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+/// Which rows to return and how to arrange them. Filters are combined with
+/// AND; rows without a sort keep their manual order.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DatabaseQuery {
+    #[serde(default)]
+    pub filters: Vec<PropertyFilter>,
+    #[serde(default)]
+    pub sorts: Vec<PropertySort>,
+    #[serde(default)]
+    pub group_by: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowGroup {
+    /// The group's value; `null` for rows without one.
+    pub key: Value,
+    pub label: String,
+    pub rows: Vec<Page>,
+}
+
+pub fn bad_request(error: impl Into<String>) -> Response<Value> {
+    Response {
+        status: StatusCode::BadRequest,
+        data: None,
+        error: Some(error.into()),
+    }
+}
+
+pub fn database_not_found() -> Response<Value> {
+    Response {
+        status: StatusCode::NotFound,
+        data: None,
+        error: Some("Database not found".to_string()),
+    }
+}
+
+pub async fn load_page(supabase_client: &RestClient, page_id: &str) -> Result<Option<Page>, String> {
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!("select={}&id=eq.{}&deleted_at=is.null", PAGE_COLUMNS, encode(page_id)),
+        )
+        .await?;
+    Ok(rows.first().map(Page::from_row))
+}
+
+/// The database page `page_id`, if the user can see it and it is one.
+pub async fn load_database(supabase_client: &RestClient, page_id: &str) -> Result<Option<Page>, String> {
+    Ok(load_page(supabase_client, page_id)
+        .await?
+        .filter(|page| page.is_database))
+}
+
+/// A database's properties in their display order.
+pub async fn load_properties(
+    supabase_client: &RestClient,
+    database_page_id: &str,
+) -> Result<Vec<Property>, String> {
+    let rows = supabase_client
+        .select(
+            "database_properties",
+            &format!(
+                "database_page_id=eq.{}&order=position.asc,created_at.asc",
+                encode(database_page_id)
+            ),
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| serde_json::from_value(row.clone()).ok())
+        .collect())
+}
+
+/// A database's rows (its live child pages) in manual order.
+pub async fn load_rows(supabase_client: &RestClient, database_page_id: &str) -> Result<Vec<Page>, String> {
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select={}&parent_page_id=eq.{}&deleted_at=is.null&order=position.asc,created_at.asc,id.asc",
+                PAGE_COLUMNS,
+                encode(database_page_id)
+            ),
+        )
+        .await?;
+    Ok(rows.iter().map(Page::from_row).collect())
+}
+
+/// Select options as `[{"name", "color"}]`, from names or objects, without
+/// blanks or case-insensitive duplicates.
+fn normalize_select_options(options: Option<&Value>) -> Result<Value, String> {
+    let mut result: Vec<Value> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    for option in options
+        .and_then(|options| options["options"].as_array())
+        .into_iter()
+        .flatten()
+    {
+        let (name, color) = match option {
+            Value::String(name) => (name.as_str(), "default"),
+            Value::Object(_) => (
+                option["name"].as_str().unwrap_or_default(),
+                option["color"].as_str().unwrap_or("default"),
+            ),
+            _ => return Err("Select options must be names or {name, color} objects".to_string()),
+        };
+        let name = name.trim();
+        if name.is_empty() || !seen.insert(name.to_lowercase()) {
+            continue;
+        }
+        result.push(json!({ "name": name, "color": color }));
+    }
+    Ok(json!({ "options": result }))
+}
+
+async fn normalize_options(
+    supabase_client: &RestClient,
+    input: &PropertyInput,
+) -> Result<Value, String> {
+    match input.property_type {
+        PropertyType::Select | PropertyType::MultiSelect => {
+            normalize_select_options(input.options.as_ref())
+        }
+        PropertyType::Relation => {
+            let target = input
+                .options
+                .as_ref()
+                .and_then(|options| options["database_page_id"].as_str())
+                .ok_or(format!("Relation {:?} needs a database_page_id", input.name))?;
+            if load_database(supabase_client, target).await?.is_none() {
+                return Err(format!("Relation {:?} must point at a database", input.name));
+            }
+            Ok(json!({ "database_page_id": target }))
+        }
+        _ => Ok(json!({})),
+    }
+}
+
+fn parse_date(value: &str) -> Option<String> {
+    let value = value.trim();
+    if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok() {
+        return Some(value.to_string());
+    }
+    DateTime::parse_from_rfc3339(value).ok().map(|at| at.to_rfc3339())
+}
+
+/// Ids in a relation value that aren't rows of the related database.
+async fn unknown_relation_rows(
+    supabase_client: &RestClient,
+    property: &Property,
+    ids: &[String],
+) -> Result<Vec<String>, String> {
+    let Some(target) = property.options["database_page_id"].as_str() else {
+        return Ok(ids.to_vec());
+    };
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select=id&parent_page_id=eq.{}&deleted_at=is.null&id={}",
+                encode(target),
+                ids_filter(ids)
+            ),
+        )
+        .await?;
+    let found: HashSet<&str> = rows.iter().filter_map(|row| row["id"].as_str()).collect();
+    Ok(ids
+        .iter()
+        .filter(|id| !found.contains(id.as_str()))
+        .cloned()
+        .collect())
+}
+
+/// Checks `value` against the property's type and returns it in the stored
+/// form. `null`, empty strings and empty lists clear the value.
+pub fn normalize_value(property: &Property, value: Value) -> Result<Value, String> {
+    let invalid = || format!("Invalid value for {:?}", property.name);
+    let strings = |value: Value| -> Result<Vec<String>, String> {
+        match value {
+            Value::String(one) => Ok(vec![one]),
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| item.as_str().map(|s| s.to_string()).ok_or_else(invalid))
+                .collect(),
+            _ => Err(invalid()),
+        }
+    };
+
+    match value {
+        Value::Null => return Ok(Value::Null),
+        Value::String(ref text) if text.trim().is_empty() => return Ok(Value::Null),
+        Value::Array(ref items) if items.is_empty() => return Ok(Value::Null),
+        _ => {}
+    }
+
+    match property.property_type {
+        PropertyType::Text => value.as_str().map(|text| json!(text)).ok_or_else(invalid),
+        PropertyType::Number => {
+            let number = match &value {
+                Value::Number(number) => number.as_f64(),
+                Value::String(text) => text.trim().parse::<f64>().ok(),
+                _ => None,
+            };
+            number
+                .filter(|number| number.is_finite())
+                .map(|number| json!(number))
+                .ok_or_else(invalid)
+        }
+        PropertyType::Select => {
+            let name = value.as_str().ok_or_else(invalid)?;
+            property
+                .canonical_option(name)
+                .map(|name| json!(name))
+                .ok_or(format!("{:?} is not an option of {:?}", name, property.name))
+        }
+        PropertyType::MultiSelect => {
+            let mut names: Vec<String> = Vec::new();
+            for name in strings(value)? {
+                let option = property
+                    .canonical_option(&name)
+                    .ok_or(format!("{:?} is not an option of {:?}", name, property.name))?;
+                if !names.contains(&option) {
+                    names.push(option);
+                }
+            }
+            Ok(json!(names))
+        }
+        PropertyType::Date => match &value {
+            Value::String(text) => parse_date(text).map(|date| json!(date)).ok_or_else(invalid),
+            Value::Object(_) => {
+                let start = value["start"].as_str().and_then(parse_date).ok_or_else(invalid)?;
+                match value["end"].as_str().filter(|end| !end.is_empty()) {
+                    Some(end) => {
+                        let end = parse_date(end).ok_or_else(invalid)?;
+                        if end < start {
+                            return Err(format!("{:?} ends before it starts", property.name));
+                        }
+                        Ok(json!({ "start": start, "end": end }))
+                    }
+                    None => Ok(json!(start)),
+                }
+            }
+            _ => Err(invalid()),
+        },
+        PropertyType::Checkbox => value.as_bool().map(|checked| json!(checked)).ok_or_else(invalid),
+        PropertyType::Relation => {
+            let mut ids: Vec<String> = Vec::new();
+            for id in strings(value)? {
+                let id = Uuid::parse_str(id.trim()).map_err(|_| invalid())?.to_string();
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            Ok(json!(ids))
+        }
+        PropertyType::Url => {
+            let text = value.as_str().ok_or_else(invalid)?.trim();
+            match reqwest::Url::parse(text) {
+                Ok(url) if ["http", "https", "mailto"].contains(&url.scheme()) => Ok(json!(text)),
+                _ => Err(format!("{:?} is not a valid URL", text)),
+            }
+        }
+    }
+}
+
+/// The start of a date value, which is a date string or `{start, end}`.
+pub fn date_start(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| value["start"].as_str())
+}
+
+/// A value reduced to something comparable across rows.
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Empty,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl Key {
+    fn compare(&self, other: &Key) -> Option<Ordering> {
+        match (self, other) {
+            (Key::Bool(a), Key::Bool(b)) => Some(a.cmp(b)),
+            (Key::Number(a), Key::Number(b)) => a.partial_cmp(b),
+            (Key::Text(a), Key::Text(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+fn row_value(row: &Page, property_id: &str) -> Value {
+    if property_id == TITLE_PROPERTY {
+        return json!(row.title);
+    }
+    row.properties.get(property_id).cloned().unwrap_or(Value::Null)
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(text) => text.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+fn key(property: Option<&Property>, value: &Value) -> Key {
+    if is_empty(value) {
+        return Key::Empty;
+    }
+    match property.map(|property| property.property_type) {
+        Some(PropertyType::Number) => value.as_f64().map(Key::Number).unwrap_or(Key::Empty),
+        Some(PropertyType::Checkbox) => value.as_bool().map(Key::Bool).unwrap_or(Key::Empty),
+        Some(PropertyType::Date) => date_start(value)
+            .map(|start| Key::Text(start.to_string()))
+            .unwrap_or(Key::Empty),
+        // Selects sort in the order their options are defined
+        Some(PropertyType::Select) => {
+            let name = value.as_str().unwrap_or_default();
+            let options = property.map(Property::option_names).unwrap_or_default();
+            match options.iter().position(|option| option == name) {
+                Some(index) => Key::Number(index as f64),
+                None => Key::Text(name.to_lowercase()),
+            }
+        }
+        _ => match value {
+            Value::String(text) => Key::Text(text.to_lowercase()),
+            Value::Array(items) => Key::Text(
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    .to_lowercase(),
+            ),
+            Value::Bool(checked) => Key::Bool(*checked),
+            Value::Number(number) => number.as_f64().map(Key::Number).unwrap_or(Key::Empty),
+            other => Key::Text(other.to_string()),
+        },
+    }
+}
+
+/// The filter value as a key of the same kind as the property's values.
+fn filter_key(property: Option<&Property>, value: &Value) -> Key {
+    match (property.map(|property| property.property_type), value) {
+        (Some(PropertyType::Number), Value::String(text)) => {
+            text.trim().parse().map(Key::Number).unwrap_or(Key::Empty)
+        }
+        (Some(PropertyType::Date), Value::String(text)) => Key::Text(text.trim().to_string()),
+        _ => key(property, value),
+    }
+}
+
+fn matches(property: Option<&Property>, value: &Value, filter: &PropertyFilter) -> bool {
+    let needle = filter.value.as_str().map(|text| text.trim().to_lowercase());
+    let contains = || match (value, &needle) {
+        (Value::Array(items), Some(needle)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|item| item.to_lowercase() == *needle),
+        (Value::String(text), Some(needle)) => text.to_lowercase().contains(needle.as_str()),
+        _ => false,
+    };
+    let ordering = || key(property, value).compare(&filter_key(property, &filter.value));
+    let equals = || match value {
+        Value::Array(_) => contains(),
+        _ => ordering() == Some(Ordering::Equal),
+    };
+
+    match filter.operator {
+        FilterOperator::IsEmpty => is_empty(value),
+        FilterOperator::IsNotEmpty => !is_empty(value),
+        FilterOperator::Equals => equals(),
+        FilterOperator::NotEquals => !equals(),
+        FilterOperator::Contains => contains(),
+        FilterOperator::NotContains => !contains(),
+        FilterOperator::GreaterThan => ordering() == Some(Ordering::Greater),
+        FilterOperator::GreaterOrEqual => {
+            matches!(ordering(), Some(Ordering::Greater | Ordering::Equal))
+        }
+        FilterOperator::LessThan => ordering() == Some(Ordering::Less),
+        FilterOperator::LessOrEqual => matches!(ordering(), Some(Ordering::Less | Ordering::Equal)),
+    }
+}
+
+pub fn find_property<'a>(properties: &'a [Property], id: &str) -> Option<&'a Property> {
+    properties.iter().find(|property| property.id == id)
+}
+
+/// Checks that a query only refers to the database's properties.
+pub fn validate_query(properties: &[Property], query: &DatabaseQuery) -> Result<(), String> {
+    let known = |id: &str| id == TITLE_PROPERTY || find_property(properties, id).is_some();
+    for id in query
+        .filters
+        .iter()
+        .map(|filter| filter.property_id.as_str())
+        .chain(query.sorts.iter().map(|sort| sort.property_id.as_str()))
+        .chain(query.group_by.as_deref())
+    {
+        if !known(id) {
+            return Err(format!("Unknown property {}", id));
+        }
+    }
+    Ok(())
+}
+
+/// Filters and sorts `rows`. Rows without a value sort last either way.
+pub fn apply_query(properties: &[Property], rows: Vec<Page>, query: &DatabaseQuery) -> Vec<Page> {
+    let mut rows: Vec<Page> = rows
+        .into_iter()
+        .filter(|row| {
+            query.filters.iter().all(|filter| {
+                let property = find_property(properties, &filter.property_id);
+                matches(property, &row_value(row, &filter.property_id), filter)
+            })
+        })
+        .collect();
+
+    // Stable, so rows that tie keep their manual order
+    rows.sort_by(|a, b| {
+        for sort in &query.sorts {
+            let property = find_property(properties, &sort.property_id);
+            let (a, b) = (
+                key(property, &row_value(a, &sort.property_id)),
+                key(property, &row_value(b, &sort.property_id)),
+            );
+            let ordering = match (&a, &b) {
+                (Key::Empty, Key::Empty) => Ordering::Equal,
+                (Key::Empty, _) => Ordering::Greater,
+                (_, Key::Empty) => Ordering::Less,
+                _ => {
+                    let ordering = a.compare(&b).unwrap_or(Ordering::Equal);
+                    match sort.direction {
+                        SortDirection::Asc => ordering,
+                        SortDirection::Desc => ordering.reverse(),
+                    }
+                }
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+    rows
+}
+
+fn group_label(key: &Value) -> String {
+    match key {
+        Value::Null => "No value".to_string(),
+        Value::Bool(true) => "Checked".to_string(),
+        Value::Bool(false) => "Unchecked".to_string(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Splits sorted rows into groups by a property. Selects get a group per
+/// option, in order, even when empty; a row with several multi-select
+/// options appears in each of their groups. Rows without a value come last.
+pub fn group_rows(property: Option<&Property>, property_id: &str, rows: Vec<Page>) -> Vec<RowGroup> {
+    let mut keys: Vec<Value> = match property.map(|property| property.property_type) {
+        Some(PropertyType::Select | PropertyType::MultiSelect) => property
+            .map(Property::option_names)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Value::String)
+            .collect(),
+        Some(PropertyType::Checkbox) => vec![json!(true), json!(false)],
+        _ => Vec::new(),
+    };
+    let mut row_keys: Vec<(Vec<Value>, Page)> = Vec::new();
+    for row in rows {
+        let value = row_value(&row, property_id);
+        let values: Vec<Value> = match (property.map(|property| property.property_type), &value) {
+            (Some(PropertyType::Checkbox), _) => vec![json!(value.as_bool().unwrap_or(false))],
+            (Some(PropertyType::Date), _) => date_start(&value)
+                .map(|start| vec![json!(&start[..start.len().min(10)])])
+                .unwrap_or_default(),
+            (_, Value::Array(items)) => items.clone(),
+            _ if is_empty(&value) => Vec::new(),
+            _ => vec![value.clone()],
+        };
+        for value in &values {
+            if !keys.contains(value) {
+                keys.push(value.clone());
+            }
+        }
+        row_keys.push((values, row));
+    }
+
+    let mut groups: Vec<RowGroup> = keys
+        .into_iter()
+        .map(|key| RowGroup {
+            label: group_label(&key),
+            key,
+            rows: Vec::new(),
+        })
+        .collect();
+    let mut ungrouped = RowGroup {
+        key: Value::Null,
+        label: group_label(&Value::Null),
+        rows: Vec::new(),
+    };
+    for (values, row) in row_keys {
+        if values.is_empty() {
+            ungrouped.rows.push(row);
+            continue;
+        }
+        for group in groups.iter_mut().filter(|group| values.contains(&group.key)) {
+            group.rows.push(row.clone());
+        }
+    }
+    groups.push(ungrouped);
+    groups
+}
+
+/// Defines a database's properties, turning the page into a database if it
+/// isn't one yet (only its owner can do that). Properties missing from
+/// `properties` are removed; their values stay on the rows but are ignored.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, properties = properties.len()))]
+pub async fn set_database_schema(
+    page_id: String,
+    properties: Vec<PropertyInput>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let Some(role) = page_role(&supabase_client, &page_id).await? else {
+        return Ok(database_not_found());
+    };
+    if !role.can_edit() {
+        return Ok(forbidden());
+    }
+    let Some(page) = load_page(&supabase_client, &page_id).await? else {
+        return Ok(database_not_found());
+    };
+    if !page.is_database && role != PageRole::Owner {
+        return Ok(Response {
+            status: StatusCode::Unauthorized,
+            data: None,
+            error: Some("Only the page owner can turn it into a database".to_string()),
+        });
+    }
+
+    if properties.len() > MAX_PROPERTIES {
+        return Ok(bad_request(format!(
+            "Databases can have at most {} properties",
+            MAX_PROPERTIES
+        )));
+    }
+    let existing = load_properties(&supabase_client, &page_id).await?;
+    let mut names: HashSet<String> = HashSet::new();
+    let mut rows: Vec<Value> = Vec::with_capacity(properties.len());
+    for (position, input) in properties.iter().enumerate() {
+        let name = input.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_PROPERTY_NAME_LENGTH {
+            return Ok(bad_request(format!(
+                "Property names must be 1 to {} characters",
+                MAX_PROPERTY_NAME_LENGTH
+            )));
+        }
+        if name.eq_ignore_ascii_case(TITLE_PROPERTY) || !names.insert(name.to_lowercase()) {
+            return Ok(bad_request(format!("Duplicate property name {:?}", name)));
+        }
+        let id = match input.id.as_deref().filter(|id| !id.is_empty()) {
+            Some(id) if find_property(&existing, id).is_some() => id.to_string(),
+            Some(id) => return Ok(bad_request(format!("Unknown property {}", id))),
+            None => Uuid::new_v4().to_string(),
+        };
+        let options = match normalize_options(&supabase_client, input).await {
+            Ok(options) => options,
+            Err(e) => return Ok(bad_request(e)),
+        };
+        rows.push(json!({
+            "id": id,
+            "database_page_id": page_id,
+            "name": name,
+            "type": input.property_type,
+            "options": options,
+            "position": position,
+        }));
+    }
+
+    let kept: HashSet<&str> = rows.iter().filter_map(|row| row["id"].as_str()).collect();
+    let removed: Vec<&str> = existing
+        .iter()
+        .map(|property| property.id.as_str())
+        .filter(|id| !kept.contains(id))
+        .collect();
+    if !removed.is_empty() {
+        supabase_client
+            .delete(
+                "database_properties",
+                &format!("id={}", ids_filter(&removed)),
+            )
+            .await?;
+    }
+    if !rows.is_empty() {
+        supabase_client
+            .upsert("database_properties", "id", json!(rows))
+            .await?;
+    }
+    if !page.is_database {
+        supabase_client
+            .update(
+                "pages",
+                &format!("id=eq.{}", encode(&page_id)),
+                json!({ "is_database": true, "updated_at": chrono::Utc::now().to_rfc3339() }),
+            )
+            .await?;
+    }
+    tracing::debug!(removed = removed.len(), "database schema saved");
+
+    let properties = load_properties(&supabase_client, &page_id).await?;
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "properties": properties })),
+        error: None,
+    })
+}
+
+/// A database page with its properties and saved views.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn fetch_database(page_id: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let Some(page) = load_database(&supabase_client, &page_id).await? else {
+        return Ok(database_not_found());
+    };
+    let properties = load_properties(&supabase_client, &page_id).await?;
+    let views = supabase_client
+        .select(
+            "database_views",
+            &format!(
+                "database_page_id=eq.{}&order=position.asc,created_at.asc",
+                encode(&page_id)
+            ),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page": page, "properties": properties, "views": views })),
+        error: None,
+    })
+}
+
+/// Sets a row's value for one property, checked against the property's
+/// type. `null` clears it.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, property_id = %property_id))]
+pub async fn set_property_value(
+    page_id: String,
+    property_id: String,
+    value: Value,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let Some(row) = load_page(&supabase_client, &page_id).await? else {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    };
+    if !page_role(&supabase_client, &page_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+    let Some(database_id) = row.parent_page_id.as_deref() else {
+        return Ok(bad_request("Page is not a database row"));
+    };
+    let properties = load_properties(&supabase_client, database_id).await?;
+    let Some(property) = find_property(&properties, &property_id) else {
+        return Ok(bad_request(format!("Unknown property {}", property_id)));
+    };
+
+    let value = match normalize_value(property, value) {
+        Ok(value) => value,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    if let (PropertyType::Relation, Value::Array(ids)) = (property.property_type, &value) {
+        let ids: Vec<String> = ids.iter().filter_map(|id| id.as_str().map(String::from)).collect();
+        let unknown = unknown_relation_rows(&supabase_client, property, &ids).await?;
+        if !unknown.is_empty() {
+            return Ok(bad_request(format!(
+                "Not rows of the related database: {}",
+                unknown.join(", ")
+            )));
+        }
+    }
+
+    let properties = supabase_client
+        .rpc(
+            "set_page_property",
+            json!({ "p_page_id": page_id, "p_property_id": property_id, "p_value": value }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "properties": properties })),
+        error: None,
+    })
+}
+
+/// A database's rows, filtered and sorted, and grouped when `group_by` is
+/// set. With `view_id` the saved view's query is used unless `query` is
+/// given.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(database_page_id = %database_page_id, view_id = ?view_id))]
+pub async fn query_database(
+    database_page_id: String,
+    query: Option<DatabaseQuery>,
+    view_id: Option<String>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if load_database(&supabase_client, &database_page_id).await?.is_none() {
+        return Ok(database_not_found());
+    }
+
+    let query = match (query, view_id.filter(|id| !id.is_empty())) {
+        (Some(query), _) => query,
+        (None, Some(view_id)) => {
+            let views = supabase_client
+                .select(
+                    "database_views",
+                    &format!(
+                        "select=config&id=eq.{}&database_page_id=eq.{}",
+                        encode(&view_id),
+                        encode(&database_page_id)
+                    ),
+                )
+                .await?;
+            let Some(view) = views.first() else {
+                return Ok(Response {
+                    status: StatusCode::NotFound,
+                    data: None,
+                    error: Some("View not found".to_string()),
+                });
+            };
+            serde_json::from_value(view["config"].clone()).unwrap_or_default()
+        }
+        (None, None) => DatabaseQuery::default(),
+    };
+
+    let properties = load_properties(&supabase_client, &database_page_id).await?;
+    if let Err(e) = validate_query(&properties, &query) {
+        return Ok(bad_request(e));
+    }
+    let rows = apply_query(
+        &properties,
+        load_rows(&supabase_client, &database_page_id).await?,
+        &query,
+    );
+    let total = rows.len();
+    let data = match query.group_by.as_deref() {
+        Some(group_by) => {
+            let groups = group_rows(find_property(&properties, group_by), group_by, rows);
+            json!({ "properties": properties, "groups": groups, "total": total })
+        }
+        None => json!({ "properties": properties, "rows": rows, "total": total }),
+    };
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(data),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(id: &str, property_type: PropertyType, options: Value) -> Property {
+        Property {
+            id: id.to_string(),
+            name: id.to_string(),
+            property_type,
+            options,
+            position: 0,
+        }
+    }
+
+    fn status() -> Property {
+        property(
+            "status",
+            PropertyType::Select,
+            json!({ "options": [{ "name": "Todo" }, { "name": "Doing" }, { "name": "Done" }] }),
+        )
+    }
+
+    fn row(id: &str, properties: Value) -> Page {
+        Page::from_row(&json!({ "id": id, "title": id, "properties": properties }))
+    }
+
+    fn ids(rows: &[Page]) -> Vec<&str> {
+        rows.iter().map(|row| row.id.as_str()).collect()
+    }
+
+    #[test]
+    fn values_are_checked_and_stored_in_canonical_form() {
+        let number = property("n", PropertyType::Number, json!({}));
+        assert_eq!(normalize_value(&number, json!(" 4.5 ")), Ok(json!(4.5)));
+        assert!(normalize_value(&number, json!("many")).is_err());
+        assert_eq!(normalize_value(&number, json!("")), Ok(Value::Null));
+
+        assert_eq!(normalize_value(&status(), json!("doing")), Ok(json!("Doing")));
+        assert!(normalize_value(&status(), json!("Blocked")).is_err());
+
+        let tags = property(
+            "tags",
+            PropertyType::MultiSelect,
+            json!({ "options": [{ "name": "A" }, { "name": "B" }] }),
+        );
+        assert_eq!(normalize_value(&tags, json!(["b", "B", "a"])), Ok(json!(["B", "A"])));
+    }
+
+    #[test]
+    fn dates_and_urls_are_validated() {
+        let due = property("due", PropertyType::Date, json!({}));
+        assert_eq!(normalize_value(&due, json!("2024-05-01")), Ok(json!("2024-05-01")));
+        assert_eq!(
+            normalize_value(&due, json!({ "start": "2024-05-01", "end": "2024-05-03" })),
+            Ok(json!({ "start": "2024-05-01", "end": "2024-05-03" }))
+        );
+        assert!(normalize_value(&due, json!({ "start": "2024-05-03", "end": "2024-05-01" })).is_err());
+        assert!(normalize_value(&due, json!("May 1st")).is_err());
+
+        let link = property("link", PropertyType::Url, json!({}));
+        assert_eq!(normalize_value(&link, json!(" https://a.b ")), Ok(json!("https://a.b")));
+        assert!(normalize_value(&link, json!("javascript:alert(1)")).is_err());
+    }
+
+    #[test]
+    fn relation_ids_must_be_uuids() {
+        let related = property("rel", PropertyType::Relation, json!({}));
+        let id = "0B5AD0E4-9A43-4D2C-8F0E-5C7B1E2D3F40";
+        assert_eq!(
+            normalize_value(&related, json!([id, id.to_lowercase()])),
+            Ok(json!([id.to_lowercase()]))
+        );
+        assert!(normalize_value(&related, json!(["not-an-id"])).is_err());
+    }
+
+    #[test]
+    fn queries_filter_then_sort_with_empty_values_last() {
+        let estimate = property("estimate", PropertyType::Number, json!({}));
+        let properties = vec![status(), estimate];
+        let rows = vec![
+            row("a", json!({ "status": "Done", "estimate": 3 })),
+            row("b", json!({ "status": "Todo" })),
+            row("c", json!({ "status": "Doing", "estimate": 1 })),
+            row("d", json!({ "status": "Todo", "estimate": 2 })),
+        ];
+
+        let by_estimate = DatabaseQuery {
+            sorts: vec![PropertySort {
+                property_id: "estimate".to_string(),
+                direction: SortDirection::Desc,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(&apply_query(&properties, rows.clone(), &by_estimate)),
+            ["a", "d", "c", "b"]
+        );
+
+        let not_done_by_status = DatabaseQuery {
+            filters: vec![PropertyFilter {
+                property_id: "status".to_string(),
+                operator: FilterOperator::NotEquals,
+                value: json!("Done"),
+            }],
+            sorts: vec![PropertySort {
+                property_id: "status".to_string(),
+                direction: SortDirection::Asc,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(&apply_query(&properties, rows, &not_done_by_status)),
+            ["b", "d", "c"]
+        );
+    }
+
+    #[test]
+    fn select_groups_follow_the_options_and_keep_empty_ones() {
+        let status = status();
+        let rows = vec![
+            row("a", json!({ "status": "Done" })),
+            row("b", json!({})),
+            row("c", json!({ "status": "Todo" })),
+        ];
+        let groups = group_rows(Some(&status), "status", rows);
+        let summary: Vec<(String, Vec<&str>)> = groups
+            .iter()
+            .map(|group| (group.label.clone(), ids(&group.rows)))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Todo".to_string(), vec!["c"]),
+                ("Doing".to_string(), vec![]),
+                ("Done".to_string(), vec!["a"]),
+                ("No value".to_string(), vec!["b"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_select_rows_appear_in_each_of_their_groups() {
+        let tags = property("tags", PropertyType::MultiSelect, json!({ "options": [] }));
+        let rows = vec![row("a", json!({ "tags": ["x", "y"] })), row("b", json!({ "tags": ["y"] }))];
+        let groups = group_rows(Some(&tags), "tags", rows);
+        assert_eq!(groups[0].key, json!("x"));
+        assert_eq!(ids(&groups[0].rows), ["a"]);
+        assert_eq!(groups[1].key, json!("y"));
+        assert_eq!(ids(&groups[1].rows), ["a", "b"]);
+    }
+}
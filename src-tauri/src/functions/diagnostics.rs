@@ -0,0 +1,331 @@
+use crate::functions::embeddings::EMBEDDING_MODEL;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::supabase::migrations::{applied_versions, latest_version};
+use dotenv::dotenv;
+use reqwest::{Client, RequestBuilder};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::env;
+use std::time::{Duration, Instant};
+
+const REQUIRED_TABLES: &[&str] = &["users", "pages", "blocks", "embeddings", "schema_migrations"];
+const STORAGE_BUCKETS: &[&str] = &["images", "avatars"];
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: String,
+    status: CheckStatus,
+    latency_ms: Option<u128>,
+    message: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Check {
+            name: name.to_string(),
+            status,
+            latency_ms: None,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    fn latency(mut self, latency: Duration) -> Self {
+        self.latency_ms = Some(latency.as_millis());
+        self
+    }
+}
+
+/// Status, body and latency of one probe, or the transport error.
+async fn probe(request: RequestBuilder) -> (Result<(u16, String), String>, Duration) {
+    let started = Instant::now();
+    let result = match request.timeout(CHECK_TIMEOUT).send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            Ok((status, response.text().await.unwrap_or_default()))
+        }
+        Err(e) if e.is_timeout() => Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+        Err(e) => Err(e.to_string()),
+    };
+    (result, started.elapsed())
+}
+
+fn transport_failure(name: &str, error: String, latency: Duration) -> Check {
+    Check::new(name, CheckStatus::Error, format!("Request failed: {}", error))
+        .latency(latency)
+        .hint("Check your network connection and that the URL in .env is correct.")
+}
+
+fn check_config() -> Vec<Check> {
+    [
+        ("VITE_SUPABASE_URL", "Supabase project URL, from Project Settings → API."),
+        ("VITE_SUPABASE_API_KEY", "Supabase anon or service key, from Project Settings → API."),
+        ("OPENAI_API_KEY", "OpenAI API key, needed for semantic search and chat."),
+    ]
+    .iter()
+    .map(|(var, hint)| {
+        let name = format!("config.{}", var);
+        match env::var(var) {
+            Ok(value) if !value.trim().is_empty() => Check::new(&name, CheckStatus::Ok, "set"),
+            _ => Check::new(&name, CheckStatus::Error, "missing from .env").hint(*hint),
+        }
+    })
+    .collect()
+}
+
+async fn check_supabase(client: &Client, url: &str, api_key: &str, bearer: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    // Reachability and project key validity in one request: the REST root
+    // answers 401 for an invalid key.
+    let (result, latency) = probe(
+        client
+            .get(format!("{}/rest/v1/", url))
+            .header("apikey", api_key)
+            .header("Authorization", format!("Bearer {}", api_key)),
+    )
+    .await;
+    match result {
+        Ok((status, _)) if status < 400 => {
+            checks.push(Check::new("supabase.reachable", CheckStatus::Ok, "reachable").latency(latency));
+            checks.push(Check::new("supabase.api_key", CheckStatus::Ok, "accepted"));
+        }
+        Ok((401, body)) | Ok((403, body)) => {
+            checks.push(Check::new("supabase.reachable", CheckStatus::Ok, "reachable").latency(latency));
+            checks.push(
+                Check::new("supabase.api_key", CheckStatus::Error, format!("rejected: {}", body))
+                    .hint("VITE_SUPABASE_API_KEY does not belong to this project."),
+            );
+            return checks;
+        }
+        Ok((status, body)) => {
+            checks.push(
+                Check::new(
+                    "supabase.reachable",
+                    CheckStatus::Error,
+                    format!("unexpected status {}: {}", status, body),
+                )
+                .latency(latency)
+                .hint("Check VITE_SUPABASE_URL and the project status in the Supabase dashboard."),
+            );
+            return checks;
+        }
+        Err(e) => {
+            checks.push(transport_failure("supabase.reachable", e, latency));
+            return checks;
+        }
+    }
+
+    for table in REQUIRED_TABLES {
+        let name = format!("supabase.table.{}", table);
+        let (result, latency) = probe(
+            client
+                .get(format!("{}/rest/v1/{}?select=*&limit=1", url, table))
+                .header("apikey", api_key)
+                .header("Authorization", format!("Bearer {}", bearer)),
+        )
+        .await;
+        checks.push(match result {
+            Ok((status, _)) if status < 400 => Check::new(&name, CheckStatus::Ok, "present").latency(latency),
+            Ok((401, body)) if body.contains("JWT") => {
+                Check::new(&name, CheckStatus::Error, format!("token rejected: {}", body))
+                    .latency(latency)
+                    .hint("The session token was rejected. Sign in again, and check that the jwt_secret Vault secret matches the project's JWT secret.")
+            }
+            Ok((status, body)) if status == 404 || body.contains("PGRST205") || body.contains("42P01") => {
+                Check::new(&name, CheckStatus::Error, "table is missing")
+                    .latency(latency)
+                    .hint("Run the migrations in src-tauri/migrations (see check_schema).")
+            }
+            Ok((status, body)) => {
+                Check::new(&name, CheckStatus::Error, format!("status {}: {}", status, body)).latency(latency)
+            }
+            Err(e) => transport_failure(&name, e, latency),
+        });
+    }
+
+    // The RPC is called with a zero vector and an impossible threshold, so it
+    // exercises the function without returning any rows.
+    let (result, latency) = probe(
+        client
+            .post(format!("{}/rest/v1/rpc/match_embeddings", url))
+            .header("apikey", api_key)
+            .header("Authorization", format!("Bearer {}", bearer))
+            .json(&json!({
+                "query_embedding": vec![0.0_f32; 1536],
+                "match_threshold": 2.0,
+                "match_count": 0,
+                "p_user_id": "0",
+            })),
+    )
+    .await;
+    checks.push(match result {
+        Ok((status, _)) if status < 400 => {
+            Check::new("supabase.rpc.match_embeddings", CheckStatus::Ok, "callable").latency(latency)
+        }
+        Ok((status, body)) if status == 404 || body.contains("PGRST202") => {
+            Check::new("supabase.rpc.match_embeddings", CheckStatus::Error, "function is missing")
+                .latency(latency)
+                .hint("Run the migrations in src-tauri/migrations (see check_schema).")
+        }
+        Ok((status, body)) => Check::new(
+            "supabase.rpc.match_embeddings",
+            CheckStatus::Error,
+            format!("status {}: {}", status, body),
+        )
+        .latency(latency)
+        .hint("The function exists but failed; make sure the vector extension is enabled."),
+        Err(e) => transport_failure("supabase.rpc.match_embeddings", e, latency),
+    });
+
+    let started = Instant::now();
+    let schema_check = match applied_versions(url, api_key).await {
+        Ok(Some(applied)) => {
+            let current = applied.iter().copied().max().unwrap_or(0);
+            if current >= latest_version() {
+                Check::new("supabase.schema_version", CheckStatus::Ok, format!("version {}", current))
+            } else {
+                Check::new(
+                    "supabase.schema_version",
+                    CheckStatus::Warning,
+                    format!("version {} of {}", current, latest_version()),
+                )
+                .hint("Run src-tauri/scripts/apply_migrations.sh to apply the missing migrations.")
+            }
+        }
+        Ok(None) => Check::new("supabase.schema_version", CheckStatus::Error, "not bootstrapped")
+            .hint("Run src-tauri/migrations/0001_initial_schema.sql in the Supabase SQL editor."),
+        Err(e) => Check::new("supabase.schema_version", CheckStatus::Error, e),
+    };
+    checks.push(schema_check.latency(started.elapsed()));
+
+    checks
+}
+
+async fn check_storage(client: &Client, url: &str, api_key: &str, bearer: &str) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for bucket in STORAGE_BUCKETS {
+        let name = format!("storage.bucket.{}", bucket);
+        let (result, latency) = probe(
+            client
+                .post(format!("{}/storage/v1/object/list/{}", url, bucket))
+                .header("apikey", api_key)
+                .header("Authorization", format!("Bearer {}", bearer))
+                .json(&json!({ "prefix": "", "limit": 1 })),
+        )
+        .await;
+        checks.push(match result {
+            Ok((status, _)) if status < 400 => Check::new(&name, CheckStatus::Ok, "accessible").latency(latency),
+            Ok((_, body)) if body.contains("Bucket not found") => {
+                Check::new(&name, CheckStatus::Error, "bucket is missing")
+                    .latency(latency)
+                    .hint("Apply migration 0002_storage_policies or create the bucket in the dashboard.")
+            }
+            Ok((status, body)) => Check::new(&name, CheckStatus::Error, format!("status {}: {}", status, body))
+                .latency(latency)
+                .hint("Check the storage policies for this bucket."),
+            Err(e) => transport_failure(&name, e, latency),
+        });
+    }
+    checks
+}
+
+async fn check_openai(client: &Client, api_key: &str) -> Vec<Check> {
+    let name = "openai.embedding_model";
+    let (result, latency) = probe(
+        client
+            .get(format!("https://api.openai.com/v1/models/{}", EMBEDDING_MODEL))
+            .header("Authorization", format!("Bearer {}", api_key)),
+    )
+    .await;
+    vec![match result {
+        Ok((status, _)) if status < 400 => {
+            Check::new(name, CheckStatus::Ok, format!("{} available", EMBEDDING_MODEL)).latency(latency)
+        }
+        Ok((401, _)) => Check::new(name, CheckStatus::Error, "API key rejected")
+            .latency(latency)
+            .hint("OPENAI_API_KEY is invalid or revoked."),
+        Ok((404, _)) => Check::new(name, CheckStatus::Error, format!("{} is not available", EMBEDDING_MODEL))
+            .latency(latency)
+            .hint("The OpenAI project behind OPENAI_API_KEY has no access to this model."),
+        Ok((429, _)) => Check::new(name, CheckStatus::Warning, "rate limited or out of quota")
+            .latency(latency)
+            .hint("Check usage limits and billing in the OpenAI dashboard."),
+        Ok((status, body)) => {
+            Check::new(name, CheckStatus::Error, format!("status {}: {}", status, body)).latency(latency)
+        }
+        Err(e) => transport_failure(name, e, latency),
+    }]
+}
+
+/// Checks every configured backend and returns a report with one entry per
+/// check. The command itself only fails if the report cannot be built.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn diagnostics() -> Result<Response<Value>, String> {
+    dotenv().ok();
+    let client = Client::new();
+    let mut checks = check_config();
+
+    match (env::var("VITE_SUPABASE_URL"), env::var("VITE_SUPABASE_API_KEY")) {
+        (Ok(url), Ok(api_key)) => {
+            // Signed in, tables and storage are checked with the user's token
+            // so RLS problems show up too.
+            let bearer = match current_session() {
+                Ok(session) => session.access_token,
+                Err(_) => api_key.clone(),
+            };
+            checks.extend(check_supabase(&client, &url, &api_key, &bearer).await);
+            checks.extend(check_storage(&client, &url, &api_key, &bearer).await);
+        }
+        _ => checks.push(Check::new(
+            "supabase",
+            CheckStatus::Skipped,
+            "Supabase is not configured",
+        )),
+    }
+
+    match env::var("OPENAI_API_KEY") {
+        Ok(api_key) => checks.extend(check_openai(&client, &api_key).await),
+        Err(_) => checks.push(Check::new("openai", CheckStatus::Skipped, "OpenAI is not configured")),
+    }
+
+    let overall = if checks.iter().any(|c| c.status == CheckStatus::Error) {
+        CheckStatus::Error
+    } else if checks.iter().any(|c| c.status == CheckStatus::Warning) {
+        CheckStatus::Warning
+    } else {
+        CheckStatus::Ok
+    };
+
+    for check in checks.iter().filter(|c| c.status == CheckStatus::Error) {
+        tracing::warn!(check = %check.name, detail = %check.message, "diagnostic check failed");
+    }
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "status": overall,
+            "signed_in": current_session().is_ok(),
+            "checks": checks,
+        })),
+        error: None,
+    })
+}
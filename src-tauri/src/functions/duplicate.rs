@@ -0,0 +1,494 @@
+use crate::functions::database_views::ViewConfig;
+use crate::functions::embeddings::{queue_indexing, IndexJob};
+use crate::functions::page_tree::PageForest;
+use crate::functions::pages::{fetch_workspace_pages, ids_filter, next_position};
+use crate::functions::links::index_links;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use supabase_rs::generate_random_id;
+use uuid::Uuid;
+
+pub const IMAGE_BUCKET: &str = "images";
+
+/// Block ids are bigints, which PostgREST returns as numbers; older rows may
+/// have been written as strings.
+pub fn block_id(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|id| id.parse().ok()))
+}
+
+/// The Storage key of an image block's URL in the images bucket, e.g.
+/// `.../object/public/images/<key>?&height=auto` -> `<key>`. Signed URLs
+/// (`.../object/sign/images/<key>?token=...`) are recognised too.
+pub fn image_key(content: &str) -> Option<&str> {
+    ["public", "sign", "authenticated"].iter().find_map(|access| {
+        let marker = format!("/storage/v1/object/{}/{}/", access, IMAGE_BUCKET);
+        let start = content.find(&marker)? + marker.len();
+        let key = content[start..].split(['?', '#']).next()?;
+        (!key.is_empty()).then_some(key)
+    })
+}
+
+/// Copies the image behind an image block and returns the block content
+/// pointing at the copy, with the copy's key, so deleting either page leaves
+/// the other intact.
+async fn copy_image(
+    supabase_client: &RestClient,
+    content: &str,
+) -> Result<Option<(String, String)>, String> {
+    let Some(key) = image_key(content) else {
+        return Ok(None);
+    };
+    let new_key = Uuid::new_v4().to_string();
+    supabase_client.copy_object(IMAGE_BUCKET, key, &new_key).await?;
+    Ok(Some((content.replacen(key, &new_key, 1), new_key)))
+}
+
+/// Deletes images copied for a copy that failed part way.
+pub async fn remove_images(supabase_client: &RestClient, keys: &[String]) {
+    if keys.is_empty() {
+        return;
+    }
+    if let Err(e) = supabase_client.delete_objects(IMAGE_BUCKET, keys).await {
+        tracing::warn!(error = %e, images = keys.len(), "failed to remove copied images");
+    }
+}
+
+/// New rows for a set of copied blocks, plus the indexing work they need.
+pub struct CopiedBlocks {
+    pub blocks: Vec<Value>,
+    pub jobs: Vec<IndexJob>,
+    /// Keys of the image copies, to delete if the blocks aren't saved.
+    pub image_keys: Vec<String>,
+    pub images_failed: usize,
+}
+
+/// Builds copies of `blocks` under the pages they map to in `page_ids`, with
+/// fresh ids and `parent_block_id` remapped to match. Text content is passed
+/// through `rewrite`; image blocks get their own copy of the image.
+pub async fn copy_blocks(
+    supabase_client: &RestClient,
+    blocks: &[Value],
+    page_ids: &HashMap<String, String>,
+    rewrite: impl Fn(&str) -> String,
+) -> CopiedBlocks {
+    let block_ids: HashMap<i64, i64> = blocks
+        .iter()
+        .filter_map(|block| block_id(&block["id"]))
+        .map(|id| (id, generate_random_id()))
+        .collect();
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut copied = CopiedBlocks {
+        blocks: Vec::with_capacity(blocks.len()),
+        jobs: Vec::new(),
+        image_keys: Vec::new(),
+        images_failed: 0,
+    };
+    for block in blocks {
+        let Some(new_id) = block_id(&block["id"]).and_then(|id| block_ids.get(&id)) else {
+            continue;
+        };
+        let Some(new_page_id) = block["page_id"].as_str().and_then(|id| page_ids.get(id)) else {
+            continue;
+        };
+        let block_type = block["type"].as_str().unwrap_or("text").to_string();
+        let mut content = block["content"].as_str().unwrap_or_default().to_string();
+
+        if block_type == "image" {
+            match copy_image(supabase_client, &content).await {
+                Ok(Some((image, key))) => {
+                    content = image;
+                    copied.image_keys.push(key);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    // The copy keeps pointing at the original image
+                    tracing::warn!(error = %e, "failed to copy image");
+                    copied.images_failed += 1;
+                }
+            }
+        } else {
+            content = rewrite(&content);
+            copied.jobs.push(IndexJob {
+                block_id: new_id.to_string(),
+                page_id: new_page_id.clone(),
+                content: content.clone(),
+                block_type: block_type.clone(),
+            });
+        }
+
+        copied.blocks.push(json!({
+            "id": new_id,
+            "page_id": new_page_id,
+            "parent_block_id": block_id(&block["parent_block_id"]).and_then(|id| block_ids.get(&id)),
+            "type": block_type,
+            "order": block["order"].as_i64().unwrap_or_default(),
+            "content": content,
+            "created_at": now,
+            "updated_at": now,
+        }));
+    }
+    copied
+}
+
+/// `id`'s new id when it is one of the copied pages or properties.
+fn remap_id(id: &str, ids: &HashMap<String, String>) -> String {
+    ids.get(id).cloned().unwrap_or_else(|| id.to_string())
+}
+
+/// A copied row's values, keyed by the new property ids. Relation values
+/// list row ids, so rows copied along are remapped; every other value is
+/// copied as is, even if it happens to look like an id.
+fn remap_row_properties(
+    properties: &Value,
+    relations: &HashSet<String>,
+    ids: &HashMap<String, String>,
+) -> Value {
+    let Some(values) = properties.as_object() else {
+        return properties.clone();
+    };
+    Value::Object(
+        values
+            .iter()
+            .map(|(property_id, value)| {
+                let value = match value {
+                    Value::Array(rows) if relations.contains(property_id) => Value::Array(
+                        rows.iter()
+                            .map(|row| match row.as_str() {
+                                Some(row_id) => json!(remap_id(row_id, ids)),
+                                None => row.clone(),
+                            })
+                            .collect(),
+                    ),
+                    other => other.clone(),
+                };
+                (remap_id(property_id, ids), value)
+            })
+            .collect(),
+    )
+}
+
+/// A copied property's options; a relation follows its database when that
+/// was copied too.
+fn remap_property_options(options: &Value, ids: &HashMap<String, String>) -> Value {
+    let mut options = options.clone();
+    if let Some(target) = options["database_page_id"].as_str() {
+        options["database_page_id"] = json!(remap_id(target, ids));
+    }
+    options
+}
+
+/// A copied view's config, with its filters, sorts, grouping, date property
+/// and columns pointing at the new property ids.
+fn remap_view_config(config: &Value, ids: &HashMap<String, String>) -> Value {
+    let Ok(mut config) = serde_json::from_value::<ViewConfig>(config.clone()) else {
+        return config.clone();
+    };
+    for filter in &mut config.query.filters {
+        filter.property_id = remap_id(&filter.property_id, ids);
+    }
+    for sort in &mut config.query.sorts {
+        sort.property_id = remap_id(&sort.property_id, ids);
+    }
+    for id in config
+        .query
+        .group_by
+        .iter_mut()
+        .chain(config.date_property.iter_mut())
+        .chain(config.visible_properties.iter_mut().flatten())
+    {
+        *id = remap_id(id, ids);
+    }
+    json!(config)
+}
+
+/// Deletes the pages of a copy that failed part way; their blocks, database
+/// properties and views go with them. `image_keys` are the images copied for
+/// it.
+async fn remove_copy(
+    supabase_client: &RestClient,
+    page_ids: &HashMap<String, String>,
+    image_keys: &[String],
+) {
+    remove_images(supabase_client, image_keys).await;
+    if let Err(e) = supabase_client
+        .delete(
+            "pages",
+            &format!("id={}", ids_filter(&page_ids.values().cloned().collect::<Vec<_>>())),
+        )
+        .await
+    {
+        tracing::warn!(error = %e, "failed to remove partial copy");
+    }
+}
+
+/// Copies a page and its blocks, and with `include_subpages` every page
+/// below it. The copy is placed after the original's last sibling, block
+/// parents are remapped to the new block ids, images are copied in Storage
+/// and the new blocks are queued for embedding. Databases keep their
+/// properties and views, and copied rows keep their values.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, include_subpages = include_subpages.unwrap_or(false)))]
+pub async fn duplicate_page(
+    page_id: String,
+    include_subpages: Option<bool>,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let forest = PageForest::new(fetch_workspace_pages(&supabase_client, &workspace_id).await?);
+
+    let Some(original) = forest.get(&page_id) else {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    };
+
+    // Parents come before their children, so one insert satisfies the
+    // parent_page_id foreign key.
+    let mut source_ids = vec![page_id.clone()];
+    if include_subpages.unwrap_or(false) {
+        source_ids.extend(forest.descendants(&page_id));
+    }
+    let page_ids: HashMap<String, String> = source_ids
+        .iter()
+        .map(|id| (id.clone(), Uuid::new_v4().to_string()))
+        .collect();
+
+    // Databases among the copied pages take their properties and views
+    // along, under new ids that the copied rows and views are remapped to
+    let database_ids: Vec<String> = source_ids
+        .iter()
+        .filter(|id| forest.get(id).is_some_and(|page| page.is_database))
+        .cloned()
+        .collect();
+    let (properties, views) = if database_ids.is_empty() {
+        (Vec::new(), Vec::new())
+    } else {
+        let filter = format!("database_page_id={}&order=position.asc", ids_filter(&database_ids));
+        (
+            supabase_client.select("database_properties", &filter).await?,
+            supabase_client.select("database_views", &filter).await?,
+        )
+    };
+    let mut ids = page_ids.clone();
+    for property in &properties {
+        if let Some(id) = property["id"].as_str() {
+            ids.insert(id.to_string(), Uuid::new_v4().to_string());
+        }
+    }
+    let relations: HashSet<String> = properties
+        .iter()
+        .filter(|property| property["type"] == "relation")
+        .filter_map(|property| property["id"].as_str().map(str::to_string))
+        .collect();
+    let property_rows: Vec<Value> = properties
+        .iter()
+        .map(|property| {
+            json!({
+                "id": property["id"].as_str().map(|id| remap_id(id, &ids)),
+                "database_page_id": property["database_page_id"].as_str().map(|id| remap_id(id, &ids)),
+                "name": property["name"],
+                "type": property["type"],
+                "options": remap_property_options(&property["options"], &ids),
+                "position": property["position"],
+            })
+        })
+        .collect();
+    let view_rows: Vec<Value> = views
+        .iter()
+        .map(|view| {
+            json!({
+                "database_page_id": view["database_page_id"].as_str().map(|id| remap_id(id, &ids)),
+                "name": view["name"],
+                "layout": view["layout"],
+                "config": remap_view_config(&view["config"], &ids),
+                "position": view["position"],
+            })
+        })
+        .collect();
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let new_root_id = page_ids[&page_id].clone();
+    let root_parent = forest.parent(&page_id);
+    let root_position = next_position(&supabase_client, &workspace_id, root_parent).await?;
+    let blocks = supabase_client
+        .select(
+            "blocks",
+            &format!("page_id={}", ids_filter(&source_ids)),
+        )
+        .await?;
+
+    // Images are copied first; if the copy then fails, the copied images
+    // are deleted along with it
+    let mut image_keys: Vec<String> = Vec::new();
+    let mut pages: Vec<Value> = Vec::with_capacity(source_ids.len());
+    for id in &source_ids {
+        let Some(page) = forest.get(id) else { continue };
+        let (title, parent, position) = if *id == page_id {
+            (
+                format!("{} (copy)", original.title),
+                root_parent.map(str::to_string),
+                root_position,
+            )
+        } else {
+            (
+                page.title.clone(),
+                forest.parent(id).and_then(|parent| page_ids.get(parent)).cloned(),
+                page.position,
+            )
+        };
+        let cover_url = match &page.cover_url {
+            Some(cover) => match copy_image(&supabase_client, cover).await {
+                Ok(Some((copied, key))) => {
+                    image_keys.push(key);
+                    Some(copied)
+                }
+                Ok(None) => Some(cover.clone()),
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to copy cover image");
+                    Some(cover.clone())
+                }
+            },
+            None => None,
+        };
+        pages.push(json!({
+            "id": page_ids[id],
+            "user_id": session.user_id,
+            "title": title,
+            "parent_page_id": parent,
+            "position": position,
+            "icon": page.icon,
+            "cover_url": cover_url,
+            "tags": page.tags,
+            "description": page.description,
+            "workspace_id": page.workspace_id,
+            "is_database": page.is_database,
+            "properties": remap_row_properties(&page.properties, &relations, &ids),
+            "created_at": now,
+            "updated_at": now,
+        }));
+    }
+
+    let copied = copy_blocks(&supabase_client, &blocks, &page_ids, |content| content.to_string()).await;
+    image_keys.extend(copied.image_keys.iter().cloned());
+
+    if let Err(e) = supabase_client.insert("pages", json!(pages)).await {
+        remove_images(&supabase_client, &image_keys).await;
+        return Err(e);
+    }
+    // Don't leave a page without its content or schema behind
+    for (table, rows) in [("database_properties", &property_rows), ("database_views", &view_rows)] {
+        if rows.is_empty() {
+            continue;
+        }
+        if let Err(e) = supabase_client.insert(table, json!(rows)).await {
+            remove_copy(&supabase_client, &page_ids, &image_keys).await;
+            return Err(e);
+        }
+    }
+    if !copied.blocks.is_empty() {
+        if let Err(e) = supabase_client.insert("blocks", json!(copied.blocks)).await {
+            remove_copy(&supabase_client, &page_ids, &image_keys).await;
+            return Err(e);
+        }
+        if let Err(e) = index_links(&supabase_client, &copied.blocks).await {
+            tracing::warn!(error = %e, "failed to index page links");
+        }
+    }
+
+    tracing::info!(
+        pages = pages.len(),
+        blocks = copied.blocks.len(),
+        properties = property_rows.len(),
+        views = view_rows.len(),
+        images_copied = image_keys.len(),
+        images_failed = copied.images_failed,
+        "page duplicated"
+    );
+    queue_indexing(copied.jobs, session.user_id);
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "page_id": new_root_id,
+            "page_ids": page_ids,
+            "pages": pages.len(),
+            "blocks": copied.blocks.len(),
+            "images_copied": image_keys.len(),
+            "images_failed": copied.images_failed,
+        })),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids() -> HashMap<String, String> {
+        HashMap::from([
+            ("status".to_string(), "status-copy".to_string()),
+            ("link".to_string(), "link-copy".to_string()),
+            ("row-1".to_string(), "row-1-copy".to_string()),
+            ("db".to_string(), "db-copy".to_string()),
+        ])
+    }
+
+    #[test]
+    fn row_values_keep_text_that_looks_like_an_id() {
+        let relations = HashSet::from(["link".to_string()]);
+        let properties = json!({
+            "status": "row-1",
+            "link": ["row-1", "row-2"],
+            "other": ["row-1"],
+        });
+        assert_eq!(
+            remap_row_properties(&properties, &relations, &ids()),
+            json!({
+                "status-copy": "row-1",
+                "link-copy": ["row-1-copy", "row-2"],
+                "other": ["row-1"],
+            })
+        );
+    }
+
+    #[test]
+    fn relation_follows_a_copied_database() {
+        assert_eq!(
+            remap_property_options(&json!({ "database_page_id": "db" }), &ids()),
+            json!({ "database_page_id": "db-copy" })
+        );
+        assert_eq!(
+            remap_property_options(&json!({ "options": [{ "name": "db" }] }), &ids()),
+            json!({ "options": [{ "name": "db" }] })
+        );
+    }
+
+    #[test]
+    fn view_config_points_at_the_copied_properties() {
+        let config = json!({
+            "filters": [{ "property_id": "status", "operator": "equals", "value": "status" }],
+            "sorts": [{ "property_id": "link", "direction": "desc" }],
+            "group_by": "status",
+            "date_property": "due",
+            "visible_properties": ["status", "link"],
+        });
+        let remapped = remap_view_config(&config, &ids());
+        assert_eq!(remapped["filters"][0]["property_id"], "status-copy");
+        assert_eq!(remapped["filters"][0]["value"], "status");
+        assert_eq!(remapped["sorts"][0]["property_id"], "link-copy");
+        assert_eq!(remapped["group_by"], "status-copy");
+        assert_eq!(remapped["date_property"], "due");
+        assert_eq!(remapped["visible_properties"], json!(["status-copy", "link-copy"]));
+    }
+}
@@ -1,10 +1,15 @@
 use crate::functions::responses::{Response, StatusCode};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::http::{self, Upstream};
 use dotenv::dotenv;
 use reqwest::{Client, header};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::env;
 
+pub const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
 #[derive(Serialize, Deserialize)]
 struct EmbeddingResponse {
     data: Vec<EmbeddingData>,
@@ -33,17 +38,17 @@ async fn generate_embedding(text: &str) -> Result<Vec<f32>, String> {
     let url = "https://api.openai.com/v1/embeddings";
     
     let body = json!({
-        "model": "text-embedding-3-small",
+        "model": EMBEDDING_MODEL,
         "input": text,
         "encoding_format": "float"
     });
     
-    let response = client
+    let request = client
         .post(url)
         .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
         .header(header::CONTENT_TYPE, "application/json")
-        .json(&body)
-        .send()
+        .json(&body);
+    let response = http::send_idempotent(Upstream::OpenAi, request)
         .await
         .map_err(|e| format!("Failed to send request to OpenAI: {}", e))?;
     
@@ -64,7 +69,64 @@ async fn generate_embedding(text: &str) -> Result<Vec<f32>, String> {
     Ok(embedding_response.data[0].embedding.clone())
 }
 
+/// A block waiting to be embedded by `queue_indexing`.
+pub struct IndexJob {
+    pub block_id: String,
+    pub page_id: String,
+    pub content: String,
+    pub block_type: String,
+}
+
+/// Embeds `jobs` one at a time in the background, skipping the same short
+/// blocks the editor skips. Failures are logged; the editor re-indexes a
+/// block the next time it is edited.
+pub fn queue_indexing(jobs: Vec<IndexJob>, user_id: String) {
+    let jobs: Vec<IndexJob> = jobs
+        .into_iter()
+        .filter(|job| job.content.trim().len() > 10)
+        .collect();
+    if jobs.is_empty() {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let total = jobs.len();
+        let mut indexed = 0;
+        for job in jobs {
+            // User tokens are short-lived, so each job gets a fresh client
+            let supabase_client = match initialize_user_client().await {
+                Ok(client) => client,
+                Err(e) => {
+                    tracing::warn!(error = %e, "indexing queue stopped");
+                    break;
+                }
+            };
+            let embedding = match generate_embedding(&job.content).await {
+                Ok(embedding) => embedding,
+                Err(e) => {
+                    tracing::warn!(block_id = %job.block_id, error = %e, "failed to generate embedding");
+                    continue;
+                }
+            };
+            let body = json!({
+                "block_id": job.block_id,
+                "content": job.content,
+                "embedding": embedding,
+                "page_id": job.page_id,
+                "metadata": { "type": job.block_type },
+                "user_id": user_id,
+            });
+            match supabase_client.upsert("embeddings", "block_id,page_id", body).await {
+                Ok(_) => indexed += 1,
+                Err(e) => tracing::warn!(block_id = %job.block_id, error = %e, "failed to store embedding"),
+            }
+        }
+        tracing::info!(indexed, total, "indexing queue finished");
+    });
+}
+
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(block_id = %block_id, page_id = %page_id))]
 pub async fn index_block(
     block_id: String,
     content: String,
@@ -72,14 +134,12 @@ pub async fn index_block(
     metadata: Value,
     user_id: String 
 ) -> Result<Response<Value>, String> {
-    dotenv().ok();
-    let supabase_url = env::var("VITE_SUPABASE_URL").map_err(|e| e.to_string())?;
-    let supabase_key = env::var("VITE_SUPABASE_API_KEY").map_err(|e| e.to_string())?;
+    let supabase_client = initialize_user_client().await?;
 
-    println!("[index_block] Indexing block_id: {}, user_id: {}", block_id, user_id);
+    tracing::debug!(content_len = content.len(), "indexing block");
 
     if content.trim().len() <= 10 { // Check from your frontend logic
-        println!("[index_block] Content for block_id {} is too short or empty, skipping indexing.", block_id);
+        tracing::debug!("content too short, skipping indexing");
         return Ok(Response {
             status: StatusCode::Ok, // Or a custom status indicating not indexed
             data: Some(json!({"message": "Content too short, block not indexed.", "block_id": block_id})),
@@ -90,7 +150,7 @@ pub async fn index_block(
     let embedding = match generate_embedding(&content).await {
         Ok(emb) => emb,
         Err(e) => {
-            println!("[index_block] Error generating embedding for block_id {}: {}", block_id, e);
+            tracing::error!(error = %e, "failed to generate embedding");
             return Ok(Response {
                 status: StatusCode::InternalServerError,
                 data: None,
@@ -99,10 +159,6 @@ pub async fn index_block(
         }
     };
 
-    let client = Client::new();
-    // Updated to use composite primary key (block_id, page_id) for conflict resolution
-    let endpoint = format!("{}/rest/v1/embeddings?on_conflict=block_id,page_id", supabase_url); 
-
     let body = json!({
         "block_id": block_id.clone(), // Required for composite key matching
         "content": content,
@@ -111,99 +167,51 @@ pub async fn index_block(
         "metadata": metadata,
         "user_id": user_id 
     });
-    
-    // println!("[index_block] Request body: {}", serde_json::to_string_pretty(&body).unwrap_or_default());
 
-    let response = client
-        .post(&endpoint)
-        .header("apikey", &supabase_key)
-        .header("Authorization", format!("Bearer {}", supabase_key))
-        .header("Content-Type", "application/json")
-        // `return=representation` asks Supabase to return the inserted/updated row.
-        // `resolution=merge-duplicates` tells it to update if there's a conflict.
-        .header("Prefer", "resolution=merge-duplicates,return=representation") 
-        .json(&body)
-        .send()
+    // Upsert on the composite primary key (block_id, page_id) so re-indexing
+    // a block replaces its previous embedding.
+    match supabase_client
+        .upsert("embeddings", "block_id,page_id", body)
         .await
-        .map_err(|e| {
-            println!("[index_block] Error sending request to Supabase for block_id {}: {}", block_id, e);
-            e.to_string()
-        })?;
-
-    let response_status = response.status();
-    println!("[index_block] Supabase response status for block_id {}: {}", block_id, response_status);
-
-    if response_status.is_success() {
-        // Even with return=representation, it's good to handle potentially empty text
-        let response_text = response.text().await.map_err(|e| {
-            println!("[index_block] Error reading Supabase success response text for block_id {}: {}", block_id, e);
-            e.to_string()
-        })?;
-
-        if response_text.is_empty() {
-            // This case should be less likely with return=representation but good to cover
-            println!("[index_block] Supabase returned successful status but empty body for block_id {}. Assuming success.", block_id);
+    {
+        Ok(rows) => {
+            tracing::info!("block indexed");
             Ok(Response {
                 status: StatusCode::Ok,
-                data: Some(json!({"message": "Block indexed (Supabase returned empty body).", "block_id": block_id})),
+                data: Some(json!(rows)),
                 error: None,
             })
-        } else {
-            // Attempt to parse the JSON
-            match serde_json::from_str::<Value>(&response_text) {
-                Ok(json_response) => Ok(Response {
-                    status: StatusCode::Ok,
-                    data: Some(json_response),
-                    error: None,
-                }),
-                Err(e) => {
-                    println!("[index_block] Error parsing Supabase JSON response for block_id {}: {}. Response text was: {}", block_id, e, response_text);
-                    Ok(Response { 
-                        status: StatusCode::InternalServerError, // Or a more specific error
-                        data: None,
-                        error: Some(format!("Failed to parse Supabase response: {}", e)),
-                    })
-                }
-            }
         }
-    } else {
-        let error_text = response.text().await.map_err(|e| {
-            println!("[index_block] Error reading Supabase error response text for block_id {}: {}", block_id, e);
-            e.to_string()
-        })?;
-        println!("[index_block] Supabase error for block_id {}. Status: {}. Response: {}", block_id, response_status, error_text);
-        Ok(Response {
-            status: match response_status.as_u16() {
-                200..=299 => StatusCode::Ok,
-                400..=499 => StatusCode::BadRequest, 
-                500..=599 => StatusCode::InternalServerError,
-                _ => StatusCode::InternalServerError,
-            },
-            data: None,
-            error: Some(format!("Supabase error ({}): {}", response_status, error_text)),
-        })
+        Err(e) => {
+            tracing::error!(error = %e, "failed to store embedding");
+            Ok(Response {
+                status: StatusCode::InternalServerError,
+                data: None,
+                error: Some(format!("Supabase error: {}", e)),
+            })
+        }
     }
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(user_id = %user_id))]
 pub async fn query_similar_blocks(
     query: String, 
     threshold: f32, 
     limit: i32,
     user_id: String // Add user_id parameter
 ) -> Result<Response<Value>, String> {
-    dotenv().ok();
-    let supabase_url = env::var("VITE_SUPABASE_URL").map_err(|e| e.to_string())?;
-    let supabase_key = env::var("VITE_SUPABASE_API_KEY").map_err(|e| e.to_string())?;
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
 
     // Generate embedding for the query
-    println!("[query_similar_blocks] Generating embedding for query...");
+    tracing::debug!(query_len = query.len(), threshold, limit, "generating query embedding");
     let embedding = match generate_embedding(&query).await {
         Ok(emb) => {
             emb
         }
         Err(e) => {
-            println!("[query_similar_blocks] Error generating embedding: {}", e);
+            tracing::error!(error = %e, "failed to generate embedding");
             return Ok(Response {
                 status: StatusCode::InternalServerError,
                 data: None,
@@ -213,64 +221,33 @@ pub async fn query_similar_blocks(
     };
     
     // Query similar embeddings from Supabase
-    let client = Client::new();
-    let endpoint = format!(
-        "{}/rest/v1/rpc/match_embeddings", 
-        supabase_url
-    );
-    
     let body = json!({
         "query_embedding": embedding,
         "match_threshold": threshold,
         "match_count": limit,
-        "p_user_id": user_id // Send as text instead of converting to i64
+        "p_user_id": user_id, // Send as text instead of converting to i64
+        "p_workspace_id": workspace_id,
     });
-        
-    let response = client
-        .post(&endpoint)
-        .header("apikey", &supabase_key)
-        .header("Authorization", format!("Bearer {}", supabase_key))
-        .header("Content-Type", "application/json")
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| {
-            println!("[query_similar_blocks] Error sending request to Supabase: {}", e);
-            e.to_string()
-        })?;
-    
-    println!("[query_similar_blocks] Supabase response status: {}", response.status());
 
-    if response.status().is_success() {
-        let response_text = response.text().await.map_err(|e| {
-            println!("[query_similar_blocks] Error reading successful Supabase response text: {}", e);
-            e.to_string()
-        })?;
-        println!("[query_similar_blocks] Supabase successful response text: {}", response_text);
-        let json_response: Value = serde_json::from_str(&response_text).map_err(|e| {
-            println!("[query_similar_blocks] Error parsing successful Supabase response to JSON: {}", e);
-            e.to_string()
-        })?;
-        Ok(Response {
+    match supabase_client.rpc_read("match_embeddings", body).await {
+        Ok(json_response) => Ok(Response {
             status: StatusCode::Ok,
             data: Some(json_response),
             error: None,
-        })
-    } else {
-        let error_text = response.text().await.map_err(|e| {
-            println!("[query_similar_blocks] Error reading error Supabase response text: {}", e);
-            e.to_string()
-        })?;
-        println!("[query_similar_blocks] Supabase error response text: {}", error_text);
-        Ok(Response {
-            status: StatusCode::InternalServerError,
-            data: None,
-            error: Some(format!("Supabase error: {}", error_text)),
-        })
+        }),
+        Err(e) => {
+            tracing::error!(error = %e, "match_embeddings failed");
+            Ok(Response {
+                status: StatusCode::InternalServerError,
+                data: None,
+                error: Some(format!("Supabase error: {}", e)),
+            })
+        }
     }
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn ask_llm(
     query: String,
     context: Option<String>
@@ -285,7 +262,11 @@ pub async fn ask_llm(
     // Create messages array
     let mut messages = Vec::new();
 
-    println!("[ask_llm] Received context: {:?}", context); // Debug print for incoming context
+    tracing::debug!(
+        query_len = query.len(),
+        context_len = context.as_ref().map(|c| c.len()),
+        "building chat request"
+    );
     
     // Construct the system message content
     let system_content = match context {
@@ -308,8 +289,6 @@ pub async fn ask_llm(
         }
     };
 
-    println!("[ask_llm] Constructed system_content: {}", system_content); // Debug print for system content
-    
     messages.push(json!({
         "role": "system",
         "content": system_content
@@ -320,8 +299,6 @@ pub async fn ask_llm(
         "role": "user",
         "content": query.clone() // Clone query for debug print later if needed, or just use query directly
     }));
-
-    println!("[ask_llm] Final messages array: {:?}", messages); // Debug print for the messages array
     
     // Prepare the request body
     let body = json!({
@@ -331,12 +308,12 @@ pub async fn ask_llm(
     });
     
     // Send the request
-    let response = client
+    let request = client
         .post(url)
         .header(header::AUTHORIZATION, format!("Bearer {}", api_key))
         .header(header::CONTENT_TYPE, "application/json")
-        .json(&body)
-        .send()
+        .json(&body);
+    let response = http::send_idempotent(Upstream::OpenAi, request)
         .await
         .map_err(|e| format!("Failed to send request: {}", e))?;
     
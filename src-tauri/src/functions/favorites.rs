@@ -0,0 +1,142 @@
+use crate::functions::pages::{live_page_exists, Page, PAGE_COLUMNS};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::functions::supabase::initialize_user_client;
+use serde_json::{json, Value};
+use urlencoding::encode;
+
+/// Visits kept per user; older ones are dropped as new pages are opened.
+const RECENT_PAGES_KEPT: usize = 50;
+const DEFAULT_RECENT_PAGES: usize = 10;
+
+fn page_not_found() -> Response<Value> {
+    Response {
+        status: StatusCode::NotFound,
+        data: None,
+        error: Some("Page not found".to_string()),
+    }
+}
+
+/// The live pages embedded in `rows` of a table joined to `pages`.
+fn embedded_pages(rows: &[Value]) -> Vec<Page> {
+    rows.iter()
+        .filter(|row| row["pages"].is_object())
+        .map(|row| Page::from_row(&row["pages"]))
+        .collect()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn pin_page(page_id: String) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    if !live_page_exists(&supabase_client, &page_id).await? {
+        return Ok(page_not_found());
+    }
+
+    // Pinning twice keeps the original pin time, and with it the page's place
+    supabase_client
+        .upsert(
+            "page_favorites",
+            "user_id,page_id",
+            json!({ "user_id": session.user_id, "page_id": page_id }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "pinned": true })),
+        error: None,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn unpin_page(page_id: String) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    supabase_client
+        .delete(
+            "page_favorites",
+            &format!("user_id=eq.{}&page_id=eq.{}", session.user_id, encode(&page_id)),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "pinned": false })),
+        error: None,
+    })
+}
+
+/// The user's pinned pages in the order they were pinned. Pages in the
+/// trash are left out but stay pinned if restored.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn fetch_favorites() -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .select(
+            "page_favorites",
+            &format!(
+                "select=created_at,pages!inner({})&user_id=eq.{}&pages.deleted_at=is.null&order=created_at.asc",
+                PAGE_COLUMNS, session.user_id
+            ),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(embedded_pages(&rows))),
+        error: None,
+    })
+}
+
+/// Records that the user opened a page, for `fetch_recent_pages`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn record_page_visit(page_id: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    if !live_page_exists(&supabase_client, &page_id).await? {
+        return Ok(page_not_found());
+    }
+
+    supabase_client
+        .rpc(
+            "record_page_visit",
+            json!({ "p_page_id": page_id, "p_keep": RECENT_PAGES_KEPT }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: None,
+        error: None,
+    })
+}
+
+/// The most recently visited pages, newest first.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(limit = ?limit))]
+pub async fn fetch_recent_pages(limit: Option<usize>) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let limit = limit.unwrap_or(DEFAULT_RECENT_PAGES).clamp(1, RECENT_PAGES_KEPT);
+    let rows = supabase_client
+        .select(
+            "page_visits",
+            &format!(
+                "select=visited_at,pages!inner({})&user_id=eq.{}&pages.deleted_at=is.null&order=visited_at.desc&limit={}",
+                PAGE_COLUMNS, session.user_id, limit
+            ),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(embedded_pages(&rows))),
+        error: None,
+    })
+}
@@ -0,0 +1,325 @@
+use crate::functions::page_tree::PageForest;
+use crate::functions::pages::{fetch_workspace_pages, ids_filter};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use urlencoding::encode;
+
+const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.8;
+/// Similarity edges compare every pair of pages, so they are skipped for
+/// larger graphs.
+const MAX_SIMILARITY_PAGES: usize = 300;
+/// Each page keeps only its closest matches, so a dense cluster doesn't
+/// turn into a hairball.
+const SIMILAR_EDGES_PER_PAGE: usize = 5;
+/// Page ids per embeddings request, keeping the `in.(...)` filter well
+/// within URL length limits.
+const CENTROID_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    Parent,
+    Mention,
+    Similar,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub title: String,
+    pub icon: Option<String>,
+    pub tags: Vec<String>,
+    pub parent_page_id: Option<String>,
+    /// Levels below the focus page, or below the top level without one.
+    pub depth: u32,
+    pub backlinks: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    /// Blocks making the mention, or cosine similarity; 1 for parent edges.
+    pub weight: f32,
+}
+
+/// Parses a pgvector value, which PostgREST returns as a string like
+/// `"[0.1,0.2]"`.
+fn parse_embedding(value: &Value) -> Option<Vec<f32>> {
+    match value {
+        Value::String(text) => serde_json::from_str(text).ok(),
+        Value::Array(_) => serde_json::from_value(value.clone()).ok(),
+        _ => None,
+    }
+}
+
+/// The normalised mean of each page's block embeddings. Only the embeddings
+/// of `nodes` are fetched, in batches.
+async fn page_centroids(
+    supabase_client: &RestClient,
+    nodes: &HashSet<&str>,
+) -> Result<HashMap<String, Vec<f32>>, String> {
+    let mut node_ids: Vec<String> = nodes.iter().map(|id| id.to_string()).collect();
+    node_ids.sort_unstable();
+    let mut rows: Vec<Value> = Vec::new();
+    for batch in node_ids.chunks(CENTROID_BATCH_SIZE) {
+        rows.extend(
+            supabase_client
+                .select(
+                    "embeddings",
+                    &format!("select=page_id,embedding&page_id={}", ids_filter(batch)),
+                )
+                .await?,
+        );
+    }
+
+    let mut sums: HashMap<String, Vec<f32>> = HashMap::new();
+    for row in &rows {
+        let Some(page_id) = row["page_id"].as_str() else {
+            continue;
+        };
+        let Some(embedding) = parse_embedding(&row["embedding"]) else {
+            continue;
+        };
+        let sum = sums
+            .entry(page_id.to_string())
+            .or_insert_with(|| vec![0.0; embedding.len()]);
+        if sum.len() == embedding.len() {
+            sum.iter_mut().zip(&embedding).for_each(|(s, e)| *s += e);
+        }
+    }
+    for sum in sums.values_mut() {
+        let norm = sum.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            sum.iter_mut().for_each(|x| *x /= norm);
+        }
+    }
+    Ok(sums)
+}
+
+/// Pairs of pages whose centroids are at least `threshold` similar, keeping
+/// an edge when it is among either page's closest matches.
+fn similarity_edges(centroids: &HashMap<String, Vec<f32>>, threshold: f32) -> Vec<GraphEdge> {
+    let mut ids: Vec<&String> = centroids.keys().collect();
+    ids.sort();
+
+    let mut candidates: Vec<(usize, usize, f32)> = Vec::new();
+    for i in 0..ids.len() {
+        for j in (i + 1)..ids.len() {
+            let (a, b) = (&centroids[ids[i]], &centroids[ids[j]]);
+            if a.len() != b.len() {
+                continue;
+            }
+            let similarity: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            if similarity >= threshold {
+                candidates.push((i, j, similarity));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut kept_per_page = vec![0; ids.len()];
+    let mut edges = Vec::new();
+    for (i, j, similarity) in candidates {
+        if kept_per_page[i] < SIMILAR_EDGES_PER_PAGE || kept_per_page[j] < SIMILAR_EDGES_PER_PAGE {
+            kept_per_page[i] += 1;
+            kept_per_page[j] += 1;
+            edges.push(GraphEdge {
+                source: ids[i].clone(),
+                target: ids[j].clone(),
+                kind: EdgeKind::Similar,
+                weight: similarity,
+            });
+        }
+    }
+    edges
+}
+
+/// Nodes and edges for a graph view of the active workspace. With
+/// `focus_page_id` only that page and its subpages are included, and
+/// `depth` limits how many levels below the focus (or the top level) are
+/// shown. Parent and mention edges are always returned; similarity edges
+/// from the pages' embeddings only with `include_similar`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(focus_page_id = ?focus_page_id, depth = ?depth))]
+pub async fn fetch_graph(
+    focus_page_id: Option<String>,
+    depth: Option<u32>,
+    include_similar: Option<bool>,
+    similarity_threshold: Option<f32>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let pages = fetch_workspace_pages(&supabase_client, &workspace_id)
+        .await?
+        .into_iter()
+        .filter(|page| !page.is_template)
+        .collect();
+    let forest = PageForest::new(pages);
+
+    let focus = focus_page_id.filter(|id| !id.is_empty());
+    let (included, base_depth): (Vec<String>, u32) = match &focus {
+        Some(id) if forest.get(id).is_some() => {
+            let mut ids = vec![id.clone()];
+            ids.extend(forest.descendants(id));
+            (ids, forest.depth(id))
+        }
+        Some(_) => {
+            return Ok(Response {
+                status: StatusCode::NotFound,
+                data: None,
+                error: Some("Page not found".to_string()),
+            })
+        }
+        None => {
+            let mut ids = Vec::new();
+            for root in forest.roots() {
+                ids.push(root.clone());
+                ids.extend(forest.descendants(root));
+            }
+            (ids, 0)
+        }
+    };
+    let included: Vec<String> = included
+        .into_iter()
+        .filter(|id| depth.is_none_or(|max| forest.depth(id) - base_depth <= max))
+        .collect();
+    let node_ids: HashSet<&str> = included.iter().map(String::as_str).collect();
+
+    let mut edges: Vec<GraphEdge> = included
+        .iter()
+        .filter_map(|id| {
+            let parent = forest.parent(id).filter(|parent| node_ids.contains(parent))?;
+            Some(GraphEdge {
+                source: parent.to_string(),
+                target: id.clone(),
+                kind: EdgeKind::Parent,
+                weight: 1.0,
+            })
+        })
+        .collect();
+
+    let links = supabase_client
+        .select(
+            "page_links",
+            &format!(
+                "select=source_page_id,target_page_id,pages!inner(workspace_id)&pages.workspace_id=eq.{}",
+                encode(&workspace_id)
+            ),
+        )
+        .await?;
+    let mut mentions: HashMap<(String, String), usize> = HashMap::new();
+    for link in &links {
+        let (Some(source), Some(target)) = (
+            link["source_page_id"].as_str(),
+            link["target_page_id"].as_str(),
+        ) else {
+            continue;
+        };
+        if node_ids.contains(source) && node_ids.contains(target) {
+            *mentions.entry((source.to_string(), target.to_string())).or_default() += 1;
+        }
+    }
+    let mut backlinks: HashMap<String, usize> = HashMap::new();
+    let mut mention_edges: Vec<GraphEdge> = mentions
+        .into_iter()
+        .map(|((source, target), blocks)| {
+            *backlinks.entry(target.clone()).or_default() += 1;
+            GraphEdge {
+                source,
+                target,
+                kind: EdgeKind::Mention,
+                weight: blocks as f32,
+            }
+        })
+        .collect();
+    mention_edges.sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+    edges.extend(mention_edges);
+
+    let mut similarity_skipped = false;
+    if include_similar.unwrap_or(false) {
+        if included.len() > MAX_SIMILARITY_PAGES {
+            similarity_skipped = true;
+        } else {
+            let threshold = similarity_threshold
+                .unwrap_or(DEFAULT_SIMILARITY_THRESHOLD)
+                .clamp(0.0, 1.0);
+            let centroids = page_centroids(&supabase_client, &node_ids).await?;
+            edges.extend(similarity_edges(&centroids, threshold));
+        }
+    }
+
+    let nodes: Vec<GraphNode> = included
+        .iter()
+        .filter_map(|id| forest.get(id))
+        .map(|page| GraphNode {
+            id: page.id.clone(),
+            title: page.title.clone(),
+            icon: page.icon.clone(),
+            tags: page.tags.clone(),
+            parent_page_id: forest.parent(&page.id).map(|id| id.to_string()),
+            depth: forest.depth(&page.id) - base_depth,
+            backlinks: backlinks.get(&page.id).copied().unwrap_or_default(),
+        })
+        .collect();
+    tracing::debug!(nodes = nodes.len(), edges = edges.len(), "graph built");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "nodes": nodes,
+            "edges": edges,
+            "similarity_skipped": similarity_skipped,
+        })),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeddings_parse_from_strings_and_arrays() {
+        assert_eq!(parse_embedding(&json!("[0.5,1]")), Some(vec![0.5, 1.0]));
+        assert_eq!(parse_embedding(&json!([0.25])), Some(vec![0.25]));
+        assert_eq!(parse_embedding(&json!(null)), None);
+    }
+
+    #[test]
+    fn only_pages_above_the_threshold_are_linked() {
+        let centroids = HashMap::from([
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("b".to_string(), vec![0.8, 0.6]),
+            ("c".to_string(), vec![0.0, 1.0]),
+            ("d".to_string(), vec![1.0, 0.0, 0.0]),
+        ]);
+        let edges = similarity_edges(&centroids, 0.75);
+        let pairs: Vec<(&str, &str)> = edges
+            .iter()
+            .map(|edge| (edge.source.as_str(), edge.target.as_str()))
+            .collect();
+        assert_eq!(pairs, [("a", "b")]);
+        assert_eq!(edges[0].kind, EdgeKind::Similar);
+        assert!((edges[0].weight - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn closest_matches_come_first() {
+        let centroids = HashMap::from([
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("b".to_string(), vec![0.8, 0.6]),
+            ("c".to_string(), vec![0.6, 0.8]),
+        ]);
+        let edges = similarity_edges(&centroids, 0.5);
+        let weights: Vec<f32> = edges.iter().map(|edge| edge.weight).collect();
+        assert_eq!(edges.len(), 3);
+        assert!(weights.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+}
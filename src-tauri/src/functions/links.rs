@@ -0,0 +1,279 @@
+use crate::functions::duplicate::block_id;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::page_role;
+use crate::functions::supabase::initialize_user_client;
+use crate::supabase::rest::RestClient;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use urlencoding::encode;
+
+/// Characters of context kept on each side of a mention in a snippet.
+const SNIPPET_CONTEXT: usize = 80;
+
+/// `[[page:<page id>]]` or `[[page:<page id>|<label>]]`. The label is what
+/// the page was called when the mention was written; links resolve by id.
+fn mention_pattern() -> &'static Regex {
+    static MENTION: OnceLock<Regex> = OnceLock::new();
+    MENTION.get_or_init(|| {
+        Regex::new(
+            r"\[\[page:([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})(?:\|([^\]]*))?\]\]",
+        )
+        .unwrap()
+    })
+}
+
+/// The ids of the pages mentioned in `content`, lowercased, without
+/// duplicates, in the order they first appear.
+pub fn mentioned_pages(content: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for caps in mention_pattern().captures_iter(content) {
+        let id = caps[1].to_lowercase();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// `content` with each mention shown as its label, as readers see it.
+pub fn render_mentions(content: &str) -> String {
+    mention_pattern()
+        .replace_all(content, |caps: &Captures| {
+            caps.get(2).map_or("Untitled", |label| label.as_str()).to_string()
+        })
+        .into_owned()
+}
+
+/// Index rows for one block. Mentions of the block's own page are left out.
+fn link_rows(block_id: i64, page_id: &str, content: &str) -> Vec<Value> {
+    mentioned_pages(content)
+        .into_iter()
+        .filter(|target| !target.eq_ignore_ascii_case(page_id))
+        .map(|target| {
+            json!({
+                "block_id": block_id,
+                "source_page_id": page_id,
+                "target_page_id": target,
+            })
+        })
+        .collect()
+}
+
+/// Replaces a block's links with the mentions in its current content.
+pub async fn reindex_block_links(
+    supabase_client: &RestClient,
+    block_id: i64,
+    page_id: &str,
+    content: &str,
+) -> Result<(), String> {
+    supabase_client
+        .delete("page_links", &format!("block_id=eq.{}", block_id))
+        .await?;
+    let rows = link_rows(block_id, page_id, content);
+    if !rows.is_empty() {
+        supabase_client.insert("page_links", json!(rows)).await?;
+    }
+    Ok(())
+}
+
+/// Indexes the links in newly inserted block rows (`id`, `page_id` and
+/// `content`), e.g. after copying or restoring a page.
+pub async fn index_links(supabase_client: &RestClient, blocks: &[Value]) -> Result<(), String> {
+    let rows: Vec<Value> = blocks
+        .iter()
+        .filter_map(|block| {
+            let id = block_id(&block["id"])?;
+            let page_id = block["page_id"].as_str()?;
+            Some(link_rows(id, page_id, block["content"].as_str().unwrap_or_default()))
+        })
+        .flatten()
+        .collect();
+    if !rows.is_empty() {
+        supabase_client.insert("page_links", json!(rows)).await?;
+    }
+    Ok(())
+}
+
+/// `content` as plain text around its first mention of `target`, with that
+/// mention shown under the page's current title and other mentions under
+/// their labels.
+fn snippet(content: &str, target: &str, title: &str) -> String {
+    let render = |text: &str| {
+        mention_pattern()
+            .replace_all(text, |caps: &Captures| {
+                if caps[1].eq_ignore_ascii_case(target) {
+                    format!("@{}", title)
+                } else {
+                    format!("@{}", caps.get(2).map_or("Untitled", |label| label.as_str()))
+                }
+            })
+            .into_owned()
+    };
+
+    let Some(mention) = mention_pattern()
+        .captures_iter(content)
+        .find(|caps| caps[1].eq_ignore_ascii_case(target))
+        .and_then(|caps| caps.get(0))
+    else {
+        return render(content);
+    };
+
+    let before: Vec<char> = render(&content[..mention.start()]).chars().collect();
+    let after: Vec<char> = render(&content[mention.end()..]).chars().collect();
+    let mut snippet = String::new();
+    if before.len() > SNIPPET_CONTEXT {
+        snippet.push('…');
+    }
+    snippet.extend(&before[before.len().saturating_sub(SNIPPET_CONTEXT)..]);
+    snippet.push_str(&format!("@{}", title));
+    snippet.extend(after.iter().take(SNIPPET_CONTEXT));
+    if after.len() > SNIPPET_CONTEXT {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[derive(Debug, Serialize)]
+pub struct BacklinkBlock {
+    pub block_id: String,
+    pub snippet: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Backlink {
+    pub page_id: String,
+    pub title: String,
+    pub icon: Option<String>,
+    pub blocks: Vec<BacklinkBlock>,
+}
+
+/// The pages that mention `page_id`, each with the referencing blocks and a
+/// snippet of text around the mention. Only pages the user can read are
+/// included; the most recently edited come first.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn fetch_backlinks(page_id: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if page_role(&supabase_client, &page_id).await?.is_none() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    }
+    let page = supabase_client
+        .select("pages", &format!("select=title&id=eq.{}", encode(&page_id)))
+        .await?;
+    let title = page
+        .first()
+        .and_then(|row| row["title"].as_str())
+        .filter(|title| !title.is_empty())
+        .unwrap_or("Untitled")
+        .to_string();
+
+    let rows = supabase_client
+        .select(
+            "page_links",
+            &format!(
+                "select=block_id,source_page_id,blocks!inner(content,order,updated_at),pages!inner(title,icon,deleted_at)&target_page_id=eq.{}&pages.deleted_at=is.null",
+                encode(&page_id)
+            ),
+        )
+        .await?;
+
+    let mut backlinks: Vec<Backlink> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut ordered: Vec<&Value> = rows.iter().collect();
+    ordered.sort_by_key(|row| row["blocks"]["order"].as_i64().unwrap_or_default());
+    for row in ordered {
+        let source = row["source_page_id"].as_str().unwrap_or_default().to_string();
+        let at = *index.entry(source.clone()).or_insert_with(|| {
+            backlinks.push(Backlink {
+                page_id: source.clone(),
+                title: row["pages"]["title"].as_str().unwrap_or_default().to_string(),
+                icon: row["pages"]["icon"].as_str().map(|icon| icon.to_string()),
+                blocks: Vec::new(),
+            });
+            backlinks.len() - 1
+        });
+        backlinks[at].blocks.push(BacklinkBlock {
+            block_id: block_id(&row["block_id"]).unwrap_or_default().to_string(),
+            snippet: snippet(
+                row["blocks"]["content"].as_str().unwrap_or_default(),
+                &page_id,
+                &title,
+            ),
+            updated_at: row["blocks"]["updated_at"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    // RFC 3339 timestamps from the database sort correctly as strings
+    let latest = |backlink: &Backlink| {
+        backlink
+            .blocks
+            .iter()
+            .map(|block| block.updated_at.clone())
+            .max()
+            .unwrap_or_default()
+    };
+    backlinks.sort_by_key(|backlink| std::cmp::Reverse(latest(backlink)));
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(backlinks)),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "0b5ad0e4-9a43-4d2c-8f0e-5c7b1e2d3f40";
+    const B: &str = "7c1f2a3b-4d5e-4f60-8a7b-9c0d1e2f3a4b";
+
+    #[test]
+    fn mentions_are_lowercased_and_deduplicated_in_order() {
+        let content = format!(
+            "See [[page:{}|Plans]], [[page:{}]] and [[page:{}]]; not [[page:nope]]",
+            B,
+            A.to_uppercase(),
+            B
+        );
+        assert_eq!(mentioned_pages(&content), vec![B.to_string(), A.to_string()]);
+    }
+
+    #[test]
+    fn mentions_render_as_their_label() {
+        let content = format!("[[page:{}|Plans]] and [[page:{}]]", A, B);
+        assert_eq!(render_mentions(&content), "Plans and Untitled");
+    }
+
+    #[test]
+    fn own_page_mentions_are_not_indexed() {
+        let content = format!("[[page:{}]] [[page:{}]]", A, B);
+        let rows = link_rows(7, A, &content);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["target_page_id"], B);
+    }
+
+    #[test]
+    fn snippet_shows_the_current_title_with_context() {
+        let content = format!("Before [[page:{}|Old name]] after [[page:{}|Other]]", A, B);
+        assert_eq!(snippet(&content, A, "New name"), "Before @New name after @Other");
+    }
+
+    #[test]
+    fn long_snippets_are_cut_around_the_mention() {
+        let content = format!("{}[[page:{}]]{}", "a".repeat(100), A, "b".repeat(100));
+        let expected = format!(
+            "…{}@Title{}…",
+            "a".repeat(SNIPPET_CONTEXT),
+            "b".repeat(SNIPPET_CONTEXT)
+        );
+        assert_eq!(snippet(&content, A, "Title"), expected);
+    }
+}
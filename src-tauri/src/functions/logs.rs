@@ -0,0 +1,44 @@
+use crate::functions::responses::{Response, StatusCode};
+use crate::logging::{log_dir, log_files, redact};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fs;
+
+const DEFAULT_LOG_LINES: usize = 500;
+/// Upper bound on `max_lines`, so a large request can't allocate without limit.
+const MAX_LOG_LINES: usize = 10_000;
+
+/// Returns the last `max_lines` (at most 10,000) log lines across the
+/// rotated log files, for attaching to bug reports. Lines are redacted again
+/// on the way out in case a file predates the current redaction rules.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn get_recent_logs(max_lines: Option<usize>) -> Result<Response<Value>, String> {
+    let max_lines = max_lines.unwrap_or(DEFAULT_LOG_LINES).min(MAX_LOG_LINES);
+    let mut lines = VecDeque::with_capacity(max_lines);
+
+    for file in log_files()? {
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!(file = %file.display(), error = %e, "skipping unreadable log file");
+                continue;
+            }
+        };
+        for line in text.lines() {
+            lines.push_back(redact(line));
+            if lines.len() > max_lines {
+                lines.pop_front();
+            }
+        }
+    }
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "log_dir": log_dir().map(|dir| dir.display().to_string()),
+            "lines": lines,
+        })),
+        error: None,
+    })
+}
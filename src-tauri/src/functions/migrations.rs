@@ -0,0 +1,54 @@
+use crate::functions::responses::{Response, StatusCode};
+use crate::supabase::migrations::{applied_versions, latest_version, MIGRATIONS};
+use dotenv::dotenv;
+use serde_json::{json, Value};
+use std::env;
+
+/// Compares the connected database against the migrations shipped with the
+/// app and reports the ones it is missing. Migrations are applied with
+/// `src-tauri/scripts/apply_migrations.sh`, not from the app, since that
+/// takes credentials end users shouldn't have.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn check_schema() -> Result<Response<Value>, String> {
+    dotenv().ok();
+    let supabase_url = env::var("VITE_SUPABASE_URL").map_err(|e| e.to_string())?;
+    let supabase_key = env::var("VITE_SUPABASE_API_KEY").map_err(|e| e.to_string())?;
+
+    let applied = match applied_versions(&supabase_url, &supabase_key).await? {
+        Some(applied) => applied,
+        None => {
+            return Ok(Response {
+                status: StatusCode::NotFound,
+                data: Some(json!({
+                    "current_version": 0,
+                    "latest_version": latest_version(),
+                    "bootstrap_required": true,
+                    "pending": MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>(),
+                })),
+                error: Some(
+                    "schema_migrations is missing. Run src-tauri/migrations/0001_initial_schema.sql \
+                     in the Supabase SQL editor once, then check again."
+                        .to_string(),
+                ),
+            });
+        }
+    };
+
+    let pending: Vec<Value> = MIGRATIONS
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .map(|m| json!({ "version": m.version, "name": m.name }))
+        .collect();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "current_version": applied.iter().copied().max().unwrap_or(0),
+            "latest_version": latest_version(),
+            "bootstrap_required": false,
+            "pending": pending,
+        })),
+        error: None,
+    })
+}
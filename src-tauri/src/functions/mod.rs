@@ -1,8 +1,29 @@
 pub mod auth;
 pub mod storage;
 pub mod supabase;
-pub mod queries;
 pub mod pages;
 pub mod responses;
 pub mod blocks;
-pub mod embeddings;
\ No newline at end of file
+pub mod embeddings;
+pub mod migrations;
+pub mod session;
+pub mod logs;
+pub mod diagnostics;
+pub mod page_tree;
+pub mod trash;
+pub mod duplicate;
+pub mod templates;
+pub mod daily_notes;
+pub mod page_metadata;
+pub mod favorites;
+pub mod versions;
+pub mod sharing;
+pub mod share_links;
+pub mod workspaces;
+pub mod links;
+pub mod graph;
+pub mod databases;
+pub mod database_views;
+pub mod board;
+pub mod calendar;
+pub mod stats;
\ No newline at end of file
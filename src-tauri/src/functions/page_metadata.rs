@@ -0,0 +1,203 @@
+use crate::functions::pages::{fetch_workspace_pages, Page};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::{forbidden, page_role};
+use crate::functions::storage::{sign_images, stored_image_url, upload_file};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use urlencoding::encode;
+use uuid::Uuid;
+
+const MAX_TAG_LENGTH: usize = 64;
+const MAX_DESCRIPTION_LENGTH: usize = 2000;
+
+/// Fields to change on a page; `None` leaves a field as it is. An empty
+/// `icon` or `cover_url` removes it.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageMetadataUpdate {
+    pub icon: Option<String>,
+    pub cover_url: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Trims tags, drops a leading `#` and empty tags, and removes duplicates
+/// that differ only in case, keeping the first spelling.
+pub fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+    let mut result: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').trim().to_string();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(format!("Tags can be at most {} characters", MAX_TAG_LENGTH));
+        }
+        if !result.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            result.push(tag);
+        }
+    }
+    Ok(result)
+}
+
+fn bad_request(error: String) -> Response<Value> {
+    Response {
+        status: StatusCode::BadRequest,
+        data: None,
+        error: Some(error),
+    }
+}
+
+async fn can_edit(supabase_client: &RestClient, page_id: &str) -> Result<bool, String> {
+    Ok(page_role(supabase_client, page_id)
+        .await?
+        .is_some_and(|role| role.can_edit()))
+}
+
+/// Updates a page's icon, cover, tags or description, for its owner and
+/// editors.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn update_page_metadata(
+    page_id: String,
+    metadata: PageMetadataUpdate,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if !can_edit(&supabase_client, &page_id).await? {
+        return Ok(forbidden());
+    }
+
+    let mut body = json!({ "updated_at": chrono::Utc::now().to_rfc3339() });
+    if let Some(icon) = metadata.icon {
+        let icon = icon.trim().to_string();
+        body["icon"] = if icon.is_empty() { Value::Null } else { json!(icon) };
+    }
+    if let Some(cover_url) = metadata.cover_url {
+        let cover_url = stored_image_url(cover_url.trim());
+        body["cover_url"] = if cover_url.is_empty() { Value::Null } else { json!(cover_url) };
+    }
+    if let Some(tags) = metadata.tags {
+        match normalize_tags(tags) {
+            Ok(tags) => body["tags"] = json!(tags),
+            Err(e) => return Ok(bad_request(e)),
+        }
+    }
+    if let Some(description) = metadata.description {
+        if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            return Ok(bad_request(format!(
+                "Descriptions can be at most {} characters",
+                MAX_DESCRIPTION_LENGTH
+            )));
+        }
+        body["description"] = json!(description.trim());
+    }
+
+    let rows = supabase_client
+        .update(
+            "pages",
+            &format!("id=eq.{}&deleted_at=is.null", encode(&page_id)),
+            body,
+        )
+        .await?;
+
+    match rows.first() {
+        Some(row) => {
+            let mut page = Page::from_row(row);
+            sign_images(&supabase_client, page.cover_url.iter_mut().collect()).await;
+            Ok(Response {
+                status: StatusCode::Ok,
+                data: Some(json!(page)),
+                error: None,
+            })
+        }
+        None => Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        }),
+    }
+}
+
+/// Uploads `file_path` to the images bucket and makes it the page's cover.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn set_page_cover(
+    page_id: String,
+    file_path: String,
+    delete_after_upload: bool,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if !can_edit(&supabase_client, &page_id).await? {
+        return Ok(forbidden());
+    }
+    let path = format!("covers/{}", Uuid::new_v4());
+    let uploaded = upload_file("images", &path, &file_path, delete_after_upload).await?;
+    let cover_url = uploaded["url"].as_str().unwrap_or_default().to_string();
+
+    update_page_metadata(
+        page_id,
+        PageMetadataUpdate {
+            cover_url: Some(cover_url),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Every tag on the active workspace's pages with the number of pages using it, most
+/// used first.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_tags() -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let pages = fetch_workspace_pages(&supabase_client, &workspace_id).await?;
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for tag in pages.iter().flat_map(|page| page.tags.iter()) {
+        *counts.entry(tag.clone()).or_default() += 1;
+    }
+    let mut tags: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(tags)),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn tags_are_trimmed_and_deduplicated_ignoring_case() {
+        assert_eq!(
+            normalize_tags(tags(&["  #Work ", "work", "", "# ", "ideas", "WORK"])),
+            Ok(tags(&["Work", "ideas"]))
+        );
+    }
+
+    #[test]
+    fn long_tags_are_rejected() {
+        assert!(normalize_tags(tags(&[&"x".repeat(MAX_TAG_LENGTH)])).is_ok());
+        assert!(normalize_tags(tags(&[&"x".repeat(MAX_TAG_LENGTH + 1)])).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+use crate::functions::pages::{fetch_workspace_pages, Page};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// A workspace's pages arranged by `parent_page_id`. Pages whose parent does not
+/// exist (deleted, or not visible to the user) are treated as roots, and
+/// parent cycles are broken at their smallest id so every page appears once.
+pub struct PageForest {
+    pages: HashMap<String, Page>,
+    parents: HashMap<String, String>,
+    children: HashMap<String, Vec<String>>,
+    roots: Vec<String>,
+    orphaned: HashSet<String>,
+    cycles: Vec<Vec<String>>,
+}
+
+impl PageForest {
+    pub fn new(pages: Vec<Page>) -> Self {
+        let pages: HashMap<String, Page> = pages.into_iter().map(|p| (p.id.clone(), p)).collect();
+
+        let mut parents = HashMap::new();
+        let mut orphaned = HashSet::new();
+        for page in pages.values() {
+            if let Some(parent_id) = &page.parent_page_id {
+                if pages.contains_key(parent_id) {
+                    parents.insert(page.id.clone(), parent_id.clone());
+                } else {
+                    orphaned.insert(page.id.clone());
+                }
+            }
+        }
+
+        let cycles = break_cycles(&mut parents, &pages);
+
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        let mut roots = Vec::new();
+        for id in pages.keys() {
+            match parents.get(id) {
+                Some(parent_id) => children.entry(parent_id.clone()).or_default().push(id.clone()),
+                None => roots.push(id.clone()),
+            }
+        }
+
+        let sort_siblings = |ids: &mut Vec<String>| {
+            ids.sort_by(|a, b| {
+                let (a, b) = (&pages[a], &pages[b]);
+                a.position
+                    .cmp(&b.position)
+                    .then_with(|| a.created_at.cmp(&b.created_at))
+                    .then_with(|| a.id.cmp(&b.id))
+            })
+        };
+        sort_siblings(&mut roots);
+        children.values_mut().for_each(sort_siblings);
+
+        PageForest {
+            pages,
+            parents,
+            children,
+            roots,
+            orphaned,
+            cycles,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Page> {
+        self.pages.get(id)
+    }
+
+    pub fn roots(&self) -> &[String] {
+        &self.roots
+    }
+
+    pub fn children(&self, id: &str) -> &[String] {
+        self.children.get(id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Children of `parent_id`, or the roots when it is `None`.
+    pub fn siblings_under(&self, parent_id: Option<&str>) -> &[String] {
+        match parent_id {
+            Some(id) => self.children(id),
+            None => self.roots(),
+        }
+    }
+
+    pub fn parent(&self, id: &str) -> Option<&str> {
+        self.parents.get(id).map(String::as_str)
+    }
+
+    pub fn depth(&self, id: &str) -> u32 {
+        let mut depth = 0;
+        let mut current = id;
+        while let Some(parent_id) = self.parent(current) {
+            depth += 1;
+            current = parent_id;
+        }
+        depth
+    }
+
+    /// Whether `ancestor` is `id` itself or one of its ancestors.
+    pub fn is_ancestor_or_self(&self, ancestor: &str, id: &str) -> bool {
+        let mut current = Some(id);
+        while let Some(page_id) = current {
+            if page_id == ancestor {
+                return true;
+            }
+            current = self.parent(page_id);
+        }
+        false
+    }
+
+    /// Every page below `id`, parents before their children.
+    pub fn descendants(&self, id: &str) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut stack: Vec<&String> = self.children(id).iter().rev().collect();
+        while let Some(page_id) = stack.pop() {
+            result.push(page_id.clone());
+            stack.extend(self.children(page_id).iter().rev());
+        }
+        result
+    }
+
+    pub fn is_orphaned(&self, id: &str) -> bool {
+        self.orphaned.contains(id)
+    }
+
+    pub fn cycles(&self) -> &[Vec<String>] {
+        &self.cycles
+    }
+
+    fn in_cycle(&self, id: &str) -> bool {
+        self.cycles.iter().any(|cycle| cycle.iter().any(|c| c == id))
+    }
+
+    fn node(&self, id: &str, depth: u32, levels: Option<u32>) -> PageTreeNode {
+        let child_ids = self.children(id);
+        let load_children = levels.is_none_or(|levels| levels > 0);
+        PageTreeNode {
+            page: self.pages[id].clone(),
+            depth,
+            child_count: child_ids.len(),
+            children_loaded: load_children,
+            children: if load_children {
+                child_ids
+                    .iter()
+                    .map(|child| self.node(child, depth + 1, levels.map(|l| l - 1)))
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            orphaned: self.is_orphaned(id),
+            in_cycle: self.in_cycle(id),
+        }
+    }
+}
+
+/// Finds cycles in the parent links and removes the link out of the smallest
+/// id in each, turning that page into a root.
+fn break_cycles(parents: &mut HashMap<String, String>, pages: &HashMap<String, Page>) -> Vec<Vec<String>> {
+    let mut cycles = Vec::new();
+    let mut finished: HashSet<String> = HashSet::new();
+
+    let mut ids: Vec<&String> = pages.keys().collect();
+    ids.sort();
+    for start in ids {
+        let mut path: Vec<String> = Vec::new();
+        let mut on_path: HashSet<String> = HashSet::new();
+        let mut current = Some(start.clone());
+
+        while let Some(id) = current {
+            if finished.contains(&id) {
+                break;
+            }
+            if on_path.contains(&id) {
+                let position = path.iter().position(|p| *p == id).unwrap_or(0);
+                let cycle: Vec<String> = path[position..].to_vec();
+                if let Some(smallest) = cycle.iter().min() {
+                    parents.remove(smallest);
+                }
+                cycles.push(cycle);
+                break;
+            }
+            on_path.insert(id.clone());
+            path.push(id.clone());
+            current = parents.get(&id).cloned();
+        }
+        finished.extend(path);
+    }
+
+    if !cycles.is_empty() {
+        tracing::warn!(cycles = cycles.len(), "page hierarchy contains parent cycles");
+    }
+    cycles
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageTreeNode {
+    #[serde(flatten)]
+    pub page: Page,
+    /// Distance from the top of the hierarchy, regardless of the requested root.
+    pub depth: u32,
+    pub child_count: usize,
+    /// False when `max_depth` stopped the walk here; fetch this page as the
+    /// root to load its children.
+    pub children_loaded: bool,
+    pub children: Vec<PageTreeNode>,
+    /// The page's `parent_page_id` points at a page that no longer exists.
+    pub orphaned: bool,
+    /// The page was part of a parent cycle.
+    pub in_cycle: bool,
+}
+
+/// Returns the active workspace's pages as a tree. With `root_page_id` only
+/// that page's subtree is returned, and `max_depth` limits how many levels
+/// of children are loaded, so the sidebar can expand one subtree at a time.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(root_page_id = ?root_page_id, max_depth = ?max_depth))]
+pub async fn fetch_page_tree(
+    root_page_id: Option<String>,
+    max_depth: Option<u32>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let forest = PageForest::new(fetch_workspace_pages(&supabase_client, &workspace_id).await?);
+
+    let nodes: Vec<PageTreeNode> = match root_page_id.filter(|id| !id.is_empty()) {
+        Some(root_id) => {
+            if forest.get(&root_id).is_none() {
+                return Ok(Response {
+                    status: StatusCode::NotFound,
+                    data: None,
+                    error: Some("Page not found".to_string()),
+                });
+            }
+            vec![forest.node(&root_id, forest.depth(&root_id), max_depth)]
+        }
+        None => forest
+            .roots()
+            .iter()
+            .map(|id| forest.node(id, 0, max_depth))
+            .collect(),
+    };
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "nodes": nodes,
+            "cycles": forest.cycles(),
+        })),
+        error: None,
+    })
+}
+
+/// Checks that `page_id` may be placed under `new_parent_id`: the parent must
+/// be one of the pages in `forest`, and must not be the page or one of its
+/// descendants.
+pub fn validate_new_parent(
+    forest: &PageForest,
+    page_id: &str,
+    new_parent_id: Option<&str>,
+) -> Result<(), String> {
+    let Some(parent_id) = new_parent_id else {
+        return Ok(());
+    };
+    if forest.get(parent_id).is_none() {
+        return Err(format!("Parent page {} not found", parent_id));
+    }
+    if forest.is_ancestor_or_self(page_id, parent_id) {
+        return Err(format!(
+            "Cannot move page {} into itself or one of its subpages",
+            page_id
+        ));
+    }
+    Ok(())
+}
+
+/// The `set_page_positions` entries that renumber each (parent, siblings)
+/// list from 0, leaving out rows whose parent and position already match.
+/// Only pages that change parent carry `parent_page_id`, so renumbering a
+/// sibling never rewrites its parent.
+fn position_changes(forest: &PageForest, lists: &[(Option<String>, Vec<String>)]) -> Vec<Value> {
+    let mut changes: Vec<Value> = Vec::new();
+    for (parent_id, ids) in lists {
+        for (index, id) in ids.iter().enumerate() {
+            let Some(page) = forest.get(id) else { continue };
+            if page.parent_page_id != *parent_id {
+                changes.push(json!({ "id": id, "parent_page_id": parent_id, "position": index }));
+            } else if page.position != index as i32 {
+                changes.push(json!({ "id": id, "position": index }));
+            }
+        }
+    }
+    changes
+}
+
+/// Writes `changes` in one transaction and returns the updated pages.
+async fn apply_positions(supabase_client: &RestClient, changes: Vec<Value>) -> Result<Vec<Page>, String> {
+    if changes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let updated = supabase_client
+        .rpc("set_page_positions", json!({ "p_pages": changes }))
+        .await?;
+    Ok(updated
+        .as_array()
+        .map(|rows| rows.iter().map(Page::from_row).collect())
+        .unwrap_or_default())
+}
+
+/// Moves `page_ids` (in that order) under `new_parent_id`, inserted at
+/// `position` among the new siblings or appended when it is `None`. Both the
+/// old and new sibling lists are renumbered, and every change is written in
+/// one `set_page_positions` call.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(pages = page_ids.len(), new_parent_id = ?new_parent_id))]
+pub async fn move_page(
+    page_ids: Vec<String>,
+    new_parent_id: Option<String>,
+    position: Option<usize>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let forest = PageForest::new(fetch_workspace_pages(&supabase_client, &workspace_id).await?);
+
+    let new_parent_id = new_parent_id.filter(|id| !id.is_empty());
+    let mut moving: Vec<String> = Vec::new();
+    for page_id in page_ids {
+        if !moving.contains(&page_id) {
+            moving.push(page_id);
+        }
+    }
+    if moving.is_empty() {
+        return Ok(Response {
+            status: StatusCode::BadRequest,
+            data: None,
+            error: Some("No pages to move".to_string()),
+        });
+    }
+
+    for page_id in &moving {
+        if forest.get(page_id).is_none() {
+            return Ok(Response {
+                status: StatusCode::NotFound,
+                data: None,
+                error: Some(format!("Page {} not found", page_id)),
+            });
+        }
+        if let Err(e) = validate_new_parent(&forest, page_id, new_parent_id.as_deref()) {
+            return Ok(Response {
+                status: StatusCode::BadRequest,
+                data: None,
+                error: Some(e),
+            });
+        }
+    }
+
+    // Final (parent, sibling list) for every list the move touches
+    let mut lists: Vec<(Option<String>, Vec<String>)> = Vec::new();
+
+    let mut target: Vec<String> = forest
+        .siblings_under(new_parent_id.as_deref())
+        .iter()
+        .filter(|id| !moving.contains(id))
+        .cloned()
+        .collect();
+    let insert_at = position.unwrap_or(target.len()).min(target.len());
+    target.splice(insert_at..insert_at, moving.iter().cloned());
+    lists.push((new_parent_id.clone(), target));
+
+    for page_id in &moving {
+        let old_parent = forest.parent(page_id).map(str::to_string);
+        if old_parent == new_parent_id || lists.iter().any(|(parent, _)| *parent == old_parent) {
+            continue;
+        }
+        let remaining: Vec<String> = forest
+            .siblings_under(old_parent.as_deref())
+            .iter()
+            .filter(|id| !moving.contains(id))
+            .cloned()
+            .collect();
+        lists.push((old_parent, remaining));
+    }
+
+    let updated = apply_positions(&supabase_client, position_changes(&forest, &lists)).await?;
+
+    tracing::info!(moved = moving.len(), updated = updated.len(), "pages moved");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(updated)),
+        error: None,
+    })
+}
+
+/// Sets the order of `parent_page_id`'s children (the top level when
+/// `None`). `page_ids` come first in the given order; siblings left out keep
+/// their relative order after them.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(parent_page_id = ?parent_page_id, pages = page_ids.len()))]
+pub async fn reorder_pages(
+    parent_page_id: Option<String>,
+    page_ids: Vec<String>,
+) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let forest = PageForest::new(fetch_workspace_pages(&supabase_client, &workspace_id).await?);
+
+    let parent_page_id = parent_page_id.filter(|id| !id.is_empty());
+    if let Some(parent_id) = &parent_page_id {
+        if forest.get(parent_id).is_none() {
+            return Ok(Response {
+                status: StatusCode::NotFound,
+                data: None,
+                error: Some(format!("Parent page {} not found", parent_id)),
+            });
+        }
+    }
+
+    let siblings = forest.siblings_under(parent_page_id.as_deref());
+    let mut ordered: Vec<String> = Vec::with_capacity(siblings.len());
+    for page_id in page_ids {
+        if !siblings.contains(&page_id) {
+            return Ok(Response {
+                status: StatusCode::BadRequest,
+                data: None,
+                error: Some(format!("Page {} is not a child of the given parent", page_id)),
+            });
+        }
+        if !ordered.contains(&page_id) {
+            ordered.push(page_id);
+        }
+    }
+    for page_id in siblings {
+        if !ordered.contains(page_id) {
+            ordered.push(page_id.clone());
+        }
+    }
+
+    let updated = apply_positions(
+        &supabase_client,
+        position_changes(&forest, &[(parent_page_id, ordered)]),
+    )
+    .await?;
+
+    tracing::info!(updated = updated.len(), "pages reordered");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(updated)),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(id: &str, parent: Option<&str>, position: i32) -> Page {
+        Page::from_row(&json!({ "id": id, "parent_page_id": parent, "position": position }))
+    }
+
+    #[test]
+    fn missing_parents_become_orphaned_roots() {
+        let forest = PageForest::new(vec![page("a", None, 1), page("b", Some("gone"), 0)]);
+        assert_eq!(forest.roots(), ["b", "a"]);
+        assert!(forest.is_orphaned("b"));
+        assert!(!forest.is_orphaned("a"));
+    }
+
+    #[test]
+    fn cycles_are_broken_at_the_smallest_id() {
+        let forest = PageForest::new(vec![
+            page("c", Some("b"), 0),
+            page("b", Some("a"), 0),
+            page("a", Some("c"), 0),
+            page("d", Some("a"), 0),
+        ]);
+        assert_eq!(forest.cycles().len(), 1);
+        let mut cycle = forest.cycles()[0].clone();
+        cycle.sort();
+        assert_eq!(cycle, ["a", "b", "c"]);
+        assert_eq!(forest.roots(), ["a"]);
+        assert_eq!(forest.parent("a"), None);
+        assert_eq!(forest.depth("c"), 2);
+        assert!(forest.in_cycle("b"));
+        assert!(!forest.in_cycle("d"));
+    }
+
+    #[test]
+    fn descendants_list_parents_before_children() {
+        let forest = PageForest::new(vec![
+            page("root", None, 0),
+            page("second", Some("root"), 1),
+            page("first", Some("root"), 0),
+            page("nested", Some("first"), 0),
+        ]);
+        assert_eq!(forest.descendants("root"), ["first", "nested", "second"]);
+    }
+
+    #[test]
+    fn pages_cannot_move_into_their_own_subtree() {
+        let forest = PageForest::new(vec![page("a", None, 0), page("b", Some("a"), 0)]);
+        assert!(validate_new_parent(&forest, "a", Some("b")).is_err());
+        assert!(validate_new_parent(&forest, "a", Some("a")).is_err());
+        assert!(validate_new_parent(&forest, "a", Some("missing")).is_err());
+        assert!(validate_new_parent(&forest, "b", None).is_ok());
+    }
+
+    #[test]
+    fn position_changes_set_the_parent_only_on_moved_pages() {
+        let forest = PageForest::new(vec![
+            page("a", None, 0),
+            page("b", None, 1),
+            page("c", Some("a"), 0),
+        ]);
+        let lists = vec![
+            (Some("a".to_string()), vec!["b".to_string(), "c".to_string()]),
+            (None, vec!["a".to_string()]),
+        ];
+        assert_eq!(
+            position_changes(&forest, &lists),
+            vec![
+                json!({ "id": "b", "parent_page_id": "a", "position": 0 }),
+                json!({ "id": "c", "position": 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn unchanged_pages_are_left_out() {
+        let forest = PageForest::new(vec![page("a", None, 0), page("b", None, 1)]);
+        let lists = vec![(None, vec!["a".to_string(), "b".to_string()])];
+        assert!(position_changes(&forest, &lists).is_empty());
+    }
+}
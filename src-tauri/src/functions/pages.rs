@@ -1,14 +1,19 @@
 use crate::functions::responses::{Response, StatusCode}; // Import Response and StatusCode
-use crate::functions::supabase::initialize_supabase_client;
-use dotenv::dotenv;
-use reqwest::{Client};
+use crate::functions::page_tree::{validate_new_parent, PageForest};
+use crate::functions::sharing::{forbidden, page_role};
+use crate::functions::storage::sign_images;
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::versions::{snapshot_before_change, SnapshotReason};
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value}; // Import Value and json macro for JSON handling
-use std::env;
-use crate::supabase::update::update;
-use chrono::DateTime;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate};
+use urlencoding::encode;
 
-#[derive(Debug, Deserialize, Serialize)] // Add Serialize here
+#[derive(Debug, Clone, Deserialize, Serialize)] // Add Serialize here
 pub struct Page {
     pub id: String,
     pub created_at: String,
@@ -16,28 +21,24 @@ pub struct Page {
     pub user_id: String,
     pub title: String,
     pub parent_page_id: Option<String>, // Assuming this can be null
+    pub position: i32, // Order among siblings
+    pub deleted_at: Option<String>, // Set while the page is in the trash
+    pub is_template: bool,
+    pub journal_date: Option<String>, // The day a daily note belongs to
+    pub icon: Option<String>,
+    pub cover_url: Option<String>,
+    pub tags: Vec<String>,
+    pub description: String,
+    pub workspace_id: Option<String>,
+    pub is_database: bool,
+    pub properties: Value, // Database property values, keyed by property id
 }
 
-#[tauri::command]
-pub async fn fetch_pages(user_id: String) -> Result<Response<serde_json::Value>, String> {
-    let supabase_client = initialize_supabase_client().await;
-    let data = supabase_client
-        .select("pages")
-        .eq("user_id", &user_id)
-        .execute()
-        .await
-        .map_err(|e| e.to_string())?;
+pub const PAGE_COLUMNS: &str = "id,created_at,updated_at,user_id,title,parent_page_id,position,deleted_at,is_template,journal_date,icon,cover_url,tags,description,workspace_id,is_database,properties";
 
-    if data.is_empty() {
-        return Ok(Response {
-            status: StatusCode::Ok,
-            data: None,
-            error: None,
-        });
-    }
-    let mut pages: Vec<Page> = data
-        .iter()
-        .map(|page| Page {
+impl Page {
+    pub fn from_row(page: &Value) -> Page {
+        Page {
             id: page
                 .get("id")
                 .unwrap_or(&Value::Null)
@@ -63,9 +64,9 @@ pub async fn fetch_pages(user_id: String) -> Result<Response<serde_json::Value>,
                     if v.is_string() {
                         v.as_str().map(|s| s.to_string())
                     } else {
-                        v.as_f64()
+                        v.as_i64()
                             .map(|n| n.to_string())
-                            .or_else(|| v.as_i64().map(|n| n.to_string()))
+                            .or_else(|| v.as_f64().map(|n| n.to_string()))
                     }
                 })
                 .unwrap_or_default(),
@@ -77,61 +78,403 @@ pub async fn fetch_pages(user_id: String) -> Result<Response<serde_json::Value>,
                 .to_string(),
             parent_page_id: page
                 .get("parent_page_id")
-                .map(|v| v.as_str().unwrap_or("").to_string()),
-        })
-        .collect();
-        
-    pages.sort_by(|a, b| {
-        // Parse timestamp, adding UTC timezone if missing
-        let parse_date = |ts: &str| {
-            if ts.ends_with('Z') {
-                DateTime::parse_from_rfc3339(ts)
-            } else {
-                // If no timezone, assume UTC and append Z
-                DateTime::parse_from_rfc3339(&format!("{}Z", ts))
-                    .or_else(|_| DateTime::parse_from_str(ts, "%Y-%m-%dT%H:%M:%S%.f"))
+                .and_then(|v| v.as_str())
+                .filter(|id| !id.is_empty())
+                .map(|id| id.to_string()),
+            position: page
+                .get("position")
+                .and_then(|v| v.as_i64())
+                .unwrap_or_default() as i32,
+            deleted_at: page
+                .get("deleted_at")
+                .and_then(|v| v.as_str())
+                .map(|ts| ts.to_string()),
+            is_template: page
+                .get("is_template")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            journal_date: page
+                .get("journal_date")
+                .and_then(|v| v.as_str())
+                .map(|date| date.to_string()),
+            icon: page
+                .get("icon")
+                .and_then(|v| v.as_str())
+                .filter(|icon| !icon.is_empty())
+                .map(|icon| icon.to_string()),
+            cover_url: page
+                .get("cover_url")
+                .and_then(|v| v.as_str())
+                .filter(|url| !url.is_empty())
+                .map(|url| url.to_string()),
+            tags: page
+                .get("tags")
+                .and_then(|v| v.as_array())
+                .map(|tags| {
+                    tags.iter()
+                        .filter_map(|tag| tag.as_str().map(|tag| tag.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            description: page
+                .get("description")
+                .unwrap_or(&Value::Null)
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            workspace_id: page
+                .get("workspace_id")
+                .and_then(|v| v.as_str())
+                .map(|id| id.to_string()),
+            is_database: page
+                .get("is_database")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            properties: page
+                .get("properties")
+                .filter(|v| v.is_object())
+                .cloned()
+                .unwrap_or_else(|| Value::Object(Default::default())),
+        }
+    }
+}
+
+/// The position after the last of `parent_page_id`'s children, so new pages
+/// are added at the end instead of jumping to the top. Top-level pages are
+/// counted in `workspace_id`; subpages always share their parent's workspace.
+pub async fn next_position(
+    supabase_client: &RestClient,
+    workspace_id: &str,
+    parent_page_id: Option<&str>,
+) -> Result<i32, String> {
+    let siblings_filter = match parent_page_id {
+        Some(id) => format!("parent_page_id=eq.{}", encode(id)),
+        None => format!("workspace_id=eq.{}&parent_page_id=is.null", encode(workspace_id)),
+    };
+    let data = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select=position&{}&deleted_at=is.null&order=position.desc&limit=1",
+                siblings_filter
+            ),
+        )
+        .await?;
+    Ok(data
+        .first()
+        .and_then(|row| row["position"].as_i64())
+        .map_or(0, |last| last as i32 + 1))
+}
+
+/// A PostgREST `in.(...)` filter matching any of `ids`.
+pub fn ids_filter<S: AsRef<str>>(ids: &[S]) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| encode(id.as_ref()).into_owned()).collect();
+    format!("in.({})", ids.join(","))
+}
+
+/// Whether the user can read `page_id`, as its owner, a workspace member or a
+/// collaborator, and it is not in the trash.
+pub async fn live_page_exists(supabase_client: &RestClient, page_id: &str) -> Result<bool, String> {
+    if page_role(supabase_client, page_id).await?.is_none() {
+        return Ok(false);
+    }
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!("select=id&id=eq.{}&deleted_at=is.null", encode(page_id)),
+        )
+        .await?;
+    Ok(!rows.is_empty())
+}
+
+/// Every live page in a workspace the user can read, whoever created it.
+pub async fn fetch_workspace_pages(
+    supabase_client: &RestClient,
+    workspace_id: &str,
+) -> Result<Vec<Page>, String> {
+    let data = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select={}&workspace_id=eq.{}&deleted_at=is.null",
+                PAGE_COLUMNS,
+                encode(workspace_id)
+            ),
+        )
+        .await?;
+    Ok(data.iter().map(Page::from_row).collect())
+}
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+const MAX_PAGE_LIMIT: usize = 500;
+
+/// How `fetch_pages` sorts its result. Every order breaks ties by id so
+/// cursors are stable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageOrder {
+    /// By position among siblings; combine with a parent filter for the
+    /// sidebar order of one level.
+    Manual,
+    /// Alphabetical, ignoring case.
+    Title,
+    /// Newest first.
+    Created,
+    /// Most recently edited first.
+    #[default]
+    Updated,
+}
+
+impl PageOrder {
+    /// The column the order sorts on and whether it sorts descending.
+    fn key(self) -> (&'static str, bool) {
+        match self {
+            PageOrder::Manual => ("position", false),
+            PageOrder::Title => ("title_key", false),
+            PageOrder::Created => ("created_at", true),
+            PageOrder::Updated => ("updated_at", true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateField {
+    Created,
+    #[default]
+    Updated,
+}
+
+/// Optional filters for `fetch_pages`; all given filters must match.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageFilters {
+    pub parent_page_id: Option<String>,
+    /// Only pages without a parent. Ignored when `parent_page_id` is set.
+    pub top_level: Option<bool>,
+    pub tag: Option<String>,
+    /// Case-insensitive title prefix.
+    pub title_prefix: Option<String>,
+    /// Which timestamp `from`/`to` apply to.
+    pub date_field: Option<DateField>,
+    /// Inclusive lower bound, as RFC 3339 or `YYYY-MM-DD`.
+    pub from: Option<String>,
+    /// Inclusive upper bound, as RFC 3339 or `YYYY-MM-DD` (the whole day).
+    pub to: Option<String>,
+}
+
+/// Where the previous page of results stopped: the last row's sort key
+/// and id, for the order it was fetched with.
+#[derive(Debug, Deserialize, Serialize)]
+struct PageCursor {
+    order: PageOrder,
+    key: Value,
+    id: String,
+}
+
+impl PageCursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Option<PageCursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+/// Quotes a value for use inside a PostgREST `or=(...)` expression.
+fn quote_filter_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parses a date range bound into a timestamp. A bare date means the start
+/// of that day (UTC), or with `end_of_day` the start of the next one.
+fn parse_bound(bound: &str, end_of_day: bool) -> Option<(String, bool)> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(bound) {
+        return Some((ts.to_rfc3339(), false));
+    }
+    let date = NaiveDate::parse_from_str(bound, "%Y-%m-%d").ok()?;
+    let date = if end_of_day { date.succ_opt()? } else { date };
+    Some((date.and_hms_opt(0, 0, 0)?.and_utc().to_rfc3339(), end_of_day))
+}
+
+/// Builds the PostgREST filters for `filters`, or the message for an
+/// invalid one.
+fn filter_query(filters: &PageFilters) -> Result<String, String> {
+    let mut query = String::new();
+
+    match filters.parent_page_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(parent_id) => query.push_str(&format!("&parent_page_id=eq.{}", encode(parent_id))),
+        None if filters.top_level.unwrap_or(false) => query.push_str("&parent_page_id=is.null"),
+        None => {}
+    }
+
+    if let Some(tag) = filters.tag.as_deref().map(str::trim).filter(|tag| !tag.is_empty()) {
+        let array = format!("{{{}}}", quote_filter_value(tag.trim_start_matches('#')));
+        query.push_str(&format!("&tags=cs.{}", encode(&array)));
+    }
+
+    if let Some(prefix) = filters.title_prefix.as_deref().filter(|prefix| !prefix.is_empty()) {
+        let pattern = prefix
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query.push_str(&format!("&title_key=like.{}*", encode(&pattern)));
+    }
+
+    let column = match filters.date_field.unwrap_or_default() {
+        DateField::Created => "created_at",
+        DateField::Updated => "updated_at",
+    };
+    if let Some(from) = filters.from.as_deref().filter(|from| !from.is_empty()) {
+        let (from, _) = parse_bound(from, false).ok_or(format!("Invalid from date {:?}", from))?;
+        query.push_str(&format!("&{}=gte.{}", column, encode(&from)));
+    }
+    if let Some(to) = filters.to.as_deref().filter(|to| !to.is_empty()) {
+        let (to, exclusive) = parse_bound(to, true).ok_or(format!("Invalid to date {:?}", to))?;
+        let op = if exclusive { "lt" } else { "lte" };
+        query.push_str(&format!("&{}={}.{}", column, op, encode(&to)));
+    }
+
+    Ok(query)
+}
+
+/// Keyset condition selecting the rows after `cursor` in `order`.
+fn cursor_query(order: PageOrder, cursor: &PageCursor) -> String {
+    let (column, descending) = order.key();
+    let op = if descending { "lt" } else { "gt" };
+    let key = match &cursor.key {
+        Value::String(key) => quote_filter_value(key),
+        other => other.to_string(),
+    };
+    let id = quote_filter_value(&cursor.id);
+    let condition = format!(
+        "({column}.{op}.{key},and({column}.eq.{key},id.{op}.{id}))",
+        column = column,
+        op = op,
+        key = key,
+        id = id
+    );
+    format!("&or={}", encode(&condition))
+}
+
+/// Lists the pages (not in the trash) of the active workspace, or of
+/// `workspace_id`, one page of results at a time, sorted and filtered in the
+/// database. Pass the returned `next_cursor` back to get the following
+/// results; it is `null` at the end.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(order = ?order, limit = ?limit))]
+pub async fn fetch_pages(
+    order: Option<PageOrder>,
+    filters: Option<PageFilters>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<Response<serde_json::Value>, String> {
+    let order = order.unwrap_or_default();
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT);
+
+    let filters = match filter_query(&filters.unwrap_or_default()) {
+        Ok(query) => query,
+        Err(e) => {
+            return Ok(Response {
+                status: StatusCode::BadRequest,
+                data: None,
+                error: Some(e),
+            })
+        }
+    };
+
+    let after = match cursor.as_deref().filter(|cursor| !cursor.is_empty()) {
+        Some(cursor) => match PageCursor::decode(cursor).filter(|c| c.order == order) {
+            Some(cursor) => cursor_query(order, &cursor),
+            None => {
+                return Ok(Response {
+                    status: StatusCode::BadRequest,
+                    data: None,
+                    error: Some("Invalid cursor for this order".to_string()),
+                })
             }
-        };
+        },
+        None => String::new(),
+    };
 
-        let a_date = parse_date(&a.updated_at).unwrap_or_else(|e| {
-            println!("Error parsing date for page {}: {}", a.id, e);
-            DateTime::parse_from_rfc3339("1970-01-01T00:00:00Z").unwrap()
-        });
-            
-        let b_date = parse_date(&b.updated_at).unwrap_or_else(|e| {
-            println!("Error parsing date for page {}: {}", b.id, e);
-            DateTime::parse_from_rfc3339("1970-01-01T00:00:00Z").unwrap()
-        });
-        b_date.cmp(&a_date)
-    });
+    let (column, descending) = order.key();
+    let direction = if descending { "desc" } else { "asc" };
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = match workspace_id.filter(|id| !id.is_empty()) {
+        Some(id) => id,
+        None => active_workspace_id(&supabase_client).await?,
+    };
+    let mut rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select={},title_key&workspace_id=eq.{}&deleted_at=is.null{}{}&order={}.{},id.{}&limit={}",
+                PAGE_COLUMNS,
+                encode(&workspace_id),
+                filters,
+                after,
+                column,
+                direction,
+                direction,
+                limit + 1
+            ),
+        )
+        .await?;
+
+    // One extra row tells us whether there is another page of results
+    let next_cursor = if rows.len() > limit {
+        rows.truncate(limit);
+        rows.last().map(|row| {
+            PageCursor {
+                order,
+                key: row[column].clone(),
+                id: row["id"].as_str().unwrap_or_default().to_string(),
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    let mut pages: Vec<Page> = rows.iter().map(Page::from_row).collect();
+    sign_images(
+        &supabase_client,
+        pages.iter_mut().filter_map(|page| page.cover_url.as_mut()).collect(),
+    )
+    .await;
+    tracing::debug!(pages = pages.len(), more = next_cursor.is_some(), "pages listed");
 
     Ok(Response {
         status: StatusCode::Ok,
-        data: Some(serde_json::json!(pages)),
+        data: Some(serde_json::json!({
+            "pages": pages,
+            "next_cursor": next_cursor,
+        })),
         error: None,
     })
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
 pub async fn fetch_page(page_id: String) -> Result<Response<serde_json::Value>, String> {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = initialize_user_client().await?;
+    // Pages that aren't the user's and aren't shared with them look missing
+    let Some(role) = page_role(&supabase_client, &page_id).await? else {
+        return Ok(Response {
+            status: StatusCode::Ok,
+            data: None,
+            error: None,
+        });
+    };
     let data = supabase_client
-        .select("pages")
-        .columns(
-            [
-                "id",
-                "created_at",
-                "updated_at",
-                "user_id",
-                "title",
-                "parent_page_id",
-            ]
-            .to_vec(),
+        .select(
+            "pages",
+            &format!("select={}&id=eq.{}", PAGE_COLUMNS, encode(&page_id)),
         )
-        .eq("id", &page_id)
-        .execute()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     if data.is_empty() {
         return Ok(Response {
@@ -141,61 +484,25 @@ pub async fn fetch_page(page_id: String) -> Result<Response<serde_json::Value>,
         });
     }
 
-    let page = Page {
-        id: data
-            .first()
-            .and_then(|d| d.get("id"))
-            .unwrap_or(&Value::Null)
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        created_at: data
-            .get(0)
-            .and_then(|d| d.get("created_at"))
-            .unwrap_or(&Value::Null)
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        updated_at: data
-            .get(0)
-            .and_then(|d| d.get("updated_at"))
-            .unwrap_or(&Value::Null)
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        user_id: data
-            .get(0)
-            .and_then(|d| d.get("user_id"))
-            .unwrap_or(&Value::Null)
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        title: data
-            .get(0)
-            .and_then(|d| d.get("title"))
-            .unwrap_or(&Value::Null)
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        parent_page_id: data
-            .get(0)
-            .and_then(|d| d.get("parent_page_id"))
-            .map(|v| v.as_str().unwrap_or("").to_string()),
-    };
+    let mut page = Page::from_row(&data[0]);
+    sign_images(&supabase_client, page.cover_url.iter_mut().collect()).await;
+    let mut data = serde_json::json!(page);
+    data["role"] = serde_json::json!(role);
 
     Ok(Response {
         status: StatusCode::Ok,
-        data: Some(serde_json::json!(page)),
+        data: Some(data),
         error: None,
     })
 }
 
 pub async fn page_exists(page_id: String) -> bool {
-    let supabase_client = initialize_supabase_client().await;
+    let supabase_client = match initialize_user_client().await {
+        Ok(client) => client,
+        Err(_) => return false,
+    };
     let data = match supabase_client
-        .select("pages")
-        .eq("id", &page_id)
-        .execute()
+        .select("pages", &format!("select=id&id=eq.{}", encode(&page_id)))
         .await
     {
         Ok(data) => data,
@@ -208,6 +515,7 @@ pub async fn page_exists(page_id: String) -> bool {
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
 pub async fn update_page(
     page_id: String,
     user_id: String,
@@ -230,9 +538,45 @@ pub async fn update_page(
         });
     }
 
-    dotenv().ok();
-    let supabase_url = env::var("VITE_SUPABASE_URL").map_err(|e| e.to_string())?;
-    let supabase_key = env::var("VITE_SUPABASE_API_KEY").map_err(|e| e.to_string())?;
+    let supabase_client = initialize_user_client().await?;
+    if !page_role(&supabase_client, &page_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+
+    // Reparenting through update_page gets the same checks as move_page,
+    // within the page's own workspace, and the user must be able to edit the
+    // new parent
+    if let Some(parent_id) = parent_page_id.as_deref().filter(|id| !id.is_empty()) {
+        let rows = supabase_client
+            .select("pages", &format!("select=workspace_id&id=eq.{}", encode(&page_id)))
+            .await?;
+        let Some(workspace_id) = rows.first().and_then(|row| row["workspace_id"].as_str()) else {
+            return Ok(Response {
+                status: StatusCode::NotFound,
+                data: None,
+                error: Some("Page not found".to_string()),
+            });
+        };
+        let forest = PageForest::new(fetch_workspace_pages(&supabase_client, workspace_id).await?);
+        if let Err(e) = validate_new_parent(&forest, &page_id, Some(parent_id)) {
+            return Ok(Response {
+                status: StatusCode::BadRequest,
+                data: None,
+                error: Some(e),
+            });
+        }
+        if !page_role(&supabase_client, parent_id)
+            .await?
+            .is_some_and(|role| role.can_edit())
+        {
+            return Ok(forbidden());
+        }
+    }
+
+    snapshot_before_change(&supabase_client, &page_id, SnapshotReason::Edit).await;
 
     let body = serde_json::json!({
         "id": page_id,
@@ -247,18 +591,18 @@ pub async fn update_page(
             serde_json::Value::Null
         },
         "updated_at": chrono::Utc::now().to_rfc3339(),
-        "user_id": user_id,
     });
-    
-    let result = update(
-        &supabase_url,
-        &supabase_key,
-        "pages",
-        "id",
-        &page_id,
-        body
-    ).await?;
-    
+
+    let rows = supabase_client
+        .update("pages", &format!("id=eq.{}", encode(&page_id)), body)
+        .await?;
+    if rows.is_empty() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    }
 
     Ok(Response {
         status: StatusCode::Ok,
@@ -273,20 +617,17 @@ pub async fn create_page(
     title: String,
     parent_page_id: Option<String>,
 ) -> Result<Response<serde_json::Value>, String> {
+    tracing::info!(%page_id, ?parent_page_id, "creating page");
 
-    dotenv().ok();
-    println!("Creating page with user_id: {}, page_id: {}, title: {}, parent_page_id: {:?}", user_id, page_id, title, parent_page_id);
-
-    let supabase_url =
-        env::var("VITE_SUPABASE_URL").map_err(|_| "Missing SUPABASE_URL in .env".to_string())?;
-    let supabase_key =
-        env::var("VITE_SUPABASE_API_KEY").map_err(|_| "Missing SUPABASE_KEY in .env".to_string())?;
-
-    let client = Client::new();
+    let supabase_client = initialize_user_client().await?;
+    let parent_page_id = parent_page_id.filter(|id| !id.is_empty());
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let position = next_position(&supabase_client, &workspace_id, parent_page_id.as_deref()).await?;
     let mut payload = serde_json::json!({
         "user_id": user_id,
         "id": page_id,
         "title": title,
+        "position": position,
         "created_at": chrono::Utc::now().to_rfc3339(),
         "updated_at": chrono::Utc::now().to_rfc3339(),
     });
@@ -295,26 +636,11 @@ pub async fn create_page(
         payload["parent_page_id"] = serde_json::Value::String(parent_id);
     }
 
-    let response = client
-        .post(&format!("{}/rest/v1/pages", supabase_url))
-        .header("apikey", &supabase_key)
-        .header("Authorization", format!("Bearer {}", supabase_key))
-        .header("Content-Type", "application/json")
-        .header("Prefer", "return=representation")
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let status = if response.status().is_success() {
-        StatusCode::Ok
-    } else {
-        StatusCode::BadRequest
-    };
+    let rows = supabase_client.insert("pages", payload).await?;
 
     Ok(Response {
-        status,
-        data: Some(response.json().await.map_err(|e| e.to_string())?),
+        status: StatusCode::Ok,
+        data: Some(serde_json::json!(rows)),
         error: None,
     })
 }
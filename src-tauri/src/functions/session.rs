@@ -0,0 +1,61 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::RwLock;
+
+/// The user signed in to this app instance. Set by `sign_in` or
+/// `restore_session`; `access_token` is the database-issued JWT that
+/// user-scoped Supabase requests are made with.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub user_id: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub access_token: String,
+}
+
+impl Session {
+    /// Builds a session from a `sign_in` or `refresh_session` result.
+    pub fn from_rpc(result: &Value) -> Option<Session> {
+        let user = &result["user"];
+        let field = |name: &str| user[name].as_str().unwrap_or_default().to_string();
+        Some(Session {
+            user_id: user["id"].as_str()?.to_string(),
+            email: field("email"),
+            first_name: field("first_name"),
+            last_name: field("last_name"),
+            access_token: result["access_token"].as_str()?.to_string(),
+        })
+    }
+
+    /// Whether the access token expires within `seconds`. The token isn't
+    /// verified here; the database does that on every request.
+    pub fn expires_within(&self, seconds: i64) -> bool {
+        token_expiry(&self.access_token).is_none_or(|exp| exp - Utc::now().timestamp() < seconds)
+    }
+}
+
+/// The `exp` claim of a JWT.
+fn token_expiry(token: &str) -> Option<i64> {
+    let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+    serde_json::from_slice::<Value>(&payload).ok()?["exp"].as_i64()
+}
+
+static SESSION: RwLock<Option<Session>> = RwLock::new(None);
+
+pub fn set_session(session: Session) {
+    *SESSION.write().unwrap_or_else(|e| e.into_inner()) = Some(session);
+}
+
+pub fn clear_session() {
+    *SESSION.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn current_session() -> Result<Session, String> {
+    SESSION
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| "Not signed in".to_string())
+}
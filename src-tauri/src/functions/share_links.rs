@@ -0,0 +1,204 @@
+use crate::functions::blocks::Block;
+use crate::functions::pages::Page;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::functions::sharing::{page_role, PageRole};
+use crate::functions::storage::sign_images;
+use crate::functions::supabase::{initialize_anon_client, initialize_user_client};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+use serde_json::{json, Value};
+use urlencoding::encode;
+
+const MAX_EXPIRY_DAYS: i64 = 365;
+
+#[derive(Debug, Serialize)]
+pub struct ShareLink {
+    pub id: String,
+    pub token: String,
+    pub page_id: String,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub disabled_at: Option<String>,
+    pub active: bool,
+}
+
+impl ShareLink {
+    fn from_row(row: &Value) -> ShareLink {
+        let expires_at = row["expires_at"].as_str().map(|s| s.to_string());
+        let disabled_at = row["disabled_at"].as_str().map(|s| s.to_string());
+        let expired = expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| at <= Utc::now());
+        ShareLink {
+            id: row["id"].as_str().unwrap_or_default().to_string(),
+            token: row["token"].as_str().unwrap_or_default().to_string(),
+            page_id: row["page_id"].as_str().unwrap_or_default().to_string(),
+            created_at: row["created_at"].as_str().unwrap_or_default().to_string(),
+            active: disabled_at.is_none() && !expired,
+            expires_at,
+            disabled_at,
+        }
+    }
+}
+
+/// 256 random bits, URL-safe.
+fn new_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn bad_request(error: &str) -> Response<Value> {
+    Response {
+        status: StatusCode::BadRequest,
+        data: None,
+        error: Some(error.to_string()),
+    }
+}
+
+/// Creates a read-only link to a page the user owns. Without
+/// `expires_in_days` the link works until it is disabled.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, expires_in_days = ?expires_in_days))]
+pub async fn create_share_link(
+    page_id: String,
+    expires_in_days: Option<i64>,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    if let Some(days) = expires_in_days {
+        if !(1..=MAX_EXPIRY_DAYS).contains(&days) {
+            return Ok(bad_request(&format!(
+                "Links can expire after 1 to {} days",
+                MAX_EXPIRY_DAYS
+            )));
+        }
+    }
+    if page_role(&supabase_client, &page_id).await? != Some(PageRole::Owner) {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    }
+
+    let expires_at = expires_in_days.map(|days| (Utc::now() + Duration::days(days)).to_rfc3339());
+    let rows = supabase_client
+        .insert(
+            "share_links",
+            json!({
+                "token": new_token(),
+                "page_id": page_id,
+                "user_id": session.user_id,
+                "expires_at": expires_at,
+            }),
+        )
+        .await?;
+    let link = rows
+        .first()
+        .map(ShareLink::from_row)
+        .ok_or("Share link was not created")?;
+    tracing::debug!(link_id = %link.id, "share link created");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(link)),
+        error: None,
+    })
+}
+
+/// The user's share links, newest first, optionally for one page.
+/// Disabled and expired links are included with `active: false`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = ?page_id))]
+pub async fn list_share_links(page_id: Option<String>) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let mut query = format!("user_id=eq.{}&order=created_at.desc", session.user_id);
+    if let Some(page_id) = page_id {
+        query.push_str(&format!("&page_id=eq.{}", encode(&page_id)));
+    }
+    let rows = supabase_client.select("share_links", &query).await?;
+    let links: Vec<ShareLink> = rows.iter().map(ShareLink::from_row).collect();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(links)),
+        error: None,
+    })
+}
+
+/// Stops a share link from working. Disabled links can't be re-enabled;
+/// create a new one instead.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(link_id = %link_id))]
+pub async fn disable_share_link(link_id: String) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .update(
+            "share_links",
+            &format!(
+                "id=eq.{}&user_id=eq.{}&disabled_at=is.null",
+                encode(&link_id),
+                session.user_id
+            ),
+            json!({ "disabled_at": Utc::now().to_rfc3339() }),
+        )
+        .await?;
+
+    match rows.first() {
+        Some(row) => Ok(Response {
+            status: StatusCode::Ok,
+            data: Some(json!(ShareLink::from_row(row))),
+            error: None,
+        }),
+        None => Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Share link not found".to_string()),
+        }),
+    }
+}
+
+/// The page and blocks behind a share link, for readers who may not be
+/// signed in. Images come back as signed URLs valid for an hour.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn fetch_shared_page(token: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_anon_client().await?;
+    let shared = supabase_client
+        .rpc_read("fetch_shared_page", json!({ "p_token": token.trim() }))
+        .await?;
+    if shared.is_null() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("This link is invalid or has expired".to_string()),
+        });
+    }
+
+    let mut page = Page::from_row(&shared["page"]);
+    let mut blocks: Vec<Block> = shared["blocks"]
+        .as_array()
+        .map(|rows| rows.iter().map(Block::from_row).collect())
+        .unwrap_or_default();
+    sign_images(
+        &supabase_client,
+        page.cover_url
+            .iter_mut()
+            .chain(blocks.iter_mut().map(|block| &mut block.content))
+            .collect(),
+    )
+    .await;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page": page, "blocks": blocks, "read_only": true })),
+        error: None,
+    })
+}
@@ -0,0 +1,233 @@
+use crate::functions::pages::{Page, PAGE_COLUMNS};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::functions::supabase::initialize_user_client;
+use crate::supabase::rest::RestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use urlencoding::encode;
+
+/// A role granted to another user. Commenters can read like viewers; the
+/// distinction is kept so comments can be allowed without granting edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareRole {
+    Viewer,
+    Commenter,
+    Editor,
+}
+
+/// The caller's effective access to a page, as resolved by `page_role`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageRole {
+    Owner,
+    Editor,
+    Commenter,
+    Viewer,
+}
+
+impl PageRole {
+    fn parse(role: &str) -> Option<Self> {
+        match role {
+            "owner" => Some(PageRole::Owner),
+            "editor" => Some(PageRole::Editor),
+            "commenter" => Some(PageRole::Commenter),
+            "viewer" => Some(PageRole::Viewer),
+            _ => None,
+        }
+    }
+
+    pub fn can_edit(self) -> bool {
+        matches!(self, PageRole::Owner | PageRole::Editor)
+    }
+}
+
+/// The caller's role on `page_id`, including grants inherited from parent
+/// pages. `None` when the page doesn't exist or isn't shared with them.
+pub async fn page_role(client: &RestClient, page_id: &str) -> Result<Option<PageRole>, String> {
+    let role = client
+        .rpc_read("page_role", json!({ "p_page_id": page_id }))
+        .await?;
+    Ok(role.as_str().and_then(PageRole::parse))
+}
+
+pub fn forbidden() -> Response<Value> {
+    Response {
+        status: StatusCode::Unauthorized,
+        data: None,
+        error: Some("You don't have permission to edit this page".to_string()),
+    }
+}
+
+fn page_not_found() -> Response<Value> {
+    Response {
+        status: StatusCode::NotFound,
+        data: None,
+        error: Some("Page not found".to_string()),
+    }
+}
+
+/// Shares a page the user owns with whoever signed up with `email`. Sharing
+/// again with the same person changes their role.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, role = ?role))]
+pub async fn share_page(
+    page_id: String,
+    email: String,
+    role: ShareRole,
+    include_subpages: Option<bool>,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+
+    let email = email.trim().to_string();
+    if email.is_empty() {
+        return Ok(Response {
+            status: StatusCode::BadRequest,
+            data: None,
+            error: Some("Email is required".to_string()),
+        });
+    }
+    if email.eq_ignore_ascii_case(&session.email) {
+        return Ok(Response {
+            status: StatusCode::BadRequest,
+            data: None,
+            error: Some("You can't share a page with yourself".to_string()),
+        });
+    }
+    match page_role(&supabase_client, &page_id).await? {
+        Some(PageRole::Owner) => {}
+        Some(_) => {
+            return Ok(Response {
+                status: StatusCode::Unauthorized,
+                data: None,
+                error: Some("Only the page owner can share it".to_string()),
+            })
+        }
+        None => return Ok(page_not_found()),
+    }
+
+    let collaborator = supabase_client
+        .rpc(
+            "share_page",
+            json!({
+                "p_page_id": page_id,
+                "p_email": email,
+                "p_role": role,
+                "p_include_subpages": include_subpages.unwrap_or(true),
+            }),
+        )
+        .await?;
+    if collaborator.is_null() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("No user with that email".to_string()),
+        });
+    }
+    tracing::debug!("page shared");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(collaborator),
+        error: None,
+    })
+}
+
+/// Everyone with access to a page: the owner first, then direct grants and
+/// grants inherited from parent pages (`page_id` names the page granted).
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn list_collaborators(page_id: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    if page_role(&supabase_client, &page_id).await?.is_none() {
+        return Ok(page_not_found());
+    }
+
+    let collaborators = supabase_client
+        .rpc_read("list_page_collaborators", json!({ "p_page_id": page_id }))
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(collaborators),
+        error: None,
+    })
+}
+
+/// Removes `user_id`'s grant on a page. Owners can revoke anyone; other
+/// users can only remove themselves. Inherited access has to be revoked on
+/// the page it was granted on.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id, user_id = %user_id))]
+pub async fn revoke_access(page_id: String, user_id: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let removed = supabase_client
+        .delete(
+            "page_shares",
+            &format!("page_id=eq.{}&user_id=eq.{}", encode(&page_id), encode(&user_id)),
+        )
+        .await?;
+
+    if removed.is_empty() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("No such collaborator".to_string()),
+        });
+    }
+    tracing::debug!("access revoked");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "user_id": user_id, "revoked": true })),
+        error: None,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedPage {
+    #[serde(flatten)]
+    pub page: Page,
+    pub role: String,
+    pub include_subpages: bool,
+    pub shared_at: String,
+}
+
+/// Pages other users have shared with the current user, most recent first.
+/// Subpages reached through an inherited grant aren't listed separately.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn fetch_shared_with_me() -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .select(
+            "page_shares",
+            &format!(
+                "select=role,include_subpages,created_at,pages!inner({})&user_id=eq.{}&pages.deleted_at=is.null&order=created_at.desc",
+                PAGE_COLUMNS, session.user_id
+            ),
+        )
+        .await?;
+
+    let pages: Vec<SharedPage> = rows
+        .iter()
+        .filter(|row| row["pages"].is_object())
+        .map(|row| SharedPage {
+            page: Page::from_row(&row["pages"]),
+            role: row["role"].as_str().unwrap_or_default().to_string(),
+            include_subpages: row["include_subpages"].as_bool().unwrap_or(true),
+            shared_at: row["created_at"].as_str().unwrap_or_default().to_string(),
+        })
+        .collect();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(pages)),
+        error: None,
+    })
+}
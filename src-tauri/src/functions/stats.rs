@@ -0,0 +1,364 @@
+use crate::functions::duplicate::block_id;
+use crate::functions::links::{mentioned_pages, render_mentions};
+use crate::functions::pages::{fetch_workspace_pages, Page, PAGE_COLUMNS};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::sharing::page_role;
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::OnceLock;
+use urlencoding::encode;
+
+const BLOCK_COLUMNS: &str = "id,type,content,order,parent_block_id,updated_at,page_id";
+
+/// Reading speed used for estimates, plus a fixed time per image.
+const WORDS_PER_MINUTE: usize = 230;
+const SECONDS_PER_IMAGE: usize = 12;
+/// Blocks per request in `account_stats`; PostgREST caps responses at its
+/// `max-rows` setting, so larger workspaces are read in pages.
+const BLOCKS_PAGE_SIZE: usize = 1000;
+
+fn url_pattern() -> &'static Regex {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    URL.get_or_init(|| Regex::new(r"https?://[^\s<>()\]]+").unwrap())
+}
+
+/// Totals for a page, or for every page of an account.
+#[derive(Debug, Default, Serialize)]
+pub struct Totals {
+    pub words: usize,
+    pub characters: usize,
+    pub blocks: usize,
+    pub blocks_by_type: BTreeMap<String, usize>,
+    pub images: usize,
+    /// Page mentions plus web links.
+    pub links: usize,
+    pub reading_time_minutes: usize,
+    /// How deeply blocks are nested; 0 when no block has a parent.
+    pub max_depth: usize,
+    pub last_edited_at: Option<String>,
+}
+
+impl Totals {
+    fn add(&mut self, other: &Totals) {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.blocks += other.blocks;
+        for (block_type, count) in &other.blocks_by_type {
+            *self.blocks_by_type.entry(block_type.clone()).or_default() += count;
+        }
+        self.images += other.images;
+        self.links += other.links;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        if other.last_edited_at > self.last_edited_at {
+            self.last_edited_at = other.last_edited_at.clone();
+        }
+        self.reading_time_minutes = reading_time_minutes(self.words, self.images);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockStats {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub parent_block_id: Option<i64>,
+    pub depth: usize,
+    pub words: usize,
+    pub characters: usize,
+    pub links: usize,
+    pub updated_at: String,
+    /// The latest edit to the block or any block nested under it.
+    pub last_edited_at: String,
+}
+
+/// Rounded up, so any text at all reads in at least a minute.
+fn reading_time_minutes(words: usize, images: usize) -> usize {
+    let seconds = (words * 60).div_ceil(WORDS_PER_MINUTE) + images * SECONDS_PER_IMAGE;
+    seconds.div_ceil(60)
+}
+
+struct Node<'a> {
+    stats: BlockStats,
+    order: i64,
+    row: &'a Value,
+}
+
+fn block_stats(row: &Value, id: i64) -> BlockStats {
+    let block_type = row["type"].as_str().unwrap_or_default().to_string();
+    let content = row["content"].as_str().unwrap_or_default();
+    // An image block's content is its URL, not text
+    let (words, characters, links) = if block_type == "image" {
+        (0, 0, 0)
+    } else {
+        let text = render_mentions(content);
+        (
+            text.split_whitespace().count(),
+            text.trim().chars().count(),
+            mentioned_pages(content).len() + url_pattern().find_iter(content).count(),
+        )
+    };
+    let updated_at = row["updated_at"].as_str().unwrap_or_default().to_string();
+    BlockStats {
+        id,
+        block_type,
+        parent_block_id: block_id(&row["parent_block_id"]),
+        depth: 0,
+        words,
+        characters,
+        links,
+        last_edited_at: updated_at.clone(),
+        updated_at,
+    }
+}
+
+/// Visits `index` and the blocks nested under it, depth first, and returns
+/// the latest edit in that subtree.
+fn visit(
+    index: usize,
+    depth: usize,
+    nodes: &mut [Node],
+    children: &HashMap<i64, Vec<usize>>,
+    visited: &mut HashSet<usize>,
+    ordered: &mut Vec<usize>,
+) -> String {
+    visited.insert(index);
+    ordered.push(index);
+    nodes[index].stats.depth = depth;
+    let mut last_edited_at = nodes[index].stats.updated_at.clone();
+    for &child in children.get(&nodes[index].stats.id).into_iter().flatten() {
+        if visited.contains(&child) {
+            continue;
+        }
+        let child_edited_at = visit(child, depth + 1, nodes, children, visited, ordered);
+        last_edited_at = last_edited_at.max(child_edited_at);
+    }
+    nodes[index].stats.last_edited_at = last_edited_at.clone();
+    last_edited_at
+}
+
+/// Stats for one page's blocks, in reading order: each block followed by
+/// the blocks nested under it. Blocks whose parent isn't on the page are
+/// treated as top-level.
+fn page_blocks(rows: &[&Value]) -> (Totals, Vec<BlockStats>) {
+    let mut nodes: Vec<Node> = rows
+        .iter()
+        .filter_map(|row| {
+            let id = block_id(&row["id"])?;
+            Some(Node {
+                stats: block_stats(row, id),
+                order: row["order"].as_i64().unwrap_or_default(),
+                row,
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.order);
+
+    let ids: HashSet<i64> = nodes.iter().map(|node| node.stats.id).collect();
+    let mut roots: Vec<usize> = Vec::new();
+    let mut children: HashMap<i64, Vec<usize>> = HashMap::new();
+    for (index, node) in nodes.iter().enumerate() {
+        match node.stats.parent_block_id.filter(|parent| ids.contains(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(index),
+            None => roots.push(index),
+        }
+    }
+
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut ordered: Vec<usize> = Vec::new();
+    for root in roots {
+        visit(root, 0, &mut nodes, &children, &mut visited, &mut ordered);
+    }
+    // Blocks caught in a parent cycle are never reached from a root
+    for index in 0..nodes.len() {
+        if !visited.contains(&index) {
+            visit(index, 0, &mut nodes, &children, &mut visited, &mut ordered);
+        }
+    }
+
+    let mut totals = Totals::default();
+    for node in &nodes {
+        let stats = &node.stats;
+        totals.words += stats.words;
+        totals.characters += stats.characters;
+        totals.blocks += 1;
+        *totals.blocks_by_type.entry(stats.block_type.clone()).or_default() += 1;
+        if stats.block_type == "image" && node.row["content"].as_str().is_some_and(|url| !url.is_empty()) {
+            totals.images += 1;
+        }
+        totals.links += stats.links;
+        totals.max_depth = totals.max_depth.max(stats.depth);
+        if Some(&stats.updated_at) > totals.last_edited_at.as_ref() {
+            totals.last_edited_at = Some(stats.updated_at.clone());
+        }
+    }
+    totals.reading_time_minutes = reading_time_minutes(totals.words, totals.images);
+
+    let mut nodes: Vec<Option<Node>> = nodes.into_iter().map(Some).collect();
+    let blocks = ordered
+        .into_iter()
+        .filter_map(|index| nodes[index].take().map(|node| node.stats))
+        .collect();
+    (totals, blocks)
+}
+
+/// Word, character, block, image and link counts for a page, its estimated
+/// reading time, and per-block stats with when each block (or anything
+/// nested under it) was last edited.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn page_stats(page_id: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    if page_role(&supabase_client, &page_id).await?.is_none() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    }
+    let pages = supabase_client
+        .select("pages", &format!("select={}&id=eq.{}", PAGE_COLUMNS, encode(&page_id)))
+        .await?;
+    let title = pages.first().map(Page::from_row).map(|page| page.title).unwrap_or_default();
+    let rows = supabase_client
+        .select("blocks", &format!("select={}&page_id=eq.{}", BLOCK_COLUMNS, encode(&page_id)))
+        .await?;
+    let (totals, blocks) = page_blocks(&rows.iter().collect::<Vec<_>>());
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "page_id": page_id,
+            "title": title,
+            "totals": totals,
+            "blocks": blocks,
+        })),
+        error: None,
+    })
+}
+
+/// The same totals across every page in the active workspace (templates and
+/// the trash aside), with each page's headline numbers, longest first.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn account_stats() -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let pages: Vec<Page> = fetch_workspace_pages(&supabase_client, &workspace_id)
+        .await?
+        .into_iter()
+        .filter(|page| !page.is_template)
+        .collect();
+    let mut rows: Vec<Value> = Vec::new();
+    loop {
+        let batch = supabase_client
+            .select(
+                "blocks",
+                &format!(
+                    "select={},pages!inner(workspace_id,deleted_at,is_template)&pages.workspace_id=eq.{}&pages.deleted_at=is.null&pages.is_template=is.false&order=id.asc&limit={}&offset={}",
+                    BLOCK_COLUMNS,
+                    encode(&workspace_id),
+                    BLOCKS_PAGE_SIZE,
+                    rows.len()
+                ),
+            )
+            .await?;
+        let done = batch.len() < BLOCKS_PAGE_SIZE;
+        rows.extend(batch);
+        if done {
+            break;
+        }
+    }
+    let mut by_page: HashMap<&str, Vec<&Value>> = HashMap::new();
+    for row in &rows {
+        by_page
+            .entry(row["page_id"].as_str().unwrap_or_default())
+            .or_default()
+            .push(row);
+    }
+
+    let mut totals = Totals::default();
+    let mut summaries: Vec<(usize, Value)> = Vec::new();
+    for page in &pages {
+        let (page_totals, _) = page_blocks(by_page.get(page.id.as_str()).map_or(&[][..], |rows| rows));
+        totals.add(&page_totals);
+        summaries.push((
+            page_totals.words,
+            json!({
+                "page_id": page.id,
+                "title": page.title,
+                "words": page_totals.words,
+                "blocks": page_totals.blocks,
+                "reading_time_minutes": page_totals.reading_time_minutes,
+                "last_edited_at": page_totals.last_edited_at.as_ref().unwrap_or(&page.updated_at),
+            }),
+        ));
+    }
+    summaries.sort_by_key(|(words, _)| std::cmp::Reverse(*words));
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({
+            "page_count": pages.len(),
+            "totals": totals,
+            "pages": summaries.into_iter().map(|(_, page)| page).collect::<Vec<_>>(),
+        })),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: i64, parent: Option<i64>, order: i64, content: &str, updated_at: &str) -> Value {
+        json!({
+            "id": id,
+            "type": "text",
+            "content": content,
+            "order": order,
+            "parent_block_id": parent,
+            "updated_at": updated_at,
+        })
+    }
+
+    #[test]
+    fn reading_time_rounds_up_and_counts_images() {
+        assert_eq!(reading_time_minutes(0, 0), 0);
+        assert_eq!(reading_time_minutes(1, 0), 1);
+        assert_eq!(reading_time_minutes(230, 0), 1);
+        assert_eq!(reading_time_minutes(231, 0), 2);
+        assert_eq!(reading_time_minutes(230, 5), 2);
+    }
+
+    #[test]
+    fn blocks_come_in_reading_order_with_nested_edits() {
+        let rows = [
+            block(3, Some(1), 0, "child", "2024-01-05T00:00:00Z"),
+            block(2, None, 2, "second", "2024-01-02T00:00:00Z"),
+            block(1, None, 1, "first https://a.b", "2024-01-01T00:00:00Z"),
+            block(4, Some(99), 3, "orphan", "2024-01-03T00:00:00Z"),
+        ];
+        let (totals, blocks) = page_blocks(&rows.iter().collect::<Vec<_>>());
+
+        let order: Vec<(i64, usize)> = blocks.iter().map(|b| (b.id, b.depth)).collect();
+        assert_eq!(order, [(1, 0), (3, 1), (2, 0), (4, 0)]);
+        assert_eq!(blocks[0].last_edited_at, "2024-01-05T00:00:00Z");
+        assert_eq!(totals.words, 5);
+        assert_eq!(totals.links, 1);
+        assert_eq!(totals.max_depth, 1);
+        assert_eq!(totals.last_edited_at.as_deref(), Some("2024-01-05T00:00:00Z"));
+    }
+
+    #[test]
+    fn image_blocks_count_as_images_not_words() {
+        let mut image = block(1, None, 0, "https://x/storage/v1/object/public/images/k", "");
+        image["type"] = json!("image");
+        let (totals, _) = page_blocks(&[&image]);
+        assert_eq!((totals.words, totals.images, totals.links), (0, 1, 0));
+        assert_eq!(totals.reading_time_minutes, 1);
+    }
+}
@@ -1,4 +1,6 @@
-use reqwest::Client;
+use crate::functions::duplicate::{image_key, IMAGE_BUCKET};
+use crate::functions::supabase::{initialize_anon_client, initialize_user_client};
+use crate::supabase::rest::RestClient;
 use serde::Deserialize;
 use std::env;
 use std::fs::{self, File};
@@ -6,21 +8,96 @@ use std::io::Read;
 use std::path::Path;
 use uuid::Uuid;
 
+/// The images bucket is private; images are shown through signed URLs that
+/// stay valid this long, well past how long a page stays open unrefreshed.
+pub const SIGNED_IMAGE_SECONDS: u64 = 60 * 60;
+
 #[derive(Deserialize)]
 struct UploadResponse {
     key: String,
 }
 
+/// An images-bucket URL in the form it is stored in, `.../object/public/images/<key>`
+/// plus its display parameters, for a signed URL the webview sends back.
+/// Anything else is returned unchanged.
+pub fn stored_image_url(content: &str) -> String {
+    let (Some(key), Some(start)) = (image_key(content), content.find("/storage/v1/object/")) else {
+        return content.to_string();
+    };
+    if content[start..].starts_with("/storage/v1/object/public/") {
+        return content.to_string();
+    }
+    let key_end = content[start..].find(key).map_or(content.len(), |i| start + i + key.len());
+    let params: Vec<&str> = content[key_end..]
+        .trim_start_matches('?')
+        .split('&')
+        .filter(|param| !param.is_empty() && !param.starts_with("token="))
+        .collect();
+    let mut url = format!("{}/storage/v1/object/public/{}/{}", &content[..start], IMAGE_BUCKET, key);
+    if !params.is_empty() {
+        url.push('?');
+        url.push_str(&params.join("&"));
+    }
+    url
+}
+
+/// `signed` with the display parameters of the stored URL it replaces.
+fn signed_image_url(content: &str, key: &str, signed: &str) -> String {
+    let key_end = content.find(key).map_or(content.len(), |i| i + key.len());
+    let params = content[key_end..].trim_start_matches(['?', '&']);
+    if params.is_empty() {
+        signed.to_string()
+    } else {
+        format!("{}&{}", signed, params)
+    }
+}
+
+/// Replaces each images-bucket URL in `urls` with a signed one, signing them
+/// all in one request. URLs the caller can't read, or that fail to sign, are
+/// left as they are so one missing image doesn't hide the rest.
+pub async fn sign_images(supabase_client: &RestClient, urls: Vec<&mut String>) {
+    let mut keys: Vec<String> = urls
+        .iter()
+        .filter_map(|url| image_key(url))
+        .map(str::to_string)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    if keys.is_empty() {
+        return;
+    }
+    let signed = match supabase_client
+        .sign_objects(IMAGE_BUCKET, &keys, SIGNED_IMAGE_SECONDS)
+        .await
+    {
+        Ok(signed) => signed,
+        Err(e) => {
+            tracing::warn!(images = keys.len(), error = %e, "failed to sign images");
+            return;
+        }
+    };
+    for url in urls {
+        let replacement = image_key(url)
+            .and_then(|key| signed.get(key).map(|signed| signed_image_url(url, key, signed)));
+        if let Some(replacement) = replacement {
+            *url = replacement;
+        }
+    }
+}
+
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(bucket = %bucket))]
 pub async fn upload_file(
     bucket: &str,
     path: &str,
     file_path: &str,
-    supabase_url: &str,
-    supabase_key: &str,
     delete_after_upload: bool, // New parameter to control file deletion
 ) -> Result<serde_json::Value, String> {
-    let client = Client::new();
+    // Uploads made before sign in (the sign up avatar) fall back to the project key
+    let supabase_client = match initialize_user_client().await {
+        Ok(client) => client,
+        Err(_) => initialize_anon_client().await?,
+    };
 
     // Check if file exists
     if !Path::new(file_path).exists() {
@@ -36,42 +113,37 @@ pub async fn upload_file(
     let content_type = mime_guess::from_path(file_path).first_or_octet_stream().to_string();
 
     // Make the request to upload the file
-    let response = client
-        .post(format!(
-            "{}/storage/v1/object/{}/{}",
-            supabase_url, bucket, path
-        ))
-        .header("Authorization", format!("Bearer {}", supabase_key))
-        .header("Content-Type", content_type)
-        .body(buffer)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let status = response.status();
-    let text = response.text().await.map_err(|e| e.to_string())?;
+    let result = supabase_client
+        .upload_object(bucket, path, content_type, buffer)
+        .await;
 
     // Delete the temp file if requested and upload was successful
-    if status.is_success() && delete_after_upload {
+    if result.is_ok() && delete_after_upload {
         // Attempt to delete the file, but don't fail the function if deletion fails
         if let Err(e) = fs::remove_file(file_path) {
-            eprintln!("Warning: Failed to delete temp file {}: {}", file_path, e);
+            tracing::warn!(file_path, error = %e, "failed to delete temp file");
         }
     }
 
-    if status.is_success() {
-        Ok(serde_json::json!({
-            "success": true,
-            "response": text,
-            "path": path,
-            "url": format!("{}/storage/v1/object/public/{}/{}", supabase_url, bucket, path)
-        }))
-    } else {
-        Err(format!("Upload failed: {}", text))
+    let text = result?;
+    let url = format!("{}/storage/v1/object/public/{}/{}", supabase_client.url(), bucket, path);
+    // `url` is what gets stored; the images bucket is private, so it is
+    // shown through `signed_url`
+    let mut signed_url = url.clone();
+    if bucket == IMAGE_BUCKET {
+        sign_images(&supabase_client, vec![&mut signed_url]).await;
     }
+    Ok(serde_json::json!({
+        "success": true,
+        "response": text,
+        "path": path,
+        "url": url,
+        "signed_url": signed_url,
+    }))
 }
 
 #[tauri::command]
+#[tracing::instrument(skip_all)]
 pub async fn save_temp_file(file_bytes: Vec<u8>) -> Result<String, String> {
     // Get temp directory
     let temp_dir = env::temp_dir();
@@ -83,3 +155,40 @@ pub async fn save_temp_file(file_bytes: Vec<u8>) -> Result<String, String> {
 
     Ok(file_path.to_string_lossy().into_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORED: &str = "https://x.supabase.co/storage/v1/object/public/images/abc-123?&height=auto&width=auto";
+    const SIGNED: &str = "https://x.supabase.co/storage/v1/object/sign/images/abc-123?token=t0k";
+
+    #[test]
+    fn signed_url_keeps_the_display_parameters() {
+        assert_eq!(
+            signed_image_url(STORED, "abc-123", SIGNED),
+            format!("{}&height=auto&width=auto", SIGNED)
+        );
+    }
+
+    #[test]
+    fn signed_url_without_parameters_is_used_as_is() {
+        let stored = "https://x.supabase.co/storage/v1/object/public/images/abc-123";
+        assert_eq!(signed_image_url(stored, "abc-123", SIGNED), SIGNED);
+    }
+
+    #[test]
+    fn signed_url_is_stored_in_plain_form_without_its_token() {
+        let resized = format!("{}&width=320&height=auto", SIGNED);
+        assert_eq!(
+            stored_image_url(&resized),
+            "https://x.supabase.co/storage/v1/object/public/images/abc-123?width=320&height=auto"
+        );
+    }
+
+    #[test]
+    fn plain_urls_and_other_content_are_stored_unchanged() {
+        assert_eq!(stored_image_url(STORED), STORED);
+        assert_eq!(stored_image_url("https://example.com/cat.png"), "https://example.com/cat.png");
+    }
+}
@@ -1,15 +1,57 @@
+use crate::functions::session::{current_session, set_session, Session};
+use crate::supabase::rest::RestClient;
+use serde_json::json;
 use dotenv::dotenv;
 use std::env::var;
-use supabase_rs::SupabaseClient;
 
-pub async fn initialize_supabase_client() -> SupabaseClient {
-    dotenv().ok(); // Load the .env file
+// Tokens are refreshed this long before they expire, so a request never
+// goes out with one that lapses on the way.
+const REFRESH_MARGIN_SECONDS: i64 = 300;
 
-    let supabase_client: SupabaseClient = SupabaseClient::new(
-        var("VITE_SUPABASE_URL").unwrap(),
-        var("VITE_SUPABASE_API_KEY").unwrap(),
-    )
-    .unwrap();
+/// Client for the signed-in user. Requests carry the JWT the database issued
+/// at sign-in, so the database's RLS policies only expose their own rows.
+/// The token is swapped for a fresh one shortly before it expires.
+pub async fn initialize_user_client() -> Result<RestClient, String> {
+    dotenv().ok();
 
-    supabase_client
+    let mut session = current_session()?;
+    let supabase_url =
+        var("VITE_SUPABASE_URL").map_err(|_| "Missing SUPABASE_URL in .env".to_string())?;
+    let supabase_key =
+        var("VITE_SUPABASE_API_KEY").map_err(|_| "Missing SUPABASE_KEY in .env".to_string())?;
+
+    if session.expires_within(REFRESH_MARGIN_SECONDS) {
+        let client = RestClient::new(supabase_url.clone(), supabase_key.clone(), session.access_token);
+        let result = client.rpc_read("refresh_session", json!({})).await?;
+        session = Session::from_rpc(&result).ok_or("Session expired, sign in again")?;
+        set_session(session.clone());
+    }
+
+    Ok(RestClient::new(supabase_url, supabase_key, session.access_token))
+}
+
+/// Client authorized with a token that hasn't been checked yet, e.g. one the
+/// webview kept from an earlier sign-in.
+pub async fn initialize_token_client(access_token: String) -> Result<RestClient, String> {
+    dotenv().ok();
+
+    let supabase_url =
+        var("VITE_SUPABASE_URL").map_err(|_| "Missing SUPABASE_URL in .env".to_string())?;
+    let supabase_key =
+        var("VITE_SUPABASE_API_KEY").map_err(|_| "Missing SUPABASE_KEY in .env".to_string())?;
+
+    Ok(RestClient::new(supabase_url, supabase_key, access_token))
+}
+
+/// Client authorized with the project API key only, for the few requests that
+/// happen before anyone is signed in (e.g. uploading an avatar during sign up).
+pub async fn initialize_anon_client() -> Result<RestClient, String> {
+    dotenv().ok();
+
+    let supabase_url =
+        var("VITE_SUPABASE_URL").map_err(|_| "Missing SUPABASE_URL in .env".to_string())?;
+    let supabase_key =
+        var("VITE_SUPABASE_API_KEY").map_err(|_| "Missing SUPABASE_KEY in .env".to_string())?;
+
+    Ok(RestClient::new(supabase_url, supabase_key.clone(), supabase_key))
 }
@@ -0,0 +1,471 @@
+use crate::functions::duplicate::{copy_blocks, remove_images};
+use crate::functions::embeddings::queue_indexing;
+use crate::functions::pages::{live_page_exists, next_position, Page, PAGE_COLUMNS};
+use crate::functions::links::index_links;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::{current_session, Session};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::trash::delete_page;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use chrono::{Local, NaiveDate};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use urlencoding::encode;
+use uuid::Uuid;
+
+const BUILTIN_PREFIX: &str = "builtin:";
+
+struct BuiltinTemplate {
+    id: &'static str,
+    title: &'static str,
+    description: &'static str,
+    blocks: &'static [&'static str],
+}
+
+const BUILTIN_TEMPLATES: &[BuiltinTemplate] = &[
+    BuiltinTemplate {
+        id: "builtin:meeting-notes",
+        title: "Meeting notes {{date}}",
+        description: "Attendees, agenda, notes and action items",
+        blocks: &[
+            "Date: {{date}}",
+            "Attendees: {{user.first_name}}",
+            "Agenda",
+            "Notes",
+            "Action items",
+        ],
+    },
+    BuiltinTemplate {
+        id: "builtin:daily-journal",
+        title: "{{date}}",
+        description: "Priorities, notes and gratitude for the day",
+        blocks: &[
+            "{{weekday}}, {{date}}",
+            "Top three priorities",
+            "Notes",
+            "Grateful for",
+        ],
+    },
+    BuiltinTemplate {
+        id: "builtin:project-brief",
+        title: "Project brief",
+        description: "Goal, scope, milestones and risks",
+        blocks: &[
+            "{{title}}",
+            "Owner: {{user.first_name}} {{user.last_name}}",
+            "Goal",
+            "Scope",
+            "Milestones",
+            "Risks",
+        ],
+    },
+];
+
+fn builtin(template_id: &str) -> Option<&'static BuiltinTemplate> {
+    BUILTIN_TEMPLATES.iter().find(|t| t.id == template_id)
+}
+
+/// Values for the `{{name}}` placeholders in a template's title and blocks.
+/// Unknown placeholders are left as written.
+pub struct TemplateVariables {
+    values: HashMap<&'static str, String>,
+}
+
+impl TemplateVariables {
+    pub fn new(session: &Session, date: NaiveDate) -> Self {
+        let values = HashMap::from([
+            ("date", date.format("%Y-%m-%d").to_string()),
+            ("weekday", date.format("%A").to_string()),
+            ("time", Local::now().format("%H:%M").to_string()),
+            ("user.first_name", session.first_name.clone()),
+            ("user.last_name", session.last_name.clone()),
+            ("user.email", session.email.clone()),
+        ]);
+        TemplateVariables { values }
+    }
+
+    pub fn set(&mut self, name: &'static str, value: String) {
+        self.values.insert(name, value);
+    }
+
+    pub fn expand(&self, text: &str) -> String {
+        static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+        let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*([\w.]+)\s*\}\}").unwrap());
+        placeholder
+            .replace_all(text, |caps: &Captures| {
+                self.values
+                    .get(&caps[1])
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateSummary {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub builtin: bool,
+    pub updated_at: Option<String>,
+}
+
+fn builtin_error(template_id: &str) -> Response<Value> {
+    Response {
+        status: StatusCode::BadRequest,
+        data: None,
+        error: Some(format!("{} is a built-in template and cannot be changed", template_id)),
+    }
+}
+
+fn not_found() -> Response<Value> {
+    Response {
+        status: StatusCode::NotFound,
+        data: None,
+        error: Some("Template not found".to_string()),
+    }
+}
+
+/// Whether `template_id` is a built-in template or one of the user's live
+/// template pages.
+pub async fn template_exists(
+    supabase_client: &RestClient,
+    user_id: &str,
+    template_id: &str,
+) -> Result<bool, String> {
+    if builtin(template_id).is_some() {
+        return Ok(true);
+    }
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select=id&id=eq.{}&user_id=eq.{}&is_template=is.true&deleted_at=is.null",
+                encode(template_id),
+                user_id
+            ),
+        )
+        .await?;
+    Ok(!rows.is_empty())
+}
+
+/// Creates a page from a built-in or user template, expanding placeholders
+/// in its title and text blocks. The new page's title wins over the
+/// template's when given, and `page_fields` (a JSON object) is merged into
+/// the new page row. Returns `None` if the template doesn't exist.
+pub async fn create_page_from_template(
+    supabase_client: &RestClient,
+    session: &Session,
+    template_id: &str,
+    title: Option<String>,
+    parent_page_id: Option<String>,
+    date: NaiveDate,
+    page_fields: Value,
+) -> Result<Option<Page>, String> {
+    let (template_title, blocks) = match builtin(template_id) {
+        Some(template) => {
+            let blocks: Vec<Value> = template
+                .blocks
+                .iter()
+                .enumerate()
+                .map(|(order, content)| {
+                    json!({
+                        "id": order,
+                        "page_id": template_id,
+                        "type": "text",
+                        "order": order,
+                        "content": content,
+                    })
+                })
+                .collect();
+            (template.title.to_string(), blocks)
+        }
+        None => {
+            let rows = supabase_client
+                .select(
+                    "pages",
+                    &format!(
+                        "select=title&id=eq.{}&user_id=eq.{}&is_template=is.true&deleted_at=is.null",
+                        encode(template_id),
+                        session.user_id
+                    ),
+                )
+                .await?;
+            let Some(row) = rows.first() else {
+                return Ok(None);
+            };
+            let blocks = supabase_client
+                .select("blocks", &format!("page_id=eq.{}", encode(template_id)))
+                .await?;
+            (row["title"].as_str().unwrap_or_default().to_string(), blocks)
+        }
+    };
+
+    let parent_page_id = parent_page_id.filter(|id| !id.is_empty());
+    if let Some(parent_id) = &parent_page_id {
+        if !live_page_exists(supabase_client, parent_id).await? {
+            return Err(format!("Parent page {} not found", parent_id));
+        }
+    }
+
+    let mut variables = TemplateVariables::new(session, date);
+    variables.set("title", template_title.clone());
+    let title = title
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| variables.expand(&template_title));
+    variables.set("title", title.clone());
+
+    let page_id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let workspace_id = active_workspace_id(supabase_client).await?;
+    let position = next_position(supabase_client, &workspace_id, parent_page_id.as_deref()).await?;
+    let mut row = json!({
+        "id": page_id,
+        "user_id": session.user_id,
+        "title": title,
+        "parent_page_id": parent_page_id,
+        "position": position,
+        "created_at": now,
+        "updated_at": now,
+    });
+    if let (Some(row), Some(fields)) = (row.as_object_mut(), page_fields.as_object()) {
+        row.extend(fields.clone());
+    }
+    let rows = supabase_client.insert("pages", row).await?;
+    let page = rows.first().map(Page::from_row).ok_or("Page was not created")?;
+
+    let page_ids = HashMap::from([(template_id.to_string(), page_id.clone())]);
+    let copied = copy_blocks(supabase_client, &blocks, &page_ids, |content| variables.expand(content)).await;
+    if !copied.blocks.is_empty() {
+        if let Err(e) = supabase_client.insert("blocks", json!(copied.blocks)).await {
+            if let Err(cleanup) = supabase_client
+                .delete("pages", &format!("id=eq.{}", encode(&page_id)))
+                .await
+            {
+                tracing::warn!(error = %cleanup, "failed to remove partial page");
+            }
+            remove_images(supabase_client, &copied.image_keys).await;
+            return Err(e);
+        }
+        if let Err(e) = index_links(supabase_client, &copied.blocks).await {
+            tracing::warn!(error = %e, "failed to index page links");
+        }
+    }
+
+    tracing::info!(template_id, blocks = copied.blocks.len(), "page created from template");
+    queue_indexing(copied.jobs, session.user_id.clone());
+
+    Ok(Some(page))
+}
+
+/// The built-in templates followed by the user's own, alphabetically.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_templates() -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select={}&user_id=eq.{}&is_template=is.true&deleted_at=is.null&order=title.asc",
+                PAGE_COLUMNS, session.user_id
+            ),
+        )
+        .await?;
+
+    let templates: Vec<TemplateSummary> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|template| TemplateSummary {
+            id: template.id.to_string(),
+            title: template.title.to_string(),
+            description: Some(template.description.to_string()),
+            builtin: true,
+            updated_at: None,
+        })
+        .chain(rows.iter().map(Page::from_row).map(|page| TemplateSummary {
+            id: page.id,
+            title: page.title,
+            description: None,
+            builtin: false,
+            updated_at: Some(page.updated_at),
+        }))
+        .collect();
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(templates)),
+        error: None,
+    })
+}
+
+/// Marks `from_page_id` as a template, or creates a new, empty template
+/// page to be filled in with the editor.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(from_page_id = ?from_page_id))]
+pub async fn create_template(
+    title: String,
+    from_page_id: Option<String>,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let rows = match from_page_id.filter(|id| !id.is_empty()) {
+        Some(page_id) => {
+            let mut body = json!({ "is_template": true, "updated_at": now });
+            if !title.trim().is_empty() {
+                body["title"] = json!(title);
+            }
+            supabase_client
+                .update(
+                    "pages",
+                    &format!(
+                        "id=eq.{}&user_id=eq.{}&deleted_at=is.null",
+                        encode(&page_id),
+                        session.user_id
+                    ),
+                    body,
+                )
+                .await?
+        }
+        None => {
+            let workspace_id = active_workspace_id(&supabase_client).await?;
+            let position = next_position(&supabase_client, &workspace_id, None).await?;
+            supabase_client
+                .insert(
+                    "pages",
+                    json!({
+                        "id": Uuid::new_v4().to_string(),
+                        "user_id": session.user_id,
+                        "title": title,
+                        "is_template": true,
+                        "position": position,
+                        "created_at": now,
+                        "updated_at": now,
+                    }),
+                )
+                .await?
+        }
+    };
+
+    match rows.first() {
+        Some(row) => Ok(Response {
+            status: StatusCode::Ok,
+            data: Some(json!(Page::from_row(row))),
+            error: None,
+        }),
+        None => Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        }),
+    }
+}
+
+/// Renames a template, or with `is_template: false` turns it back into an
+/// ordinary page. Its blocks are edited like any other page's.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(template_id = %template_id))]
+pub async fn update_template(
+    template_id: String,
+    title: Option<String>,
+    is_template: Option<bool>,
+) -> Result<Response<Value>, String> {
+    if template_id.starts_with(BUILTIN_PREFIX) {
+        return Ok(builtin_error(&template_id));
+    }
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+
+    let mut body = json!({ "updated_at": chrono::Utc::now().to_rfc3339() });
+    if let Some(title) = title {
+        body["title"] = json!(title);
+    }
+    if let Some(is_template) = is_template {
+        body["is_template"] = json!(is_template);
+    }
+    let rows = supabase_client
+        .update(
+            "pages",
+            &format!(
+                "id=eq.{}&user_id=eq.{}&is_template=is.true&deleted_at=is.null",
+                encode(&template_id),
+                session.user_id
+            ),
+            body,
+        )
+        .await?;
+
+    match rows.first() {
+        Some(row) => Ok(Response {
+            status: StatusCode::Ok,
+            data: Some(json!(Page::from_row(row))),
+            error: None,
+        }),
+        None => Ok(not_found()),
+    }
+}
+
+/// Moves a template page (and its subpages) to the trash.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(template_id = %template_id))]
+pub async fn delete_template(template_id: String) -> Result<Response<Value>, String> {
+    if template_id.starts_with(BUILTIN_PREFIX) {
+        return Ok(builtin_error(&template_id));
+    }
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select=id&id=eq.{}&user_id=eq.{}&is_template=is.true&deleted_at=is.null",
+                encode(&template_id),
+                session.user_id
+            ),
+        )
+        .await?;
+    if rows.is_empty() {
+        return Ok(not_found());
+    }
+
+    delete_page(template_id).await
+}
+
+/// Creates a new page from a template. See `create_page_from_template`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(template_id = %template_id))]
+pub async fn instantiate_template(
+    template_id: String,
+    title: Option<String>,
+    parent_page_id: Option<String>,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let today = Local::now().date_naive();
+
+    match create_page_from_template(
+        &supabase_client,
+        &session,
+        &template_id,
+        title,
+        parent_page_id,
+        today,
+        json!({}),
+    )
+    .await?
+    {
+        Some(page) => Ok(Response {
+            status: StatusCode::Ok,
+            data: Some(json!(page)),
+            error: None,
+        }),
+        None => Ok(not_found()),
+    }
+}
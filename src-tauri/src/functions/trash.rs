@@ -0,0 +1,245 @@
+use crate::functions::page_tree::PageForest;
+use crate::functions::pages::{fetch_workspace_pages, ids_filter, Page, PAGE_COLUMNS};
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::supabase::initialize_user_client;
+use crate::functions::workspaces::active_workspace_id;
+use crate::supabase::rest::RestClient;
+use chrono::{DateTime, Duration, Utc};
+use dotenv::dotenv;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use urlencoding::encode;
+
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// Days a page stays in the trash before it is purged, from
+/// `ZENOTE_TRASH_RETENTION_DAYS`. `None` (a value of 0) keeps it forever.
+pub fn retention_days() -> Option<u32> {
+    dotenv().ok();
+    let days = env::var("ZENOTE_TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    (days > 0).then_some(days)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashItem {
+    #[serde(flatten)]
+    pub page: Page,
+    /// Pages deleted along with this one.
+    pub subpage_count: usize,
+    /// When the page will be deleted for good, if retention is enabled.
+    pub purge_at: Option<String>,
+}
+
+/// Every trashed page in `workspace_id`, with the id of the page whose
+/// deletion put it there.
+async fn fetch_trashed_pages(
+    supabase_client: &RestClient,
+    workspace_id: &str,
+) -> Result<Vec<(Page, Option<String>)>, String> {
+    let data = supabase_client
+        .select(
+            "pages",
+            &format!(
+                "select={},trashed_root_id&workspace_id=eq.{}&deleted_at=not.is.null",
+                PAGE_COLUMNS,
+                encode(workspace_id)
+            ),
+        )
+        .await?;
+    Ok(data
+        .iter()
+        .map(|row| {
+            let root = row["trashed_root_id"].as_str().map(|id| id.to_string());
+            (Page::from_row(row), root)
+        })
+        .collect())
+}
+
+/// Permanently deletes the signed-in user's pages that have outlived the
+/// retention period. Blocks and embeddings go with them through the
+/// database's cascades.
+pub async fn purge_expired_trash() -> Result<usize, String> {
+    let Some(days) = retention_days() else {
+        return Ok(0);
+    };
+    let supabase_client = initialize_user_client().await?;
+    let purged = supabase_client
+        .rpc("purge_trash", json!({ "p_retention_days": days }))
+        .await?;
+    let purged = purged.as_u64().unwrap_or_default() as usize;
+    if purged > 0 {
+        tracing::info!(purged, retention_days = days, "purged expired trash");
+    }
+    Ok(purged)
+}
+
+/// Runs `purge_expired_trash` in the background, e.g. right after sign in.
+pub fn schedule_purge() {
+    tauri::async_runtime::spawn(async {
+        if let Err(e) = purge_expired_trash().await {
+            tracing::warn!(error = %e, "failed to purge expired trash");
+        }
+    });
+}
+
+/// Moves a page and all of its subpages to the trash. Their blocks stay
+/// attached and come back with them on `restore_page`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn delete_page(page_id: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let forest = PageForest::new(fetch_workspace_pages(&supabase_client, &workspace_id).await?);
+
+    if forest.get(&page_id).is_none() {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page not found".to_string()),
+        });
+    }
+
+    let mut ids = vec![page_id.clone()];
+    ids.extend(forest.descendants(&page_id));
+
+    let body = json!({
+        "deleted_at": Utc::now().to_rfc3339(),
+        "trashed_root_id": page_id,
+    });
+    let rows = supabase_client
+        .update(
+            "pages",
+            &format!("id={}&deleted_at=is.null", ids_filter(&ids)),
+            body,
+        )
+        .await?;
+
+    tracing::info!(pages = rows.len(), "page moved to trash");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "trashed_page_ids": ids })),
+        error: None,
+    })
+}
+
+/// The active workspace's deleted pages, most recent first. Subpages deleted with a
+/// page are counted under it rather than listed separately.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_trash() -> Result<Response<Value>, String> {
+    if let Err(e) = purge_expired_trash().await {
+        tracing::warn!(error = %e, "failed to purge expired trash");
+    }
+
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let trashed = fetch_trashed_pages(&supabase_client, &workspace_id).await?;
+
+    let mut subpages: HashMap<String, usize> = HashMap::new();
+    for (page, root) in &trashed {
+        if let Some(root) = root.as_ref().filter(|root| **root != page.id) {
+            *subpages.entry(root.clone()).or_default() += 1;
+        }
+    }
+
+    let retention = retention_days();
+    let mut items: Vec<TrashItem> = trashed
+        .into_iter()
+        // Pages trashed before trashed_root_id existed count as their own root
+        .filter(|(page, root)| root.as_ref().is_none_or(|root| *root == page.id))
+        .map(|(page, _)| {
+            let purge_at = retention.and_then(|days| {
+                let deleted_at = DateTime::parse_from_rfc3339(page.deleted_at.as_deref()?).ok()?;
+                Some((deleted_at + Duration::days(days as i64)).to_rfc3339())
+            });
+            TrashItem {
+                subpage_count: subpages.get(&page.id).copied().unwrap_or_default(),
+                page,
+                purge_at,
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| b.page.deleted_at.cmp(&a.page.deleted_at));
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(items)),
+        error: None,
+    })
+}
+
+/// Restores a trashed page together with the subpages deleted with it. If
+/// its parent is gone or still in the trash, it is restored at the top level.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn restore_page(page_id: String) -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let trashed = fetch_trashed_pages(&supabase_client, &workspace_id).await?;
+
+    // Restoring a subpage restores the whole deletion it was part of
+    let Some(root_id) = trashed
+        .iter()
+        .find(|(page, _)| page.id == page_id)
+        .map(|(page, root)| root.clone().unwrap_or_else(|| page.id.clone()))
+    else {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page is not in the trash".to_string()),
+        });
+    };
+    let Some(root) = trashed.iter().map(|(page, _)| page).find(|page| page.id == root_id) else {
+        return Ok(Response {
+            status: StatusCode::NotFound,
+            data: None,
+            error: Some("Page is not in the trash".to_string()),
+        });
+    };
+
+    let rows = supabase_client
+        .rpc("restore_trashed_pages", json!({ "p_root_id": root_id }))
+        .await?;
+    let rows = rows.as_array().cloned().unwrap_or_default();
+    let reparented = root.parent_page_id.is_some()
+        && rows
+            .iter()
+            .any(|row| row["id"] == json!(root_id) && row["parent_page_id"].is_null());
+
+    let restored: Vec<Page> = rows.iter().map(Page::from_row).collect();
+    tracing::info!(pages = restored.len(), reparented, "page restored");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(restored)),
+        error: None,
+    })
+}
+
+/// Permanently deletes everything in the active workspace's trash.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn empty_trash() -> Result<Response<Value>, String> {
+    let supabase_client = initialize_user_client().await?;
+    let workspace_id = active_workspace_id(&supabase_client).await?;
+    let rows = supabase_client
+        .delete(
+            "pages",
+            &format!("workspace_id=eq.{}&deleted_at=not.is.null", encode(&workspace_id)),
+        )
+        .await?;
+
+    tracing::info!(pages = rows.len(), "trash emptied");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "deleted": rows.len() })),
+        error: None,
+    })
+}
@@ -0,0 +1,496 @@
+use crate::functions::embeddings::{queue_indexing, IndexJob};
+use crate::functions::links::index_links;
+use crate::functions::pages::ids_filter;
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::functions::sharing::{forbidden, page_role};
+use crate::functions::supabase::initialize_user_client;
+use crate::supabase::rest::RestClient;
+use chrono::{DateTime, Duration, Utc};
+use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use urlencoding::encode;
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+const DEFAULT_MAX_VERSIONS: usize = 50;
+
+const VERSION_SUMMARY_COLUMNS: &str = "id,page_id,created_at,reason,title,block_count";
+
+/// Why a snapshot is taken, which also decides how often it may happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotReason {
+    /// Blocks or the title are about to be edited. At most one snapshot per
+    /// editing session, so typing doesn't create a version per keystroke.
+    Edit,
+    /// A block is about to be deleted. Deleting many blocks in a row keeps
+    /// the one snapshot taken before the first of them.
+    Delete,
+    /// The page is about to be replaced by an older version.
+    Restore,
+}
+
+impl SnapshotReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            SnapshotReason::Edit => "edit",
+            SnapshotReason::Delete => "delete",
+            SnapshotReason::Restore => "restore",
+        }
+    }
+
+    /// No new snapshot is taken if the latest one is younger than this.
+    fn min_interval(self) -> Duration {
+        match self {
+            SnapshotReason::Edit => Duration::minutes(10),
+            SnapshotReason::Delete => Duration::minutes(1),
+            SnapshotReason::Restore => Duration::zero(),
+        }
+    }
+}
+
+/// Days versions are kept (`ZENOTE_VERSION_RETENTION_DAYS`) and the most
+/// kept per page (`ZENOTE_MAX_VERSIONS_PER_PAGE`).
+fn retention() -> (i64, usize) {
+    dotenv().ok();
+    let days = env::var("ZENOTE_VERSION_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    let max = env::var("ZENOTE_MAX_VERSIONS_PER_PAGE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_VERSIONS);
+    (days, max.max(1))
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VersionBlock {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub order: i64,
+    pub content: String,
+    pub parent_block_id: Option<i64>,
+}
+
+fn id_from(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|id| id.parse().ok()))
+}
+
+impl VersionBlock {
+    fn from_row(block: &Value) -> Option<VersionBlock> {
+        Some(VersionBlock {
+            id: id_from(&block["id"])?,
+            block_type: block["type"].as_str().unwrap_or("text").to_string(),
+            order: block["order"].as_i64().unwrap_or_default(),
+            content: block["content"].as_str().unwrap_or_default().to_string(),
+            parent_block_id: id_from(&block["parent_block_id"]),
+        })
+    }
+}
+
+/// A page's title and ordered blocks, as stored in a version or as they
+/// are now.
+#[derive(Debug, Serialize)]
+pub struct PageContent {
+    pub title: String,
+    pub blocks: Vec<VersionBlock>,
+}
+
+impl PageContent {
+    /// FNV-1a over the title and blocks; stable across builds so it can be
+    /// compared with hashes stored earlier.
+    fn hash(&self) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let bytes = serde_json::to_vec(&(&self.title, &self.blocks)).unwrap_or_default();
+        for byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+async fn current_content(
+    supabase_client: &RestClient,
+    page_id: &str,
+) -> Result<Option<PageContent>, String> {
+    let pages = supabase_client
+        .select("pages", &format!("select=title&id=eq.{}", encode(page_id)))
+        .await?;
+    let Some(page) = pages.first() else {
+        return Ok(None);
+    };
+    let rows = supabase_client
+        .select(
+            "blocks",
+            &format!(
+                "select=id,type,order,content,parent_block_id&page_id=eq.{}&order=order.asc,id.asc",
+                encode(page_id)
+            ),
+        )
+        .await?;
+    Ok(Some(PageContent {
+        title: page["title"].as_str().unwrap_or_default().to_string(),
+        blocks: rows.iter().filter_map(VersionBlock::from_row).collect(),
+    }))
+}
+
+async fn version_content(
+    supabase_client: &RestClient,
+    version_id: i64,
+) -> Result<Option<(Value, PageContent)>, String> {
+    let rows = supabase_client
+        .select(
+            "page_versions",
+            &format!("select={},blocks&id=eq.{}", VERSION_SUMMARY_COLUMNS, version_id),
+        )
+        .await?;
+    let Some(row) = rows.into_iter().next() else {
+        return Ok(None);
+    };
+    let content = PageContent {
+        title: row["title"].as_str().unwrap_or_default().to_string(),
+        blocks: serde_json::from_value(row["blocks"].clone()).unwrap_or_default(),
+    };
+    Ok(Some((row, content)))
+}
+
+/// Deletes versions of `page_id` past the retention period or beyond the
+/// per-page limit.
+async fn prune_versions(supabase_client: &RestClient, page_id: &str) -> Result<(), String> {
+    let (days, max) = retention();
+    if days > 0 {
+        let cutoff = (Utc::now() - Duration::days(days)).to_rfc3339();
+        supabase_client
+            .delete(
+                "page_versions",
+                &format!(
+                    "page_id=eq.{}&created_at=lt.{}",
+                    encode(page_id),
+                    encode(&cutoff)
+                ),
+            )
+            .await?;
+    }
+
+    let excess = supabase_client
+        .select(
+            "page_versions",
+            &format!(
+                "select=id&page_id=eq.{}&order=created_at.desc&offset={}",
+                encode(page_id),
+                max
+            ),
+        )
+        .await?;
+    let ids: Vec<String> = excess.iter().filter_map(|row| id_from(&row["id"])).map(|id| id.to_string()).collect();
+    if !ids.is_empty() {
+        supabase_client
+            .delete("page_versions", &format!("id={}", ids_filter(&ids)))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Records the page as it is now, unless a snapshot was taken too recently
+/// for `reason` or nothing changed since the last one. Returns the new
+/// version id, if any.
+pub async fn snapshot_page(
+    supabase_client: &RestClient,
+    page_id: &str,
+    reason: SnapshotReason,
+) -> Result<Option<i64>, String> {
+    let session = current_session()?;
+    let latest = supabase_client
+        .select(
+            "page_versions",
+            &format!(
+                "select=created_at,content_hash&page_id=eq.{}&order=created_at.desc&limit=1",
+                encode(page_id)
+            ),
+        )
+        .await?;
+    let latest = latest.first();
+
+    let recent = latest
+        .and_then(|row| row["created_at"].as_str())
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .is_some_and(|created_at| Utc::now() - created_at.with_timezone(&Utc) < reason.min_interval());
+    if recent {
+        return Ok(None);
+    }
+
+    let Some(content) = current_content(supabase_client, page_id).await? else {
+        return Ok(None);
+    };
+    let hash = content.hash();
+    if latest.and_then(|row| row["content_hash"].as_str()) == Some(hash.as_str()) {
+        return Ok(None);
+    }
+
+    let rows = supabase_client
+        .insert(
+            "page_versions",
+            json!({
+                "page_id": page_id,
+                "user_id": session.user_id,
+                "reason": reason.as_str(),
+                "title": content.title,
+                "blocks": content.blocks,
+                "block_count": content.blocks.len(),
+                "content_hash": hash,
+            }),
+        )
+        .await?;
+    let version_id = rows.first().and_then(|row| id_from(&row["id"]));
+    tracing::debug!(%page_id, reason = reason.as_str(), ?version_id, "page snapshot taken");
+
+    if let Err(e) = prune_versions(supabase_client, page_id).await {
+        tracing::warn!(%page_id, error = %e, "failed to prune page versions");
+    }
+    Ok(version_id)
+}
+
+/// `snapshot_page` for callers about to change a page: a failed snapshot is
+/// logged and never blocks the change itself.
+pub async fn snapshot_before_change(supabase_client: &RestClient, page_id: &str, reason: SnapshotReason) {
+    if let Err(e) = snapshot_page(supabase_client, page_id, reason).await {
+        tracing::warn!(%page_id, error = %e, "failed to snapshot page");
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockChange {
+    pub before: VersionBlock,
+    pub after: VersionBlock,
+    /// Which of content, type, order and parent_block_id changed.
+    pub fields: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageDiff {
+    pub title: Option<(String, String)>,
+    pub added: Vec<VersionBlock>,
+    pub removed: Vec<VersionBlock>,
+    pub changed: Vec<BlockChange>,
+    pub unchanged: usize,
+}
+
+/// Compares two versions block by block, matching blocks by id.
+pub fn diff_content(before: &PageContent, after: &PageContent) -> PageDiff {
+    let old: HashMap<i64, &VersionBlock> = before.blocks.iter().map(|b| (b.id, b)).collect();
+    let new: HashMap<i64, &VersionBlock> = after.blocks.iter().map(|b| (b.id, b)).collect();
+
+    let mut diff = PageDiff {
+        title: (before.title != after.title).then(|| (before.title.clone(), after.title.clone())),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+        unchanged: 0,
+    };
+
+    for block in &after.blocks {
+        let Some(old_block) = old.get(&block.id) else {
+            diff.added.push(block.clone());
+            continue;
+        };
+        let mut fields = Vec::new();
+        if old_block.content != block.content {
+            fields.push("content");
+        }
+        if old_block.block_type != block.block_type {
+            fields.push("type");
+        }
+        if old_block.order != block.order {
+            fields.push("order");
+        }
+        if old_block.parent_block_id != block.parent_block_id {
+            fields.push("parent_block_id");
+        }
+        if fields.is_empty() {
+            diff.unchanged += 1;
+        } else {
+            diff.changed.push(BlockChange {
+                before: (*old_block).clone(),
+                after: block.clone(),
+                fields,
+            });
+        }
+    }
+    diff.removed = before
+        .blocks
+        .iter()
+        .filter(|block| !new.contains_key(&block.id))
+        .cloned()
+        .collect();
+    diff
+}
+
+fn version_not_found() -> Response<Value> {
+    Response {
+        status: StatusCode::NotFound,
+        data: None,
+        error: Some("Version not found".to_string()),
+    }
+}
+
+/// The snapshots of a page, newest first, without their blocks.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(page_id = %page_id))]
+pub async fn list_page_versions(page_id: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let rows = supabase_client
+        .select(
+            "page_versions",
+            &format!(
+                "select={}&page_id=eq.{}&order=created_at.desc",
+                VERSION_SUMMARY_COLUMNS,
+                encode(&page_id)
+            ),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(rows)),
+        error: None,
+    })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(version_id))]
+pub async fn get_page_version(version_id: i64) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    match version_content(&supabase_client, version_id).await? {
+        Some((mut row, content)) => {
+            row["blocks"] = json!(content.blocks);
+            Ok(Response {
+                status: StatusCode::Ok,
+                data: Some(row),
+                error: None,
+            })
+        }
+        None => Ok(version_not_found()),
+    }
+}
+
+/// Block-level differences from `from_version_id` to `to_version_id`, or to
+/// the page as it is now when `to_version_id` is `None`.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(from_version_id, to_version_id = ?to_version_id))]
+pub async fn diff_page_versions(
+    from_version_id: i64,
+    to_version_id: Option<i64>,
+) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+
+    let Some((from_row, before)) = version_content(&supabase_client, from_version_id).await? else {
+        return Ok(version_not_found());
+    };
+    let after = match to_version_id {
+        Some(to_version_id) => match version_content(&supabase_client, to_version_id).await? {
+            Some((to_row, content)) if to_row["page_id"] == from_row["page_id"] => content,
+            Some(_) => {
+                return Ok(Response {
+                    status: StatusCode::BadRequest,
+                    data: None,
+                    error: Some("Versions belong to different pages".to_string()),
+                })
+            }
+            None => return Ok(version_not_found()),
+        },
+        None => {
+            let page_id = from_row["page_id"].as_str().unwrap_or_default();
+            match current_content(&supabase_client, page_id).await? {
+                Some(content) => content,
+                None => return Ok(version_not_found()),
+            }
+        }
+    };
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(diff_content(&before, &after))),
+        error: None,
+    })
+}
+
+/// Replaces the page's title and blocks with a saved version. The current
+/// state is snapshotted first, so a restore can itself be undone.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(version_id))]
+pub async fn restore_page_version(version_id: i64) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+
+    let Some((row, content)) = version_content(&supabase_client, version_id).await? else {
+        return Ok(version_not_found());
+    };
+    let page_id = row["page_id"].as_str().unwrap_or_default().to_string();
+    if !page_role(&supabase_client, &page_id)
+        .await?
+        .is_some_and(|role| role.can_edit())
+    {
+        return Ok(forbidden());
+    }
+
+    snapshot_page(&supabase_client, &page_id, SnapshotReason::Restore).await?;
+
+    // The title and blocks are swapped in one transaction, which checks the
+    // role again
+    supabase_client
+        .rpc("restore_page_version", json!({ "p_version_id": version_id }))
+        .await?;
+
+    let blocks: Vec<Value> = content
+        .blocks
+        .iter()
+        .map(|block| json!({ "id": block.id, "page_id": page_id, "content": block.content }))
+        .collect();
+    if let Err(e) = index_links(&supabase_client, &blocks).await {
+        tracing::warn!(error = %e, "failed to index page links");
+    }
+
+    // Embeddings aren't tied to blocks by a foreign key; drop the ones for
+    // blocks that no longer exist and re-embed the restored content
+    let kept: Vec<String> = content.blocks.iter().map(|block| block.id.to_string()).collect();
+    let stale_filter = if kept.is_empty() {
+        String::new()
+    } else {
+        format!("&block_id=not.{}", ids_filter(&kept))
+    };
+    supabase_client
+        .delete("embeddings", &format!("page_id=eq.{}{}", encode(&page_id), stale_filter))
+        .await?;
+    queue_indexing(
+        content
+            .blocks
+            .iter()
+            .filter(|block| block.block_type != "image")
+            .map(|block| IndexJob {
+                block_id: block.id.to_string(),
+                page_id: page_id.clone(),
+                content: block.content.clone(),
+                block_type: block.block_type.clone(),
+            })
+            .collect(),
+        session.user_id,
+    );
+
+    tracing::info!(%page_id, blocks = content.blocks.len(), "page version restored");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "page_id": page_id, "title": content.title, "blocks": content.blocks })),
+        error: None,
+    })
+}
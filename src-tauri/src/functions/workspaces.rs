@@ -0,0 +1,347 @@
+use crate::functions::responses::{Response, StatusCode};
+use crate::functions::session::current_session;
+use crate::functions::supabase::initialize_user_client;
+use crate::supabase::rest::RestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use urlencoding::encode;
+
+const MAX_WORKSPACE_NAME_LENGTH: usize = 100;
+
+/// Roles that can be given when inviting; `owner` only changes hands
+/// through `transfer_workspace_ownership`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Admin,
+    Member,
+    Guest,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub owner_id: String,
+    pub personal: bool,
+    pub created_at: String,
+    pub role: String,
+    pub active: bool,
+}
+
+/// The workspace new pages go to and that `fetch_pages` and search are
+/// scoped to. Falls back to the user's personal workspace, creating it the
+/// first time.
+pub async fn active_workspace_id(client: &RestClient) -> Result<String, String> {
+    let id = client.rpc("active_workspace_id", json!({})).await?;
+    id.as_str()
+        .map(|id| id.to_string())
+        .ok_or_else(|| "No active workspace".to_string())
+}
+
+/// The caller's role in a workspace, or `None` if they aren't a member.
+async fn workspace_role(client: &RestClient, workspace_id: &str) -> Result<Option<String>, String> {
+    let role = client
+        .rpc_read("workspace_role", json!({ "p_workspace_id": workspace_id }))
+        .await?;
+    Ok(role.as_str().map(|role| role.to_string()))
+}
+
+async fn is_personal(client: &RestClient, workspace_id: &str) -> Result<bool, String> {
+    let rows = client
+        .select(
+            "workspaces",
+            &format!("select=personal&id=eq.{}", encode(workspace_id)),
+        )
+        .await?;
+    Ok(rows.first().and_then(|row| row["personal"].as_bool()).unwrap_or(false))
+}
+
+fn respond(status: StatusCode, error: &str) -> Response<Value> {
+    Response {
+        status,
+        data: None,
+        error: Some(error.to_string()),
+    }
+}
+
+fn workspace_not_found() -> Response<Value> {
+    respond(StatusCode::NotFound, "Workspace not found")
+}
+
+/// The workspaces the user belongs to, personal first, with their role and
+/// which one is active.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn list_workspaces() -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    let active = active_workspace_id(&supabase_client).await?;
+    let rows = supabase_client
+        .select(
+            "workspace_members",
+            &format!(
+                "select=role,workspaces!inner(id,name,owner_id,personal,created_at)&user_id=eq.{}",
+                session.user_id
+            ),
+        )
+        .await?;
+
+    let mut workspaces: Vec<Workspace> = rows
+        .iter()
+        .map(|row| {
+            let workspace = &row["workspaces"];
+            let id = workspace["id"].as_str().unwrap_or_default().to_string();
+            Workspace {
+                active: id == active,
+                id,
+                name: workspace["name"].as_str().unwrap_or_default().to_string(),
+                owner_id: match &workspace["owner_id"] {
+                    Value::String(id) => id.clone(),
+                    other => other.to_string(),
+                },
+                personal: workspace["personal"].as_bool().unwrap_or(false),
+                created_at: workspace["created_at"].as_str().unwrap_or_default().to_string(),
+                role: row["role"].as_str().unwrap_or_default().to_string(),
+            }
+        })
+        .collect();
+    workspaces.sort_by(|a, b| {
+        b.personal
+            .cmp(&a.personal)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!(workspaces)),
+        error: None,
+    })
+}
+
+/// Creates a team workspace owned by the user. It doesn't become active
+/// until `set_active_workspace` is called.
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn create_workspace(name: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_WORKSPACE_NAME_LENGTH {
+        return Ok(respond(
+            StatusCode::BadRequest,
+            &format!(
+                "Workspace names must be 1 to {} characters",
+                MAX_WORKSPACE_NAME_LENGTH
+            ),
+        ));
+    }
+
+    let supabase_client = initialize_user_client().await?;
+    let workspace = supabase_client
+        .rpc("create_workspace", json!({ "p_name": name }))
+        .await?;
+    tracing::debug!(workspace_id = ?workspace["id"], "workspace created");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(workspace),
+        error: None,
+    })
+}
+
+/// Switches the workspace that pages are listed, searched and created in.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(workspace_id = %workspace_id))]
+pub async fn set_active_workspace(workspace_id: String) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    if workspace_role(&supabase_client, &workspace_id).await?.is_none() {
+        return Ok(workspace_not_found());
+    }
+
+    supabase_client
+        .upsert(
+            "active_workspaces",
+            "user_id",
+            json!({
+                "user_id": session.user_id,
+                "workspace_id": workspace_id,
+                "updated_at": chrono::Utc::now().to_rfc3339(),
+            }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "workspace_id": workspace_id, "active": true })),
+        error: None,
+    })
+}
+
+/// Adds the user with `email` to a team workspace, or changes their role if
+/// they already belong to it. Admins can invite members and guests; only
+/// the owner can add admins.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(workspace_id = %workspace_id, role = ?role))]
+pub async fn invite_workspace_member(
+    workspace_id: String,
+    email: String,
+    role: WorkspaceRole,
+) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    match workspace_role(&supabase_client, &workspace_id).await?.as_deref() {
+        Some("owner") => {}
+        Some("admin") if role != WorkspaceRole::Admin => {}
+        Some("admin") => {
+            return Ok(respond(StatusCode::Unauthorized, "Only the owner can add admins"))
+        }
+        Some(_) => {
+            return Ok(respond(
+                StatusCode::Unauthorized,
+                "Only owners and admins can invite members",
+            ))
+        }
+        None => return Ok(workspace_not_found()),
+    }
+    if is_personal(&supabase_client, &workspace_id).await? {
+        return Ok(respond(
+            StatusCode::BadRequest,
+            "Personal workspaces can't be shared",
+        ));
+    }
+
+    let member = supabase_client
+        .rpc(
+            "invite_workspace_member",
+            json!({
+                "p_workspace_id": workspace_id,
+                "p_email": email.trim(),
+                "p_role": role,
+            }),
+        )
+        .await?;
+    if member.is_null() {
+        return Ok(respond(StatusCode::NotFound, "No user with that email"));
+    }
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(member),
+        error: None,
+    })
+}
+
+/// Everyone in a workspace, owner first.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(workspace_id = %workspace_id))]
+pub async fn list_workspace_members(workspace_id: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    if workspace_role(&supabase_client, &workspace_id).await?.is_none() {
+        return Ok(workspace_not_found());
+    }
+
+    let members = supabase_client
+        .rpc_read(
+            "list_workspace_members",
+            json!({ "p_workspace_id": workspace_id }),
+        )
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(members),
+        error: None,
+    })
+}
+
+/// Makes another member the owner of a team workspace. The current owner
+/// becomes an admin.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(workspace_id = %workspace_id, user_id = %user_id))]
+pub async fn transfer_workspace_ownership(
+    workspace_id: String,
+    user_id: String,
+) -> Result<Response<Value>, String> {
+    let session = current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    match workspace_role(&supabase_client, &workspace_id).await?.as_deref() {
+        Some("owner") => {}
+        Some(_) => {
+            return Ok(respond(
+                StatusCode::Unauthorized,
+                "Only the owner can transfer a workspace",
+            ))
+        }
+        None => return Ok(workspace_not_found()),
+    }
+    if is_personal(&supabase_client, &workspace_id).await? {
+        return Ok(respond(
+            StatusCode::BadRequest,
+            "Personal workspaces can't be transferred",
+        ));
+    }
+    if user_id == session.user_id {
+        return Ok(respond(StatusCode::BadRequest, "You already own this workspace"));
+    }
+    let Ok(new_owner) = user_id.parse::<i64>() else {
+        return Ok(respond(StatusCode::BadRequest, "Invalid user id"));
+    };
+    let member = supabase_client
+        .select(
+            "workspace_members",
+            &format!(
+                "select=user_id&workspace_id=eq.{}&user_id=eq.{}",
+                encode(&workspace_id),
+                new_owner
+            ),
+        )
+        .await?;
+    if member.is_empty() {
+        return Ok(respond(StatusCode::BadRequest, "The new owner must be a member"));
+    }
+
+    supabase_client
+        .rpc(
+            "transfer_workspace_ownership",
+            json!({ "p_workspace_id": workspace_id, "p_user_id": new_owner }),
+        )
+        .await?;
+    tracing::info!("workspace ownership transferred");
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "workspace_id": workspace_id, "owner_id": user_id })),
+        error: None,
+    })
+}
+
+/// Leaves a team workspace. Owners have to transfer it first. If it was
+/// the active workspace, the personal workspace becomes active.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(workspace_id = %workspace_id))]
+pub async fn leave_workspace(workspace_id: String) -> Result<Response<Value>, String> {
+    current_session()?;
+    let supabase_client = initialize_user_client().await?;
+    match workspace_role(&supabase_client, &workspace_id).await?.as_deref() {
+        Some("owner") => {
+            return Ok(respond(
+                StatusCode::BadRequest,
+                "Transfer ownership before leaving",
+            ))
+        }
+        Some(_) => {}
+        None => return Ok(workspace_not_found()),
+    }
+
+    supabase_client
+        .rpc("leave_workspace", json!({ "p_workspace_id": workspace_id }))
+        .await?;
+
+    Ok(Response {
+        status: StatusCode::Ok,
+        data: Some(json!({ "workspace_id": workspace_id, "left": true })),
+        error: None,
+    })
+}
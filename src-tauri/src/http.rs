@@ -0,0 +1,402 @@
+use rand::Rng;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The services the backend talks to. Each has its own policy and its own
+/// circuit breaker, so an OpenAI outage doesn't stop notes from loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Upstream {
+    SupabaseRest,
+    SupabaseStorage,
+    OpenAi,
+}
+
+impl Upstream {
+    fn env_prefix(self) -> &'static str {
+        match self {
+            Upstream::SupabaseRest => "ZENOTE_HTTP_SUPABASE",
+            Upstream::SupabaseStorage => "ZENOTE_HTTP_STORAGE",
+            Upstream::OpenAi => "ZENOTE_HTTP_OPENAI",
+        }
+    }
+
+    fn default_policy(self) -> Policy {
+        let base = Policy {
+            timeout: Duration::from_secs(15),
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            max_retry_after: Duration::from_secs(30),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+        };
+        match self {
+            Upstream::SupabaseRest => base,
+            Upstream::SupabaseStorage => Policy {
+                timeout: Duration::from_secs(60),
+                max_retries: 2,
+                ..base
+            },
+            Upstream::OpenAi => Policy {
+                timeout: Duration::from_secs(60),
+                base_delay: Duration::from_millis(500),
+                max_delay: Duration::from_secs(20),
+                max_retry_after: Duration::from_secs(60),
+                ..base
+            },
+        }
+    }
+
+    /// The default policy with any `ZENOTE_HTTP_<UPSTREAM>_*` overrides from
+    /// the environment applied, e.g. `ZENOTE_HTTP_OPENAI_TIMEOUT_MS=90000`.
+    pub fn policy(self) -> Policy {
+        static POLICIES: OnceLock<Mutex<HashMap<Upstream, Policy>>> = OnceLock::new();
+        let mut policies = POLICIES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *policies.entry(self).or_insert_with(|| {
+            dotenv::dotenv().ok();
+            let prefix = self.env_prefix();
+            let millis = |name: &str, default: Duration| {
+                env::var(format!("{}_{}", prefix, name))
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_millis)
+                    .unwrap_or(default)
+            };
+            let count = |name: &str, default: u32| {
+                env::var(format!("{}_{}", prefix, name))
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default)
+            };
+            let defaults = self.default_policy();
+            Policy {
+                timeout: millis("TIMEOUT_MS", defaults.timeout),
+                max_retries: count("MAX_RETRIES", defaults.max_retries),
+                base_delay: millis("BASE_DELAY_MS", defaults.base_delay),
+                max_delay: millis("MAX_DELAY_MS", defaults.max_delay),
+                max_retry_after: millis("MAX_RETRY_AFTER_MS", defaults.max_retry_after),
+                breaker_threshold: count("BREAKER_THRESHOLD", defaults.breaker_threshold),
+                breaker_cooldown: millis("BREAKER_COOLDOWN_MS", defaults.breaker_cooldown),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Policy {
+    /// Timeout for a single attempt.
+    pub timeout: Duration,
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Backoff is drawn from `0..=min(max_delay, base_delay * 2^attempt)`.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Longest `Retry-After` we are willing to wait out before giving up.
+    pub max_retry_after: Duration,
+    /// Consecutive failures that open the circuit.
+    pub breaker_threshold: u32,
+    /// How long an open circuit rejects requests before letting one through.
+    pub breaker_cooldown: Duration,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// When the trial request of a half-open circuit was let through.
+    probe_started: Option<Instant>,
+}
+
+fn breakers() -> &'static Mutex<HashMap<Upstream, Breaker>> {
+    static BREAKERS: OnceLock<Mutex<HashMap<Upstream, Breaker>>> = OnceLock::new();
+    BREAKERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Fails fast while the circuit is open. Once the cooldown has passed a
+/// single request goes through as a trial; its outcome closes or re-opens
+/// the circuit, and other requests fail fast until then. A trial that never
+/// reports back (e.g. its caller was dropped) stops counting after one
+/// timeout.
+fn check_breaker(upstream: Upstream, policy: &Policy) -> Result<(), String> {
+    let mut breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    let Some(breaker) = breakers.get_mut(&upstream) else {
+        return Ok(());
+    };
+    let Some(opened_at) = breaker.opened_at else {
+        return Ok(());
+    };
+    let elapsed = opened_at.elapsed();
+    if elapsed < policy.breaker_cooldown {
+        return Err(format!(
+            "{:?} is unavailable after repeated failures; retrying in {}s",
+            upstream,
+            (policy.breaker_cooldown - elapsed).as_secs().max(1)
+        ));
+    }
+    if breaker
+        .probe_started
+        .is_some_and(|started| started.elapsed() < policy.timeout)
+    {
+        return Err(format!(
+            "{:?} is unavailable after repeated failures; checking whether it has recovered",
+            upstream
+        ));
+    }
+    breaker.probe_started = Some(Instant::now());
+    Ok(())
+}
+
+fn record_success(upstream: Upstream) {
+    let mut breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    breakers.remove(&upstream);
+}
+
+fn record_failure(upstream: Upstream, policy: &Policy) {
+    let mut breakers = breakers().lock().unwrap_or_else(|e| e.into_inner());
+    let breaker = breakers.entry(upstream).or_default();
+    breaker.consecutive_failures += 1;
+    if breaker.consecutive_failures >= policy.breaker_threshold {
+        if breaker.opened_at.is_none() {
+            tracing::warn!(?upstream, failures = breaker.consecutive_failures, "circuit opened");
+        }
+        breaker.opened_at = Some(Instant::now());
+        breaker.probe_started = None;
+    }
+}
+
+fn backoff(policy: &Policy, attempt: u32) -> Duration {
+    let ceiling = policy
+        .base_delay
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(policy.max_delay);
+    let millis = ceiling.as_millis() as u64;
+    Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+}
+
+/// Parses `Retry-After` as either delay-seconds or an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    SystemTime::from(date).duration_since(SystemTime::now()).ok()
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// Sends `request` under the upstream's policy. GET/PUT/DELETE are retried
+/// on transport errors and 502/503/504; other methods only on 429, which
+/// guarantees the server did not act on the request.
+pub async fn send(upstream: Upstream, request: RequestBuilder) -> Result<Response, String> {
+    let idempotent = request
+        .try_clone()
+        .and_then(|r| r.build().ok())
+        .is_some_and(|r| is_idempotent(r.method()));
+    execute(upstream, request, idempotent).await
+}
+
+/// Like `send`, for POST/PATCH requests the caller knows are safe to repeat
+/// (upserts, read-only RPCs, embedding and completion calls).
+pub async fn send_idempotent(upstream: Upstream, request: RequestBuilder) -> Result<Response, String> {
+    execute(upstream, request, true).await
+}
+
+async fn execute(
+    upstream: Upstream,
+    request: RequestBuilder,
+    idempotent: bool,
+) -> Result<Response, String> {
+    let policy = upstream.policy();
+    check_breaker(upstream, &policy)?;
+
+    let mut attempt = 0;
+    loop {
+        let Some(current) = request.try_clone() else {
+            // Streaming bodies can't be cloned; those requests get one attempt
+            return single_attempt(upstream, &policy, request).await;
+        };
+        let retry = attempt < policy.max_retries;
+
+        let started = Instant::now();
+        let result = current.timeout(policy.timeout).send().await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let delay = match result {
+            Ok(response) => {
+                let status = response.status();
+                tracing::debug!(?upstream, status = status.as_u16(), latency_ms, attempt, "http response");
+
+                let retryable_status = status == StatusCode::TOO_MANY_REQUESTS
+                    || (idempotent
+                        && matches!(
+                            status,
+                            StatusCode::BAD_GATEWAY
+                                | StatusCode::SERVICE_UNAVAILABLE
+                                | StatusCode::GATEWAY_TIMEOUT
+                        ));
+
+                if status.is_server_error() {
+                    record_failure(upstream, &policy);
+                } else {
+                    record_success(upstream);
+                }
+
+                if !retryable_status || !retry {
+                    return Ok(response);
+                }
+
+                match retry_after(&response) {
+                    Some(wait) if wait > policy.max_retry_after => {
+                        tracing::warn!(?upstream, retry_after_s = wait.as_secs(), "retry-after exceeds policy");
+                        return Ok(response);
+                    }
+                    Some(wait) => wait,
+                    None => backoff(&policy, attempt),
+                }
+            }
+            Err(e) => {
+                record_failure(upstream, &policy);
+                tracing::warn!(?upstream, latency_ms, attempt, error = %e, "http request failed");
+                if !idempotent || !retry {
+                    return Err(if e.is_timeout() {
+                        format!("{:?} request timed out after {}s", upstream, policy.timeout.as_secs())
+                    } else {
+                        e.to_string()
+                    });
+                }
+                backoff(&policy, attempt)
+            }
+        };
+
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        check_breaker(upstream, &policy)?;
+    }
+}
+
+async fn single_attempt(
+    upstream: Upstream,
+    policy: &Policy,
+    request: RequestBuilder,
+) -> Result<Response, String> {
+    match request.timeout(policy.timeout).send().await {
+        Ok(response) => {
+            if response.status().is_server_error() {
+                record_failure(upstream, policy);
+            } else {
+                record_success(upstream);
+            }
+            Ok(response)
+        }
+        Err(e) => {
+            record_failure(upstream, policy);
+            Err(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Policy {
+        Policy {
+            timeout: Duration::from_secs(60),
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            max_retry_after: Duration::from_secs(30),
+            breaker_threshold: 3,
+            breaker_cooldown: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_max_delay() {
+        let policy = policy();
+        for _ in 0..200 {
+            assert!(backoff(&policy, 0) <= Duration::from_millis(100));
+            assert!(backoff(&policy, 2) <= Duration::from_millis(400));
+            assert!(backoff(&policy, 5) <= policy.max_delay);
+            assert!(backoff(&policy, 40) <= policy.max_delay);
+        }
+        let zero = Policy {
+            base_delay: Duration::ZERO,
+            ..policy
+        };
+        assert_eq!(backoff(&zero, 3), Duration::ZERO);
+    }
+
+    // The only test touching `OpenAi`'s breaker, since breakers are shared
+    // by every test in the process.
+    #[test]
+    fn breaker_opens_after_repeated_failures_and_lets_one_trial_through() {
+        let upstream = Upstream::OpenAi;
+        let policy = policy();
+        record_success(upstream);
+
+        for _ in 0..policy.breaker_threshold - 1 {
+            record_failure(upstream, &policy);
+        }
+        assert!(check_breaker(upstream, &policy).is_ok());
+        record_failure(upstream, &policy);
+        assert!(check_breaker(upstream, &policy).is_err());
+
+        std::thread::sleep(policy.breaker_cooldown);
+        assert!(check_breaker(upstream, &policy).is_ok());
+        assert!(check_breaker(upstream, &policy).is_err());
+
+        // A failed trial re-opens the circuit, a successful one closes it.
+        record_failure(upstream, &policy);
+        assert!(check_breaker(upstream, &policy).is_err());
+        std::thread::sleep(policy.breaker_cooldown);
+        assert!(check_breaker(upstream, &policy).is_ok());
+        record_success(upstream);
+        assert!(check_breaker(upstream, &policy).is_ok());
+        assert!(check_breaker(upstream, &policy).is_ok());
+    }
+
+    #[test]
+    fn a_trial_that_never_reports_back_expires_after_a_timeout() {
+        let upstream = Upstream::SupabaseStorage;
+        let policy = Policy {
+            timeout: Duration::from_millis(20),
+            ..policy()
+        };
+        for _ in 0..policy.breaker_threshold {
+            record_failure(upstream, &policy);
+        }
+        std::thread::sleep(policy.breaker_cooldown);
+        assert!(check_breaker(upstream, &policy).is_ok());
+        assert!(check_breaker(upstream, &policy).is_err());
+        std::thread::sleep(policy.timeout);
+        assert!(check_breaker(upstream, &policy).is_ok());
+        record_success(upstream);
+    }
+
+    #[test]
+    fn only_safe_methods_are_idempotent() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(is_idempotent(&Method::DELETE));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+}
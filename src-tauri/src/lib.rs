@@ -1,4 +1,6 @@
 mod functions;
+mod http;
+mod logging;
 mod supabase;
 
 pub use supabase::update::update;
@@ -6,6 +8,8 @@ pub use supabase::update::update;
 use crate::functions::auth::check_if_email_exists;
 use crate::functions::auth::sign_up;
 use crate::functions::auth::sign_in;
+use crate::functions::auth::restore_session;
+use crate::functions::auth::sign_out;
 
 //storage
 use crate::functions::storage::save_temp_file;
@@ -15,6 +19,45 @@ use crate::functions::storage::upload_file;
 use crate::functions::pages::fetch_pages;
 use crate::functions::pages::fetch_page;
 use crate::functions::pages::update_page;
+use crate::functions::page_tree::fetch_page_tree;
+use crate::functions::page_tree::move_page;
+use crate::functions::page_tree::reorder_pages;
+use crate::functions::page_metadata::{list_tags, set_page_cover, update_page_metadata};
+use crate::functions::versions::{
+    diff_page_versions, get_page_version, list_page_versions, restore_page_version,
+};
+use crate::functions::sharing::{
+    fetch_shared_with_me, list_collaborators, revoke_access, share_page,
+};
+use crate::functions::share_links::{
+    create_share_link, disable_share_link, fetch_shared_page, list_share_links,
+};
+use crate::functions::workspaces::{
+    create_workspace, invite_workspace_member, leave_workspace, list_workspace_members,
+    list_workspaces, set_active_workspace, transfer_workspace_ownership,
+};
+use crate::functions::links::fetch_backlinks;
+use crate::functions::graph::fetch_graph;
+use crate::functions::databases::{
+    fetch_database, query_database, set_database_schema, set_property_value,
+};
+use crate::functions::board::{fetch_board, move_card};
+use crate::functions::calendar::{export_calendar_ics, fetch_calendar};
+use crate::functions::stats::{account_stats, page_stats};
+use crate::functions::database_views::{
+    create_database_view, delete_database_view, update_database_view,
+};
+use crate::functions::favorites::{
+    fetch_favorites, fetch_recent_pages, pin_page, record_page_visit, unpin_page,
+};
+use crate::functions::trash::{delete_page, empty_trash, list_trash, restore_page};
+use crate::functions::duplicate::duplicate_page;
+use crate::functions::templates::{
+    create_template, delete_template, instantiate_template, list_templates, update_template,
+};
+use crate::functions::daily_notes::{
+    get_daily_note_settings, list_daily_notes, open_daily_note, set_daily_note_settings,
+};
 
 
 //blocks
@@ -28,20 +71,99 @@ use crate::functions::embeddings::index_block;
 use crate::functions::embeddings::query_similar_blocks;
 use crate::functions::embeddings::ask_llm;
 
+//migrations
+use crate::functions::migrations::check_schema;
+
+//logs
+use crate::functions::logs::get_recent_logs;
+
+//diagnostics
+use crate::functions::diagnostics::diagnostics;
+
+use tauri::Manager;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            let log_dir = app.path().app_log_dir()?;
+            if let Err(e) = logging::init(log_dir) {
+                eprintln!("Failed to initialise logging: {}", e);
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             sign_up,
             check_if_email_exists,
             save_temp_file,
             upload_file,
             sign_in,
+            restore_session,
+            sign_out,
             fetch_pages,
             fetch_page,
             update_page,
+            fetch_page_tree,
+            move_page,
+            reorder_pages,
+            update_page_metadata,
+            set_page_cover,
+            list_tags,
+            pin_page,
+            unpin_page,
+            fetch_favorites,
+            record_page_visit,
+            fetch_recent_pages,
+            list_page_versions,
+            get_page_version,
+            diff_page_versions,
+            restore_page_version,
+            share_page,
+            list_collaborators,
+            revoke_access,
+            fetch_shared_with_me,
+            create_share_link,
+            list_share_links,
+            disable_share_link,
+            fetch_shared_page,
+            list_workspaces,
+            create_workspace,
+            set_active_workspace,
+            invite_workspace_member,
+            list_workspace_members,
+            transfer_workspace_ownership,
+            leave_workspace,
+            fetch_backlinks,
+            fetch_graph,
+            set_database_schema,
+            fetch_database,
+            set_property_value,
+            query_database,
+            create_database_view,
+            update_database_view,
+            delete_database_view,
+            fetch_board,
+            move_card,
+            fetch_calendar,
+            export_calendar_ics,
+            page_stats,
+            account_stats,
+            delete_page,
+            list_trash,
+            restore_page,
+            empty_trash,
+            duplicate_page,
+            list_templates,
+            create_template,
+            update_template,
+            delete_template,
+            instantiate_template,
+            open_daily_note,
+            list_daily_notes,
+            get_daily_note_settings,
+            set_daily_note_settings,
             fetch_blocks,
             update_block,
             create_block,
@@ -49,6 +171,9 @@ pub fn run() {
             index_block,
             query_similar_blocks,
             ask_llm,
+            check_schema,
+            get_recent_logs,
+            diagnostics,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,143 @@
+use regex::Regex;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+const LOG_FILE_PREFIX: &str = "zenote";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+static REDACTIONS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+
+/// Installs the global subscriber: daily-rotated files in `log_dir` (keeping
+/// the last week) plus stderr in debug builds. Both outputs pass through
+/// `redact`. The level comes from `ZENOTE_LOG`, e.g. `ZENOTE_LOG=debug`.
+pub fn init(log_dir: PathBuf) -> Result<(), String> {
+    fs::create_dir_all(&log_dir).map_err(|e| e.to_string())?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&log_dir)
+        .map_err(|e| e.to_string())?;
+    let (file_writer, guard) = tracing_appender::non_blocking(appender);
+
+    let default_level = if cfg!(debug_assertions) {
+        "warn,zenote_lib=debug"
+    } else {
+        "warn,zenote_lib=info"
+    };
+    let filter = EnvFilter::try_from_env("ZENOTE_LOG")
+        .unwrap_or_else(|_| EnvFilter::new(default_level));
+
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(Redacting(file_writer));
+    let stderr_layer = cfg!(debug_assertions).then(|| {
+        tracing_subscriber::fmt::layer().with_writer(Redacting(io::stderr))
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init()
+        .map_err(|e| e.to_string())?;
+
+    let _ = LOG_DIR.set(log_dir);
+    let _ = FILE_GUARD.set(guard);
+    Ok(())
+}
+
+pub fn log_dir() -> Option<&'static Path> {
+    LOG_DIR.get().map(PathBuf::as_path)
+}
+
+/// Log files written by `init`, oldest first.
+pub fn log_files() -> Result<Vec<PathBuf>, String> {
+    let dir = log_dir().ok_or("Logging has not been initialised")?;
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX))
+        })
+        .collect();
+    // Rotated files are suffixed with their date, so name order is age order
+    files.sort();
+    Ok(files)
+}
+
+/// Masks credentials and note text in a formatted log line. Secrets are
+/// matched by shape (JWTs, bearer tokens, OpenAI keys); note text by the
+/// field names commands use for it (`content`, `title`, `query`, ...).
+pub fn redact(line: &str) -> String {
+    let redactions = REDACTIONS.get_or_init(|| {
+        [
+            (r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*", "[jwt]"),
+            (r"(?i)bearer\s+[^\s,;]+", "Bearer [redacted]"),
+            (r"sk-[A-Za-z0-9_-]{8,}", "[openai-key]"),
+            (
+                r"(?i)\b(apikey|api_key|password|secret|token)\s*[=:]\s*[^\s,;]+",
+                "$1=[redacted]",
+            ),
+            (
+                r#"\b(content|context|query|prompt|system_content|messages|title|text|body)=("(?:[^"\\]|\\.)*"|[^\s,}]+)"#,
+                "$1=[redacted]",
+            ),
+            (
+                r#""(content|context|query|prompt|title|text|password)"\s*:\s*"(?:[^"\\]|\\.)*""#,
+                r#""$1":"[redacted]""#,
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+        .collect()
+    });
+
+    let mut redacted = line.to_string();
+    for (pattern, replacement) in redactions {
+        redacted = pattern.replace_all(&redacted, *replacement).into_owned();
+    }
+    redacted
+}
+
+/// Wraps a `MakeWriter` so every formatted event is passed through `redact`
+/// before it reaches the underlying writer.
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    // The fmt layer writes each event with a single call, so redacting per
+    // buffer sees whole lines.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&line).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,193 @@
+use crate::http::{self, Upstream};
+use reqwest::Client;
+use serde_json::Value;
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// Keep this list in version order; the SQL lives in src-tauri/migrations.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("../../migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "storage_policies",
+        sql: include_str!("../../migrations/0002_storage_policies.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "page_positions",
+        sql: include_str!("../../migrations/0003_page_positions.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "trash",
+        sql: include_str!("../../migrations/0004_trash.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "templates",
+        sql: include_str!("../../migrations/0005_templates.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "daily_notes",
+        sql: include_str!("../../migrations/0006_daily_notes.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "page_metadata",
+        sql: include_str!("../../migrations/0007_page_metadata.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "favorites_and_recents",
+        sql: include_str!("../../migrations/0008_favorites_and_recents.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "page_listing",
+        sql: include_str!("../../migrations/0009_page_listing.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "page_versions",
+        sql: include_str!("../../migrations/0010_page_versions.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "page_shares",
+        sql: include_str!("../../migrations/0011_page_shares.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "share_links",
+        sql: include_str!("../../migrations/0012_share_links.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "workspaces",
+        sql: include_str!("../../migrations/0013_workspaces.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "page_links",
+        sql: include_str!("../../migrations/0014_page_links.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "databases",
+        sql: include_str!("../../migrations/0015_databases.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "board_cards",
+        sql: include_str!("../../migrations/0016_board_cards.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "restore_page_version",
+        sql: include_str!("../../migrations/0017_restore_page_version.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "sign_in",
+        sql: include_str!("../../migrations/0018_sign_in.sql"),
+    },
+    Migration {
+        version: 19,
+        name: "access_tokens",
+        sql: include_str!("../../migrations/0019_access_tokens.sql"),
+    },
+    Migration {
+        version: 20,
+        name: "page_images",
+        sql: include_str!("../../migrations/0020_page_images.sql"),
+    },
+    Migration {
+        version: 21,
+        name: "private_images",
+        sql: include_str!("../../migrations/0021_private_images.sql"),
+    },
+    Migration {
+        version: 22,
+        name: "purge_trash_search_path",
+        sql: include_str!("../../migrations/0022_purge_trash_search_path.sql"),
+    },
+    Migration {
+        version: 23,
+        name: "restore_trashed_pages",
+        sql: include_str!("../../migrations/0023_restore_trashed_pages.sql"),
+    },
+    Migration {
+        version: 24,
+        name: "workspace_page_listing",
+        sql: include_str!("../../migrations/0024_workspace_page_listing.sql"),
+    },
+    Migration {
+        version: 25,
+        name: "page_editor_updates",
+        sql: include_str!("../../migrations/0025_page_editor_updates.sql"),
+    },
+    Migration {
+        version: 26,
+        name: "page_workspace_rules",
+        sql: include_str!("../../migrations/0026_page_workspace_rules.sql"),
+    },
+    Migration {
+        version: 27,
+        name: "page_owner_membership",
+        sql: include_str!("../../migrations/0027_page_owner_membership.sql"),
+    },
+    Migration {
+        version: 28,
+        name: "page_position_parents",
+        sql: include_str!("../../migrations/0028_page_position_parents.sql"),
+    },
+];
+
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Returns the versions recorded in `schema_migrations`, or `None` when the
+/// table does not exist yet (the database was never bootstrapped).
+pub async fn applied_versions(
+    supabase_url: &str,
+    supabase_key: &str,
+) -> Result<Option<Vec<i32>>, String> {
+    let endpoint = format!(
+        "{}/rest/v1/schema_migrations?select=version&order=version.asc",
+        supabase_url
+    );
+
+    let request = Client::new()
+        .get(&endpoint)
+        .header("apikey", supabase_key)
+        .header("Authorization", format!("Bearer {}", supabase_key));
+    let response = http::send(Upstream::SupabaseRest, request).await?;
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+
+    // PostgREST answers 404 (PGRST205) for tables missing from its schema cache.
+    if status.as_u16() == 404 || body.contains("PGRST205") || body.contains("42P01") {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(format!("Error {}: {}", status, body));
+    }
+
+    let rows: Vec<Value> = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    Ok(Some(
+        rows.iter()
+            .filter_map(|row| row["version"].as_i64().map(|v| v as i32))
+            .collect(),
+    ))
+}
@@ -1 +1,3 @@
-pub mod update;
\ No newline at end of file
+pub mod update;
+pub mod migrations;
+pub mod rest;
\ No newline at end of file
@@ -0,0 +1,253 @@
+use crate::http::{self, Upstream};
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Minimal PostgREST/Storage client that, unlike `supabase_rs`, lets the
+/// Authorization header carry a different token than `apikey`. Requests made
+/// with a user-scoped JWT are subject to the database's RLS policies.
+#[derive(Clone)]
+pub struct RestClient {
+    url: String,
+    api_key: String,
+    access_token: String,
+    client: Client,
+}
+
+impl RestClient {
+    pub fn new(supabase_url: String, api_key: String, access_token: String) -> Self {
+        Self {
+            url: supabase_url,
+            api_key,
+            access_token,
+            client: Client::new(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header("apikey", &self.api_key)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+    }
+
+    async fn rows(request: RequestBuilder, idempotent: bool) -> Result<Vec<Value>, String> {
+        match Self::send(request, idempotent).await? {
+            Value::Array(rows) => Ok(rows),
+            Value::Null => Ok(Vec::new()),
+            other => Ok(vec![other]),
+        }
+    }
+
+    async fn send(request: RequestBuilder, idempotent: bool) -> Result<Value, String> {
+        let response = if idempotent {
+            http::send_idempotent(Upstream::SupabaseRest, request).await?
+        } else {
+            http::send(Upstream::SupabaseRest, request).await?
+        };
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+
+        if !status.is_success() {
+            return Err(format!("Error {}: {}", status, body));
+        }
+        if body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    }
+
+    /// `GET /rest/v1/{table}?{query}` where `query` is a PostgREST query
+    /// string such as `select=*&user_id=eq.1`.
+    pub async fn select(&self, table: &str, query: &str) -> Result<Vec<Value>, String> {
+        let endpoint = format!("{}/rest/v1/{}?{}", self.url, table, query);
+        Self::rows(self.authorize(self.client.get(&endpoint)), true).await
+    }
+
+    /// Inserts one row (or an array of rows) and returns the inserted rows.
+    pub async fn insert(&self, table: &str, body: Value) -> Result<Vec<Value>, String> {
+        let endpoint = format!("{}/rest/v1/{}", self.url, table);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .header("Prefer", "return=representation")
+            .json(&body);
+        Self::rows(request, false).await
+    }
+
+    /// Inserts or merges rows on the `on_conflict` columns.
+    pub async fn upsert(
+        &self,
+        table: &str,
+        on_conflict: &str,
+        body: Value,
+    ) -> Result<Vec<Value>, String> {
+        let endpoint = format!("{}/rest/v1/{}?on_conflict={}", self.url, table, on_conflict);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .header("Prefer", "resolution=merge-duplicates,return=representation")
+            .json(&body);
+        Self::rows(request, true).await
+    }
+
+    /// `PATCH`es every row matching `query` and returns the updated rows.
+    pub async fn update(&self, table: &str, query: &str, body: Value) -> Result<Vec<Value>, String> {
+        let endpoint = format!("{}/rest/v1/{}?{}", self.url, table, query);
+        let request = self
+            .authorize(self.client.patch(&endpoint))
+            .header("Content-Type", "application/json")
+            .header("Prefer", "return=representation")
+            .json(&body);
+        Self::rows(request, false).await
+    }
+
+    /// Deletes every row matching `query` and returns the deleted rows.
+    pub async fn delete(&self, table: &str, query: &str) -> Result<Vec<Value>, String> {
+        let endpoint = format!("{}/rest/v1/{}?{}", self.url, table, query);
+        let request = self
+            .authorize(self.client.delete(&endpoint))
+            .header("Prefer", "return=representation");
+        Self::rows(request, true).await
+    }
+
+    /// Calls a Postgres function exposed under `/rest/v1/rpc`.
+    pub async fn rpc(&self, function: &str, body: Value) -> Result<Value, String> {
+        let endpoint = format!("{}/rest/v1/rpc/{}", self.url, function);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .json(&body);
+        Self::send(request, false).await
+    }
+
+    /// Like `rpc`, for functions without side effects, which may be retried.
+    pub async fn rpc_read(&self, function: &str, body: Value) -> Result<Value, String> {
+        let endpoint = format!("{}/rest/v1/rpc/{}", self.url, function);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .json(&body);
+        Self::send(request, true).await
+    }
+
+    /// Copies an object within a Storage bucket.
+    pub async fn copy_object(&self, bucket: &str, from: &str, to: &str) -> Result<(), String> {
+        let endpoint = format!("{}/storage/v1/object/copy", self.url);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({
+                "bucketId": bucket,
+                "sourceKey": from,
+                "destinationKey": to,
+            }));
+        let response = http::send(Upstream::SupabaseStorage, request).await?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().await.unwrap_or_default();
+            Err(format!("Copy failed: {}", text))
+        }
+    }
+
+    /// A URL that reads `path` from `bucket` for `expires_in` seconds, for
+    /// callers allowed to read the object.
+    pub async fn sign_object(&self, bucket: &str, path: &str, expires_in: u64) -> Result<String, String> {
+        let endpoint = format!("{}/storage/v1/object/sign/{}/{}", self.url, bucket, path);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({ "expiresIn": expires_in }));
+        let response = http::send_idempotent(Upstream::SupabaseStorage, request).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("Signing failed with {}: {}", status, text));
+        }
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        match body["signedURL"].as_str() {
+            Some(signed) => Ok(format!("{}/storage/v1{}", self.url, signed)),
+            None => Err(format!("Signing failed: {}", body)),
+        }
+    }
+
+    /// Signed URLs for several objects in one request, by path. Objects the
+    /// caller can't read are left out.
+    pub async fn sign_objects(
+        &self,
+        bucket: &str,
+        paths: &[String],
+        expires_in: u64,
+    ) -> Result<HashMap<String, String>, String> {
+        let endpoint = format!("{}/storage/v1/object/sign/{}", self.url, bucket);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({ "expiresIn": expires_in, "paths": paths }));
+        let response = http::send_idempotent(Upstream::SupabaseStorage, request).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("Signing failed with {}: {}", status, text));
+        }
+        let body: Vec<Value> = response.json().await.map_err(|e| e.to_string())?;
+        Ok(body
+            .iter()
+            .filter_map(|item| {
+                let path = item["path"].as_str()?;
+                let signed = item["signedURL"].as_str()?;
+                Some((path.to_string(), format!("{}/storage/v1{}", self.url, signed)))
+            })
+            .collect())
+    }
+
+    /// Deletes objects from a Storage bucket by path.
+    pub async fn delete_objects(&self, bucket: &str, paths: &[String]) -> Result<(), String> {
+        let endpoint = format!("{}/storage/v1/object/{}", self.url, bucket);
+        let request = self
+            .authorize(self.client.delete(&endpoint))
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({ "prefixes": paths }));
+        let response = http::send_idempotent(Upstream::SupabaseStorage, request).await?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            let text = response.text().await.unwrap_or_default();
+            Err(format!("Delete failed with {}: {}", status, text))
+        }
+    }
+
+    /// Uploads an object to Supabase Storage and returns the raw response text.
+    pub async fn upload_object(
+        &self,
+        bucket: &str,
+        path: &str,
+        content_type: String,
+        bytes: Vec<u8>,
+    ) -> Result<String, String> {
+        let endpoint = format!("{}/storage/v1/object/{}/{}", self.url, bucket, path);
+        let request = self
+            .authorize(self.client.post(&endpoint))
+            .header("Content-Type", content_type)
+            .body(bytes);
+        let response = http::send(Upstream::SupabaseStorage, request).await?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            Err(format!("Upload failed: {}", text))
+        }
+    }
+}
@@ -1,9 +1,11 @@
+use crate::http::{self, Upstream};
 use reqwest::Client;
 use serde_json::Value;
 
 pub async fn update(
     supabase_url: &str,
     supabase_key: &str,
+    access_token: &str,
     table_name: &str,
     column_name: &str,
     value: &str,
@@ -19,14 +21,13 @@ pub async fn update(
     let client = Client::new();
 
     // Send the PUT request
-    let response = client
+    let request = client
         .put(&endpoint)
         .header("apikey", supabase_key)
-        .header("Authorization", format!("Bearer {}", supabase_key))
+        .header("Authorization", format!("Bearer {}", access_token))
         .header("Content-Type", "application/json")
-        .json(&body_to_update)
-        .send()
-        .await;
+        .json(&body_to_update);
+    let response = http::send(Upstream::SupabaseRest, request).await;
 
     // Debugging response
     match response {
@@ -39,6 +40,6 @@ pub async fn update(
                 Err(format!("Error {}: {}", status, error_body))
             }
         }
-        Err(err) => Err(err.to_string()),
+        Err(err) => Err(err),
     }
 }
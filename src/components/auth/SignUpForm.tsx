@@ -60,8 +60,6 @@ const SignUpForm = () => {
           bucket: "avatars",
           path: `${avatarFile.name}`,
           filePath,
-          supabaseUrl: import.meta.env.VITE_SUPABASE_URL,
-          supabaseKey: import.meta.env.VITE_SUPABASE_API_KEY,
           deleteAfterUpload: true,
         });
         const avatarPath: string = JSON.parse(response.response).Key;
@@ -59,23 +59,25 @@ const BlockSection = ({
           fileBytes: Array.from(bytes),
         });
 
-        const response: { response: string; success: boolean } = await invoke(
-          "upload_file",
-          {
-            bucket: "images",
-            path: uuidv4(),
-            filePath,
-            supabaseUrl: import.meta.env.VITE_SUPABASE_URL,
-            supabaseKey: import.meta.env.VITE_SUPABASE_API_KEY,
-            deleteAfterUpload: true,
-          }
-        );
+        const response: {
+          response: string;
+          success: boolean;
+          url: string;
+          signed_url: string;
+        } = await invoke("upload_file", {
+          bucket: "images",
+          path: uuidv4(),
+          filePath,
+          deleteAfterUpload: true,
+        });
 
-        const imagePath: string = JSON.parse(response.response).Key;
-        const imageUrl =
-          "https://gzliirrtmmdeumryfouh.supabase.co/storage/v1/object/public/" +
-          imagePath +
-          "?&height=auto&width=auto";
+        // The images bucket is private: the block stores the plain URL and
+        // shows the signed one
+        const imageUrl = response.url + "?&height=auto&width=auto";
+        const signedImageUrl =
+          response.signed_url +
+          (response.signed_url.includes("?") ? "&" : "?") +
+          "height=auto&width=auto";
 
         const newBlockData = {
           content: imageUrl,
@@ -114,7 +116,7 @@ const BlockSection = ({
                 : new Date().toISOString(),
             type: "image",
             order: blocksRef.current.length - 1,
-            content: imageUrl,
+            content: signedImageUrl,
             pageId: pageId,
             parentBlockId: null,
           };
@@ -23,8 +23,10 @@ const parseContent = (content: string): ImageContent => {
     const url = new URL(content);
     const width = url.searchParams.get("width") || "auto";
     const height = url.searchParams.get("height") || "auto";
-    console.log(url.origin + url.pathname)
-    return { url: url.origin + url.pathname, width, height };
+    // Images are shown through signed URLs, which need their token
+    const token = url.searchParams.get("token");
+    const base = url.origin + url.pathname;
+    return { url: token ? `${base}?token=${encodeURIComponent(token)}` : base, width, height };
   } catch {
     return { url: content, width: "auto", height: "auto" };
   }
@@ -213,7 +215,7 @@ const ImageBlock = ({ id, content = "", index, blocks, setBlocks, pageId }: Opti
 <div className="relative inline-block">
       <img
         ref={imgRef}
-        src={`${imageUrl}?width=${dimensions.width}&height=${dimensions.height}`}
+        src={`${imageUrl}${imageUrl.includes("?") ? "&" : "?"}width=${dimensions.width}&height=${dimensions.height}`}
         alt=""
         style={{ width: dimensions.width, height: dimensions.height }}
         className="max-w-full"
@@ -27,10 +27,16 @@ export function AuthProvider({ children }: { children: ReactNode }) {
 
   useEffect(() => {
     const checkSession = async () => {
-      const token = sessionStorage.getItem("authToken");
+      let token = sessionStorage.getItem("authToken");
       if (token && !isTokenExpired(token)) {
+        const restored: Response<{ token: string }> = await invoke("restore_session", { token });
+        if (restored.status === StatusCode.Ok && restored.data?.token) {
+          // The backend hands back a fresh token for the same session
+          token = restored.data.token;
+          sessionStorage.setItem("authToken", token);
+        }
         const payload = decodeJwt(token);
-        if (payload) {
+        if (payload && restored.status === StatusCode.Ok) {
           setUser({
             email: payload.email,
             firstName: payload.first_name,
@@ -91,6 +97,7 @@ export function AuthProvider({ children }: { children: ReactNode }) {
   };
 
   const logout = () => {
+    invoke("sign_out");
     sessionStorage.removeItem("authToken");
     setUser(null);
   };
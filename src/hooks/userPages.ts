@@ -15,11 +15,20 @@ export const useUserPages = () => {
         setIsLoading(false);
         return;
       }
-      const response: Response = await invoke("fetch_pages", {
-        userId: user.id,
-      });
+      // fetch_pages is paginated; follow next_cursor until every page is loaded
+      let pages: PageProps[] = [];
+      let cursor: string | null = null;
+      do {
+        const response: Response = await invoke("fetch_pages", {
+          order: "manual",
+          limit: 200,
+          cursor,
+        });
+        if (!response.data) break;
+        pages = pages.concat(response.data.pages);
+        cursor = response.data.next_cursor;
+      } while (cursor);
 
-      const pages = response.data ? response.data : [];
       setUserPages(pages); // Only setting userPages from the response
       setIsLoading(false);
     };
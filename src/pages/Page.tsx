@@ -85,6 +85,9 @@ const Page = () => {
       
       // Existing page logic:
       setTitle(pageResponse.data.title);
+      invoke("record_page_visit", { pageId }).catch((error) =>
+        console.error("Failed to record page visit:", error)
+      );
       console.log("Page useEffect: Setting title to:", pageResponse.data.title);
 
       const blockResponse: Response = await invoke("fetch_blocks", {
@@ -60,6 +60,14 @@ declare type PageProps = {
   id: string;
   parent_page_id: string;
   profilePicUrl: string;
+  icon?: string | null;
+  cover_url?: string | null;
+  tags?: string[];
+  description?: string;
+  role?: "owner" | "editor" | "commenter" | "viewer";
+  workspace_id?: string | null;
+  is_database?: boolean;
+  properties?: Record<string, unknown>;
 };
 
 //------------------------------------------------Blocks------------------------------------------